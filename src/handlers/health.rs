@@ -25,7 +25,7 @@ pub async fn health() -> Json<HealthResponse> {
             EndpointInfo {
                 path: "/image".to_string(),
                 method: "POST".to_string(),
-                description: "Generate customized QR code as downloadable image".to_string(),
+                description: "Generate customized QR code as downloadable image (JSON, or multipart with logo and halftone uploads)".to_string(),
             },
         ],
     })
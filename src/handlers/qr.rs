@@ -1,13 +1,17 @@
 use axum::{
-    extract::{Query, State},
-    http::{HeaderMap, HeaderValue},
-    response::IntoResponse,
+    async_trait,
+    extract::{FromRequest, Multipart, Query, Request, State},
+    http::{header::CONTENT_TYPE, HeaderMap, HeaderValue},
+    response::{IntoResponse, Response},
     Json,
 };
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 
 use crate::{
     errors::ApiError,
-    models::{QrRequest, QrResponse, QrCustomization},
+    models::{
+        Gs1Data, Gs1Output, HalftoneOptions, LogoOptions, QrCustomization, QrRequest, QrResponse,
+    },
     services::QrService,
     state::AppState,
 };
@@ -25,19 +29,20 @@ pub async fn generate_qr_json(
     let qr_service = QrService::new((*app_state.config).clone());
     
     // Generate base64 QR code
-    let base64_qr = qr_service.generate_qr_base64(&request.url, &request.customization)?;
+    let customization = request.customization();
+    let (base64_qr, details) = qr_service.generate_qr_base64(&request.content(), &customization)?;
 
     // Create response
-    let response = QrResponse::new(base64_qr, &request.customization);
+    let response = QrResponse::new(base64_qr, &customization, details);
 
     Ok(Json(response))
 }
 
 /// Generate QR code with customization options (direct image download)
-/// POST /image
+/// POST /image (JSON body, or multipart form with logo and halftone uploads)
 pub async fn generate_qr_image(
     State(app_state): State<AppState>,
-    Json(request): Json<QrRequest>,
+    ImageRequest(request): ImageRequest,
 ) -> Result<impl IntoResponse, ApiError> {
     // Validate request
     request.validate(app_state.config.max_url_length)?;
@@ -46,7 +51,8 @@ pub async fn generate_qr_image(
     let qr_service = QrService::new((*app_state.config).clone());
     
     // Generate image data
-    let image_data = qr_service.generate_qr_code(&request.url, &request.customization)?;
+    let generated = qr_service.generate_qr_code(&request.content(), &request.customization())?;
+    let dimensions = &generated.details.dimensions;
 
     // Set appropriate headers
     let mut headers = HeaderMap::new();
@@ -57,8 +63,8 @@ pub async fn generate_qr_image(
     
     let filename = format!(
         "qrcode-{}x{}.{}",
-        request.customization.size.to_pixels(),
-        request.customization.size.to_pixels(),
+        dimensions.width,
+        dimensions.height,
         request.customization.format.file_extension()
     );
     
@@ -68,7 +74,15 @@ pub async fn generate_qr_image(
             .map_err(|e| ApiError::GenerationError(format!("Invalid filename: {}", e)))?,
     );
 
-    Ok((headers, image_data))
+    for warning in &generated.details.warnings {
+        headers.append(
+            "x-qr-warning",
+            HeaderValue::from_str(warning)
+                .map_err(|e| ApiError::GenerationError(format!("Invalid warning header: {}", e)))?,
+        );
+    }
+
+    Ok((headers, generated.data))
 }
 
 /// Generate QR code with query parameters (GET endpoint for compatibility)
@@ -77,13 +91,15 @@ pub async fn generate_qr_query(
     State(app_state): State<AppState>,
     Query(params): Query<QrQueryParams>,
 ) -> Result<Json<QrResponse>, ApiError> {
-    // Extract URL first to avoid partial move
+    // Extract URL and GS1 data first to avoid partial move
     let url = params.url.clone();
+    let gs1 = params.gs1_data()?;
     let customization = params.into_customization()?;
     
     // Convert query params to request
     let request = QrRequest {
         url,
+        gs1,
         customization,
     };
 
@@ -94,26 +110,183 @@ pub async fn generate_qr_query(
     let qr_service = QrService::new((*app_state.config).clone());
     
     // Generate base64 QR code
-    let base64_qr = qr_service.generate_qr_base64(&request.url, &request.customization)?;
+    let customization = request.customization();
+    let (base64_qr, details) = qr_service.generate_qr_base64(&request.content(), &customization)?;
 
     // Create response
-    let response = QrResponse::new(base64_qr, &request.customization);
+    let response = QrResponse::new(base64_qr, &customization, details);
 
     Ok(Json(response))
 }
 
+/// Body of `POST /image`: either a JSON `QrRequest`, or a multipart form
+/// with a `url` field or a `gs1` JSON field, an optional `customization`
+/// JSON field and optional `logo` and `halftone` image files.
+pub struct ImageRequest(pub QrRequest);
+
+#[async_trait]
+impl<S> FromRequest<S> for ImageRequest
+where
+    S: Send + Sync,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let is_multipart = req
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("multipart/form-data"));
+
+        if !is_multipart {
+            let Json(request) = Json::<QrRequest>::from_request(req, state)
+                .await
+                .map_err(IntoResponse::into_response)?;
+            return Ok(Self(request));
+        }
+
+        let multipart = Multipart::from_request(req, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        read_multipart(multipart)
+            .await
+            .map(Self)
+            .map_err(IntoResponse::into_response)
+    }
+}
+
+async fn read_multipart(mut multipart: Multipart) -> Result<QrRequest, ApiError> {
+    let invalid = |e: axum::extract::multipart::MultipartError| {
+        ApiError::ValidationError(format!("Invalid multipart body: {}", e))
+    };
+
+    let mut url = None;
+    let mut gs1 = None;
+    let mut customization = QrCustomization::default();
+    let mut logo = None;
+    let mut halftone = None;
+
+    while let Some(field) = multipart.next_field().await.map_err(invalid)? {
+        match field.name() {
+            Some("url") => url = Some(field.text().await.map_err(invalid)?),
+            Some("gs1") => {
+                let json = field.text().await.map_err(invalid)?;
+                gs1 =
+                    Some(serde_json::from_str(&json).map_err(|e| {
+                        ApiError::ValidationError(format!("Invalid gs1 JSON: {}", e))
+                    })?);
+            }
+            Some("customization") => {
+                let json = field.text().await.map_err(invalid)?;
+                customization = serde_json::from_str(&json).map_err(|e| {
+                    ApiError::ValidationError(format!("Invalid customization JSON: {}", e))
+                })?;
+            }
+            Some("logo") => logo = Some(field.bytes().await.map_err(invalid)?),
+            Some("halftone") => halftone = Some(field.bytes().await.map_err(invalid)?),
+            _ => {}
+        }
+    }
+
+    // The uploaded file fills in the logo data; sizing options may still
+    // come from the customization field
+    if let Some(bytes) = logo {
+        let options = customization.logo.get_or_insert_with(LogoOptions::default);
+        options.data = BASE64.encode(bytes);
+    }
+    if let Some(bytes) = halftone {
+        let options = customization
+            .halftone
+            .get_or_insert_with(HalftoneOptions::default);
+        options.image = BASE64.encode(bytes);
+    }
+
+    if url.is_none() && gs1.is_none() {
+        return Err(ApiError::ValidationError(
+            "Multipart request must include a 'url' or 'gs1' field".to_string(),
+        ));
+    }
+
+    Ok(QrRequest {
+        url: url.unwrap_or_default(),
+        gs1,
+        customization,
+    })
+}
+
 #[derive(serde::Deserialize)]
 pub struct QrQueryParams {
+    #[serde(default)]
     pub url: String,
+
+    #[serde(default)]
+    pub gtin: Option<String>,
+
+    #[serde(default)]
+    pub batch: Option<String>,
+
+    #[serde(default)]
+    pub expiry: Option<String>,
+
+    #[serde(default)]
+    pub serial: Option<String>,
+
+    #[serde(default)]
+    pub gs1: Option<String>,
+
+    #[serde(default)]
+    pub gs1_domain: Option<String>,
     
     #[serde(default)]
     pub size: Option<String>,
+
+    #[serde(default)]
+    pub dpi: Option<u32>,
     
     #[serde(default)]
     pub format: Option<String>,
     
     #[serde(default)]
     pub error_correction: Option<String>,
+
+    #[serde(default)]
+    pub symbology: Option<String>,
+
+    #[serde(default)]
+    pub version: Option<u8>,
+
+    #[serde(default)]
+    pub min_version: Option<u8>,
+
+    #[serde(default)]
+    pub mask: Option<u8>,
+
+    #[serde(default)]
+    pub rows: Option<u8>,
+
+    #[serde(default)]
+    pub columns: Option<u8>,
+
+    #[serde(default)]
+    pub max_rows: Option<u8>,
+
+    #[serde(default)]
+    pub structured_append: Option<String>,
+
+    #[serde(default)]
+    pub max_version: Option<u8>,
+
+    #[serde(default)]
+    pub append_columns: Option<u8>,
+
+    #[serde(default)]
+    pub encoding_mode: Option<String>,
+
+    #[serde(default)]
+    pub uppercase: Option<bool>,
+
+    #[serde(default)]
+    pub charset: Option<String>,
     
     #[serde(default)]
     pub foreground_color: Option<String>,
@@ -123,12 +296,120 @@ pub struct QrQueryParams {
     
     #[serde(default)]
     pub border_width: Option<u32>,
+
+    #[serde(default)]
+    pub quality: Option<u8>,
+
+    #[serde(default)]
+    pub chroma_subsampling: Option<String>,
+
+    #[serde(default)]
+    pub png_compression: Option<String>,
+
+    #[serde(default)]
+    pub tiff_compression: Option<String>,
+
+    #[serde(default)]
+    pub module_style: Option<String>,
+
+    #[serde(default)]
+    pub eye_outer_style: Option<String>,
+
+    #[serde(default)]
+    pub eye_inner_style: Option<String>,
+
+    #[serde(default)]
+    pub eye_outer_color: Option<String>,
+
+    #[serde(default)]
+    pub eye_inner_color: Option<String>,
+
+    #[serde(default)]
+    pub backdrop_color: Option<String>,
+
+    #[serde(default)]
+    pub gradient_type: Option<String>,
+
+    #[serde(default)]
+    pub gradient_angle: Option<f64>,
+
+    #[serde(default)]
+    pub gradient_colors: Option<String>,
+
+    #[serde(default)]
+    pub caption: Option<String>,
+
+    #[serde(default)]
+    pub caption_position: Option<String>,
+
+    #[serde(default)]
+    pub caption_align: Option<String>,
+
+    #[serde(default)]
+    pub caption_font_size: Option<u32>,
+
+    #[serde(default)]
+    pub caption_color: Option<String>,
+
+    #[serde(default)]
+    pub frame: Option<String>,
+
+    #[serde(default)]
+    pub frame_text: Option<String>,
+
+    #[serde(default)]
+    pub frame_color: Option<String>,
+
+    #[serde(default)]
+    pub frame_text_color: Option<String>,
 }
 
 impl QrQueryParams {
+    /// GS1 data from the `gtin`, `batch`, `expiry` and `serial` parameters,
+    /// output as `gs1` says.
+    fn gs1_data(&self) -> Result<Option<Gs1Data>, ApiError> {
+        let Some(gtin) = self.gtin.clone() else {
+            if self.batch.is_some()
+                || self.expiry.is_some()
+                || self.serial.is_some()
+                || self.gs1.is_some()
+                || self.gs1_domain.is_some()
+            {
+                return Err(ApiError::ValidationError(
+                    "batch, expiry, serial, gs1 and gs1_domain require gtin".to_string(),
+                ));
+            }
+            return Ok(None);
+        };
+
+        let output = match self.gs1.as_deref().map(str::to_lowercase).as_deref() {
+            None | Some("element_string") => Gs1Output::ElementString,
+            Some("digital_link") => Gs1Output::DigitalLink,
+            _ => {
+                return Err(ApiError::ValidationError(
+                    "GS1 output must be 'element_string' or 'digital_link'".to_string(),
+                ))
+            }
+        };
+        Ok(Some(Gs1Data {
+            gtin,
+            batch: self.batch.clone(),
+            expiry: self.expiry.clone(),
+            serial: self.serial.clone(),
+            output,
+            domain: self.gs1_domain.clone(),
+        }))
+    }
+
     fn into_customization(self) -> Result<QrCustomization, ApiError> {
-        use crate::models::{QrSize, ErrorCorrectionLevel, OutputFormat, QrColors};
-        
+        use crate::models::{
+            AppendLayout, CaptionOptions, CaptionPosition, Charset, ChromaSubsampling,
+            EncodingMode, EncodingOptions, ErrorCorrectionLevel, EyeColors, EyeShape, FrameOptions,
+            FrameStyle, Gradient, GradientKind, GradientStop, LengthUnit, ModuleStyle,
+            OutputFormat, PngCompression, PrintSize, QrColors, QrSize, StructuredAppendOptions,
+            Symbology, TextAlign, TiffCompression, DEFAULT_DPI,
+        };
+
         let mut customization = QrCustomization::default();
         let _url = self.url; // Move url out first
 
@@ -138,16 +419,34 @@ impl QrQueryParams {
                 "small" => QrSize::Small,
                 "medium" => QrSize::Medium,
                 "large" => QrSize::Large,
-                _ => {
-                    // Try to parse as custom size
-                    let pixels: u32 = size_str.parse()
-                        .map_err(|_| ApiError::ValidationError(
-                            "Size must be 'small', 'medium', 'large', or a number".to_string()
-                        ))?;
-                    QrSize::Custom(pixels)
+                size => {
+                    let invalid = || {
+                        ApiError::ValidationError(
+                        "Size must be 'small', 'medium', 'large', a number, or a length such as '30mm' or '1.5in'".to_string()
+                    )
+                    };
+                    // A length with a unit is a print size; plain numbers are pixels
+                    let print = [("mm", LengthUnit::Mm), ("in", LengthUnit::In)]
+                        .into_iter()
+                        .find_map(|(suffix, unit)| {
+                            size.strip_suffix(suffix).map(|width| (width, unit))
+                        });
+                    match print {
+                        Some((width, unit)) => QrSize::Print(PrintSize {
+                            width: width.trim().parse().map_err(|_| invalid())?,
+                            unit,
+                            dpi: self.dpi.unwrap_or(DEFAULT_DPI),
+                        }),
+                        None => QrSize::Custom(size.parse().map_err(|_| invalid())?),
+                    }
                 }
             };
         }
+        if self.dpi.is_some() && customization.size.print_size().is_none() {
+            return Err(ApiError::ValidationError(
+                "dpi only applies to sizes given in mm or in".to_string(),
+            ));
+        }
 
         // Parse format
         if let Some(format_str) = self.format {
@@ -155,12 +454,61 @@ impl QrQueryParams {
                 "png" => OutputFormat::Png,
                 "svg" => OutputFormat::Svg,
                 "jpeg" | "jpg" => OutputFormat::Jpeg,
+                "webp" => OutputFormat::Webp,
+                "pdf" => OutputFormat::Pdf,
+                "eps" => OutputFormat::Eps,
+                "gif" => OutputFormat::Gif,
+                "bmp" => OutputFormat::Bmp,
+                "tiff" | "tif" => OutputFormat::Tiff,
+                "ico" => OutputFormat::Ico,
+                "text" => OutputFormat::Text,
+                "ansi" => OutputFormat::Ansi,
+                "ascii" => OutputFormat::Ascii,
                 _ => return Err(ApiError::ValidationError(
-                    "Format must be 'png', 'svg', or 'jpeg'".to_string()
+                    "Format must be 'png', 'svg', 'jpeg', 'webp', 'pdf', 'eps', 'gif', 'bmp', 'tiff', 'ico', 'text', 'ansi', or 'ascii'".to_string()
                 )),
             };
         }
 
+        customization.quality = self.quality;
+
+        if let Some(subsampling) = self.chroma_subsampling {
+            customization.chroma_subsampling = Some(match subsampling.as_str() {
+                "4:4:4" | "444" => ChromaSubsampling::Ratio444,
+                "4:2:0" | "420" => ChromaSubsampling::Ratio420,
+                _ => {
+                    return Err(ApiError::ValidationError(
+                        "Chroma subsampling must be '4:4:4' or '4:2:0'".to_string(),
+                    ))
+                }
+            });
+        }
+
+        if let Some(compression) = self.png_compression {
+            customization.png_compression = Some(match compression.to_lowercase().as_str() {
+                "fast" => PngCompression::Fast,
+                "balanced" => PngCompression::Balanced,
+                "smallest" => PngCompression::Smallest,
+                _ => {
+                    return Err(ApiError::ValidationError(
+                        "PNG compression must be 'fast', 'balanced', or 'smallest'".to_string(),
+                    ))
+                }
+            });
+        }
+
+        if let Some(compression) = self.tiff_compression {
+            customization.tiff_compression = Some(match compression.to_lowercase().as_str() {
+                "lzw" => TiffCompression::Lzw,
+                "group4" | "g4" => TiffCompression::Group4,
+                _ => {
+                    return Err(ApiError::ValidationError(
+                        "TIFF compression must be 'lzw' or 'group4'".to_string(),
+                    ))
+                }
+            });
+        }
+
         // Parse error correction
         if let Some(ec_str) = self.error_correction {
             customization.error_correction = match ec_str.to_uppercase().as_str() {
@@ -174,18 +522,225 @@ impl QrQueryParams {
             };
         }
 
+        // Parse symbology
+        if let Some(symbology) = self.symbology {
+            customization.symbology = match symbology.to_lowercase().as_str() {
+                "qr" => Symbology::Qr,
+                "micro_qr" | "micro" => Symbology::MicroQr,
+                "rmqr" => Symbology::Rmqr,
+                _ => {
+                    return Err(ApiError::ValidationError(
+                        "Symbology must be 'qr', 'micro_qr' or 'rmqr'".to_string(),
+                    ))
+                }
+            };
+        }
+
+        // Symbol version, mask and rMQR size are range-checked by validation
+        customization.version = self.version;
+        customization.min_version = self.min_version;
+        customization.mask = self.mask;
+        customization.rows = self.rows;
+        customization.columns = self.columns;
+        customization.max_rows = self.max_rows;
+
+        // Parse structured append layout
+        if let Some(layout) = self.structured_append {
+            let layout = match layout.to_lowercase().as_str() {
+                "separate" => AppendLayout::Separate,
+                "tiled" => AppendLayout::Tiled,
+                _ => {
+                    return Err(ApiError::ValidationError(
+                        "Structured append must be 'separate' or 'tiled'".to_string(),
+                    ))
+                }
+            };
+            customization.structured_append = Some(StructuredAppendOptions {
+                max_version: self.max_version,
+                layout,
+                columns: self.append_columns,
+            });
+        } else if self.max_version.is_some() || self.append_columns.is_some() {
+            return Err(ApiError::ValidationError(
+                "max_version and append_columns require structured_append".to_string(),
+            ));
+        }
+
+        // Parse encoding mode and uppercase folding
+        if self.encoding_mode.is_some() || self.uppercase.is_some() {
+            let mode = match self
+                .encoding_mode
+                .as_deref()
+                .map(str::to_lowercase)
+                .as_deref()
+            {
+                None | Some("auto") => EncodingMode::Auto,
+                Some("numeric") => EncodingMode::Numeric,
+                Some("alphanumeric") => EncodingMode::Alphanumeric,
+                Some("byte") => EncodingMode::Byte,
+                Some("kanji") => EncodingMode::Kanji,
+                _ => return Err(ApiError::ValidationError(
+                    "Encoding mode must be 'auto', 'numeric', 'alphanumeric', 'byte' or 'kanji'"
+                        .to_string(),
+                )),
+            };
+            customization.encoding = Some(EncodingOptions {
+                mode,
+                uppercase: self.uppercase.unwrap_or_default(),
+                ..Default::default()
+            });
+        }
+
+        // Parse charset
+        if let Some(charset) = self.charset {
+            customization.charset = Some(Charset::from_name(&charset).ok_or_else(|| ApiError::ValidationError(
+                "Charset must be 'utf-8', 'iso-8859-1' to 'iso-8859-16' (not 12), 'shift_jis', 'windows-1250', 'windows-1251', 'windows-1252', 'windows-1256', 'utf-16be', 'us-ascii', 'big5', 'gb18030' or 'euc-kr'".to_string()
+            ))?);
+        }
+
         // Parse colors
         if self.foreground_color.is_some() || self.background_color.is_some() {
-            let fg = self.foreground_color.unwrap_or_else(|| "#000000".to_string());
-            let bg = self.background_color.unwrap_or_else(|| "#FFFFFF".to_string());
-            
-            customization.colors = QrColors::new(fg, bg)
-                .map_err(|e| ApiError::ValidationError(e))?;
+            let fg = self
+                .foreground_color
+                .unwrap_or_else(|| "#000000".to_string());
+            let bg = self
+                .background_color
+                .unwrap_or_else(|| "#FFFFFF".to_string());
+
+            customization.colors = QrColors::new(fg, bg).map_err(ApiError::ValidationError)?;
+        }
+
+        // Assumed color behind transparent backgrounds
+        if let Some(backdrop) = self.backdrop_color {
+            customization.colors.backdrop = Some(backdrop);
+        }
+
+        // Parse eye colors (applied to all three finder patterns)
+        if self.eye_outer_color.is_some() || self.eye_inner_color.is_some() {
+            customization.colors.eyes = vec![EyeColors {
+                outer: self.eye_outer_color,
+                inner: self.eye_inner_color,
+            }];
+        }
+
+        // Parse gradient (comma-separated colors, evenly spaced)
+        if let Some(colors_str) = self.gradient_colors {
+            let kind = match self
+                .gradient_type
+                .as_deref()
+                .map(str::to_lowercase)
+                .as_deref()
+            {
+                None | Some("linear") => GradientKind::Linear,
+                Some("radial") => GradientKind::Radial,
+                _ => {
+                    return Err(ApiError::ValidationError(
+                        "Gradient type must be 'linear' or 'radial'".to_string(),
+                    ))
+                }
+            };
+            customization.colors.gradient = Some(Gradient {
+                kind,
+                angle: self.gradient_angle.unwrap_or_default(),
+                stops: colors_str
+                    .split(',')
+                    .map(|color| GradientStop {
+                        offset: None,
+                        color: color.trim().to_string(),
+                    })
+                    .collect(),
+            });
+        } else if self.gradient_type.is_some() || self.gradient_angle.is_some() {
+            return Err(ApiError::ValidationError(
+                "gradient_colors is required when gradient_type or gradient_angle is set"
+                    .to_string(),
+            ));
         }
 
         // Parse border width
-        if let Some(border) = self.border_width {
-            customization.border_width = border;
+        customization.border_width = self.border_width;
+
+        // Parse module style
+        if let Some(style_str) = self.module_style {
+            customization.module_style = ModuleStyle::from_name(&style_str)
+                .ok_or_else(|| ApiError::ValidationError(
+                    "Module style must be 'square', 'dots', 'rounded', 'connected_rounded', 'diamond', 'vertical_bars', or 'horizontal_bars'".to_string()
+                ))?;
+        }
+
+        // Parse eye styles
+        let parse_eye_shape = |name: &str| {
+            EyeShape::from_name(name).ok_or_else(|| {
+                ApiError::ValidationError(
+                    "Eye style must be 'square', 'rounded', 'circle', or 'leaf'".to_string(),
+                )
+            })
+        };
+        if let Some(outer) = self.eye_outer_style {
+            customization.eye_style.outer = parse_eye_shape(&outer)?;
+        }
+        if let Some(inner) = self.eye_inner_style {
+            customization.eye_style.inner = parse_eye_shape(&inner)?;
+        }
+
+        if let Some(text) = self.caption {
+            let mut caption = CaptionOptions {
+                text,
+                font_size: self.caption_font_size,
+                color: self.caption_color,
+                ..Default::default()
+            };
+            if let Some(position) = self.caption_position {
+                caption.position = match position.to_lowercase().as_str() {
+                    "below" => CaptionPosition::Below,
+                    "above" => CaptionPosition::Above,
+                    _ => {
+                        return Err(ApiError::ValidationError(
+                            "Caption position must be 'below' or 'above'".to_string(),
+                        ))
+                    }
+                };
+            }
+            if let Some(align) = self.caption_align {
+                caption.align = match align.to_lowercase().as_str() {
+                    "left" => TextAlign::Left,
+                    "center" => TextAlign::Center,
+                    "right" => TextAlign::Right,
+                    _ => {
+                        return Err(ApiError::ValidationError(
+                            "Caption alignment must be 'left', 'center' or 'right'".to_string(),
+                        ))
+                    }
+                };
+            }
+            customization.caption = Some(caption);
+        }
+
+        if let Some(style) = self.frame {
+            let style = match style.to_lowercase().as_str() {
+                "banner" => FrameStyle::Banner,
+                "bubble" => FrameStyle::Bubble,
+                "ticket" => FrameStyle::Ticket,
+                "phone" => FrameStyle::Phone,
+                _ => {
+                    return Err(ApiError::ValidationError(
+                        "Frame must be 'banner', 'bubble', 'ticket' or 'phone'".to_string(),
+                    ))
+                }
+            };
+            customization.frame = Some(FrameOptions {
+                style,
+                text: self.frame_text,
+                color: self.frame_color,
+                text_color: self.frame_text_color,
+            });
+        } else if self.frame_text.is_some()
+            || self.frame_color.is_some()
+            || self.frame_text_color.is_some()
+        {
+            return Err(ApiError::ValidationError(
+                "frame_text, frame_color and frame_text_color require a frame".to_string(),
+            ));
         }
 
         // Validate the complete customization
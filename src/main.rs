@@ -14,7 +14,6 @@ use axum::{
 use tokio::net::TcpListener;
 use tower_http::cors::CorsLayer;
 use tracing::info;
-use tracing_subscriber;
 
 use handlers::{health, generate_qr_json, generate_qr_image, generate_qr_query};
 use middleware::logging_middleware;
@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+
+use super::validate_color;
+
+/// Longest accepted caption, in characters.
+const MAX_CAPTION_LENGTH: usize = 500;
+
+/// Text drawn above or below the code, such as a fallback URL or a label.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaptionOptions {
+    /// Text to draw; `\n` starts a new line
+    #[serde(default)]
+    pub text: String,
+
+    #[serde(default)]
+    pub position: CaptionPosition,
+
+    /// Font size in pixels; a fifteenth of the image width when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font_size: Option<u32>,
+
+    /// Text color; the foreground color when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+
+    #[serde(default)]
+    pub align: TextAlign,
+
+    /// Break lines that are wider than the symbol at spaces, or anywhere in
+    /// words too long to fit
+    #[serde(default = "default_wrap")]
+    pub wrap: bool,
+}
+
+impl Default for CaptionOptions {
+    fn default() -> Self {
+        Self {
+            text: String::new(),
+            position: CaptionPosition::default(),
+            font_size: None,
+            color: None,
+            align: TextAlign::default(),
+            wrap: default_wrap(),
+        }
+    }
+}
+
+impl CaptionOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.text.trim().is_empty() {
+            return Err("Caption text cannot be empty".to_string());
+        }
+
+        if self.text.chars().count() > MAX_CAPTION_LENGTH {
+            return Err(format!(
+                "Caption cannot exceed {} characters",
+                MAX_CAPTION_LENGTH
+            ));
+        }
+
+        if let Some(font_size) = self.font_size {
+            if !(6..=200).contains(&font_size) {
+                return Err("Caption font size must be between 6 and 200 pixels".to_string());
+            }
+        }
+
+        if let Some(color) = &self.color {
+            validate_color(color, "Caption color")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptionPosition {
+    #[default]
+    Below,
+    Above,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextAlign {
+    Left,
+    #[default]
+    Center,
+    Right,
+}
+
+fn default_wrap() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_caption_options_defaults() {
+        let options: CaptionOptions = serde_json::from_str(r#"{"text": "example.com"}"#).unwrap();
+        assert_eq!(options.position, CaptionPosition::Below);
+        assert_eq!(options.align, TextAlign::Center);
+        assert!(options.wrap);
+        assert!(options.validate().is_ok());
+    }
+
+    #[test]
+    fn test_caption_options_validation() {
+        let options = CaptionOptions {
+            text: "Scan me".to_string(),
+            ..Default::default()
+        };
+
+        assert!(CaptionOptions {
+            text: " ".to_string(),
+            ..options.clone()
+        }
+        .validate()
+        .is_err());
+        assert!(CaptionOptions {
+            text: "x".repeat(501),
+            ..options.clone()
+        }
+        .validate()
+        .is_err());
+        assert!(CaptionOptions {
+            font_size: Some(4),
+            ..options.clone()
+        }
+        .validate()
+        .is_err());
+        assert!(CaptionOptions {
+            color: Some("red".to_string()),
+            ..options.clone()
+        }
+        .validate()
+        .is_err());
+        assert!(CaptionOptions {
+            color: Some("#1A237E".to_string()),
+            ..options
+        }
+        .validate()
+        .is_ok());
+    }
+}
@@ -1,12 +1,99 @@
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
+/// Colors of one finder pattern. Unset parts use the foreground color.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EyeColors {
+    #[serde(default)]
+    pub outer: Option<String>,
+    #[serde(default)]
+    pub inner: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GradientKind {
+    Linear,
+    Radial,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GradientStop {
+    /// Position between 0.0 and 1.0; omitted offsets are spread evenly
+    #[serde(default)]
+    pub offset: Option<f64>,
+    pub color: String,
+}
+
+/// Multi-stop gradient spanning the symbol, excluding the quiet zone.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Gradient {
+    #[serde(rename = "type")]
+    pub kind: GradientKind,
+    /// Direction of a linear gradient in degrees: 0 runs left to right,
+    /// 90 top to bottom. Ignored for radial gradients.
+    #[serde(default)]
+    pub angle: f64,
+    pub stops: Vec<GradientStop>,
+}
+
+impl Gradient {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.stops.len() < 2 || self.stops.len() > 16 {
+            return Err("Gradient must have between 2 and 16 color stops".to_string());
+        }
+
+        if !self.angle.is_finite() {
+            return Err("Gradient angle must be a finite number".to_string());
+        }
+
+        let mut previous = 0.0;
+        for (index, offset) in self.offsets().into_iter().enumerate() {
+            if !(0.0..=1.0).contains(&offset) {
+                return Err("Gradient stop offsets must be between 0 and 1".to_string());
+            }
+            if offset < previous {
+                return Err("Gradient stop offsets must be in ascending order".to_string());
+            }
+            previous = offset;
+            validate_color(
+                &self.stops[index].color,
+                &format!("gradient stop {}", index + 1),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Stop offsets with omitted values spread evenly over 0.0..=1.0.
+    pub fn offsets(&self) -> Vec<f64> {
+        let last = self.stops.len().saturating_sub(1).max(1) as f64;
+        self.stops
+            .iter()
+            .enumerate()
+            .map(|(index, stop)| stop.offset.unwrap_or(index as f64 / last))
+            .collect()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QrColors {
     #[serde(default = "default_foreground")]
     pub foreground: String,
     #[serde(default = "default_background")]
     pub background: String,
+    /// Finder pattern colors: empty to use the foreground, one entry for all
+    /// eyes, or three entries for top-left, top-right and bottom-left.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub eyes: Vec<EyeColors>,
+    /// Gradient fill that replaces the solid foreground color
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gradient: Option<Gradient>,
+    /// Opaque color the code is assumed to sit on when the background is
+    /// not fully opaque. Contrast is checked against it (white if unset),
+    /// and JPEG output is flattened onto it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backdrop: Option<String>,
 }
 
 impl Default for QrColors {
@@ -14,6 +101,9 @@ impl Default for QrColors {
         Self {
             foreground: default_foreground(),
             background: default_background(),
+            eyes: Vec::new(),
+            gradient: None,
+            backdrop: None,
         }
     }
 }
@@ -23,72 +113,122 @@ impl QrColors {
         let colors = Self {
             foreground,
             background,
+            eyes: Vec::new(),
+            gradient: None,
+            backdrop: None,
         };
         colors.validate()?;
         Ok(colors)
     }
 
     pub fn validate(&self) -> Result<(), String> {
-        self.validate_color(&self.foreground, "foreground")?;
-        self.validate_color(&self.background, "background")?;
-        
-        if self.foreground == self.background {
-            return Err("Foreground and background colors cannot be the same".to_string());
+        validate_color(&self.foreground, "foreground")?;
+        validate_color(&self.background, "background")?;
+
+        if let Some(backdrop) = &self.backdrop {
+            validate_color(backdrop, "backdrop")?;
+            if hex_to_rgba(backdrop)?.3 != 255 {
+                return Err("backdrop color must be opaque".to_string());
+            }
         }
-        
-        Ok(())
-    }
 
-    fn validate_color(&self, color: &str, color_type: &str) -> Result<(), String> {
-        if !color.starts_with('#') {
-            return Err(format!("{} color must start with '#'", color_type));
+        if self.gradient.is_none() && self.foreground == self.background {
+            return Err("Foreground and background colors cannot be the same".to_string());
         }
 
-        let hex_part = &color[1..];
-        if hex_part.len() != 6 {
-            return Err(format!("{} color must be 6 hex digits (e.g., #FF0000)", color_type));
+        if !matches!(self.eyes.len(), 0 | 1 | 3) {
+            return Err("Eye colors must list 1 entry for all eyes or 3 entries (top-left, top-right, bottom-left)".to_string());
         }
 
-        if !hex_part.chars().all(|c| c.is_ascii_hexdigit()) {
-            return Err(format!("{} color contains invalid hex characters", color_type));
+        if let Some(gradient) = &self.gradient {
+            gradient.validate()?;
         }
 
+        for (index, eye) in self.eyes.iter().enumerate() {
+            if let Some(outer) = &eye.outer {
+                validate_color(outer, &format!("eye {} outer", index + 1))?;
+            }
+            if let Some(inner) = &eye.inner {
+                validate_color(inner, &format!("eye {} inner", index + 1))?;
+            }
+        }
+        
         Ok(())
     }
 
-    pub fn foreground_rgb(&self) -> Result<(u8, u8, u8), String> {
-        self.hex_to_rgb(&self.foreground)
+    /// Explicit `(outer, inner)` colors of the finder pattern at `index`;
+    /// `None` means the part is painted like the data modules. Tiled
+    /// symbols list their finders three at a time, so the colors repeat.
+    pub fn eye_overrides(&self, index: usize) -> (Option<&str>, Option<&str>) {
+        let eye = match self.eyes.len() {
+            0 => None,
+            1 => self.eyes.first(),
+            _ => self.eyes.get(index % 3),
+        };
+        (
+            eye.and_then(|e| e.outer.as_deref()),
+            eye.and_then(|e| e.inner.as_deref()),
+        )
     }
 
-    pub fn background_rgb(&self) -> Result<(u8, u8, u8), String> {
-        self.hex_to_rgb(&self.background)
+    pub fn foreground_rgba(&self) -> Result<(u8, u8, u8, u8), String> {
+        hex_to_rgba(&self.foreground)
     }
 
-    fn hex_to_rgb(&self, hex: &str) -> Result<(u8, u8, u8), String> {
-        let hex = &hex[1..]; // Remove #
-        let r = u8::from_str_radix(&hex[0..2], 16)
-            .map_err(|_| "Invalid red component".to_string())?;
-        let g = u8::from_str_radix(&hex[2..4], 16)
-            .map_err(|_| "Invalid green component".to_string())?;
-        let b = u8::from_str_radix(&hex[4..6], 16)
-            .map_err(|_| "Invalid blue component".to_string())?;
-        Ok((r, g, b))
+    pub fn background_rgba(&self) -> Result<(u8, u8, u8, u8), String> {
+        hex_to_rgba(&self.background)
     }
 
-    pub fn contrast_ratio(&self) -> Result<f64, String> {
-        let (r1, g1, b1) = self.foreground_rgb()?;
-        let (r2, g2, b2) = self.background_rgb()?;
+    /// Background as seen by a scanner: composited onto the backdrop, or
+    /// white when no backdrop is declared.
+    pub fn effective_background(&self) -> Result<(u8, u8, u8), String> {
+        let backdrop = match &self.backdrop {
+            Some(backdrop) => hex_to_rgba(backdrop)?,
+            None => (255, 255, 255, 255),
+        };
+        Ok(composite(
+            self.background_rgba()?,
+            (backdrop.0, backdrop.1, backdrop.2),
+        ))
+    }
 
-        let l1 = relative_luminance(r1, g1, b1);
-        let l2 = relative_luminance(r2, g2, b2);
+    /// Contrast of `color`, composited onto the effective background,
+    /// against that background.
+    fn contrast_against_background(&self, color: (u8, u8, u8, u8)) -> Result<f64, String> {
+        let background = self.effective_background()?;
+        Ok(contrast_between(composite(color, background), background))
+    }
+
+    pub fn contrast_ratio(&self) -> Result<f64, String> {
+        self.contrast_against_background(self.foreground_rgba()?)
+    }
 
-        let (lighter, darker) = if l1 > l2 { (l1, l2) } else { (l2, l1) };
-        Ok((lighter + 0.05) / (darker + 0.05))
+    /// Lowest contrast ratio of any color drawn against the background:
+    /// the foreground or, for gradients, the worst-case stop, plus the
+    /// finder pattern colors. Translucent colors are composited first.
+    pub fn min_contrast_ratio(&self) -> Result<f64, String> {
+        let mut min_ratio = match &self.gradient {
+            Some(gradient) => {
+                let mut worst = f64::INFINITY;
+                for stop in &gradient.stops {
+                    worst = worst.min(self.contrast_against_background(hex_to_rgba(&stop.color)?)?);
+                }
+                worst
+            }
+            None => self.contrast_ratio()?,
+        };
+        for index in 0..3 {
+            let (outer, inner) = self.eye_overrides(index);
+            for color in outer.into_iter().chain(inner) {
+                min_ratio = min_ratio.min(self.contrast_against_background(hex_to_rgba(color)?)?);
+            }
+        }
+        Ok(min_ratio)
     }
 
     pub fn has_sufficient_contrast(&self) -> Result<bool, String> {
         // WCAG AA standard requires contrast ratio of at least 3:1 for graphics
-        Ok(self.contrast_ratio()? >= 3.0)
+        Ok(self.min_contrast_ratio()? >= 3.0)
     }
 }
 
@@ -100,7 +240,79 @@ fn default_background() -> String {
     "#FFFFFF".to_string()
 }
 
-fn relative_luminance(r: u8, g: u8, b: u8) -> f64 {
+/// Checks for `#RRGGBB`, `#RRGGBBAA` or the `transparent` keyword.
+pub fn validate_color(color: &str, color_type: &str) -> Result<(), String> {
+    if color.eq_ignore_ascii_case("transparent") {
+        return Ok(());
+    }
+
+    if !color.starts_with('#') {
+        return Err(format!(
+            "{} color must start with '#' or be 'transparent'",
+            color_type
+        ));
+    }
+
+    let hex_part = &color[1..];
+    if hex_part.len() != 6 && hex_part.len() != 8 {
+        return Err(format!(
+            "{} color must be 6 or 8 hex digits (e.g., #FF0000 or #FF000080)",
+            color_type
+        ));
+    }
+
+    if !hex_part.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!(
+            "{} color contains invalid hex characters",
+            color_type
+        ));
+    }
+
+    Ok(())
+}
+
+/// Parses a validated color; `#RRGGBB` is opaque and `transparent` is
+/// fully transparent black.
+pub fn hex_to_rgba(hex: &str) -> Result<(u8, u8, u8, u8), String> {
+    if hex.eq_ignore_ascii_case("transparent") {
+        return Ok((0, 0, 0, 0));
+    }
+
+    let hex = &hex[1..]; // Remove #
+    let r = u8::from_str_radix(&hex[0..2], 16).map_err(|_| "Invalid red component".to_string())?;
+    let g =
+        u8::from_str_radix(&hex[2..4], 16).map_err(|_| "Invalid green component".to_string())?;
+    let b = u8::from_str_radix(&hex[4..6], 16).map_err(|_| "Invalid blue component".to_string())?;
+    let a = match hex.get(6..8) {
+        Some(alpha) => {
+            u8::from_str_radix(alpha, 16).map_err(|_| "Invalid alpha component".to_string())?
+        }
+        None => 255,
+    };
+    Ok((r, g, b, a))
+}
+
+/// Composites a possibly translucent color onto an opaque one.
+pub fn composite(top: (u8, u8, u8, u8), bottom: (u8, u8, u8)) -> (u8, u8, u8) {
+    let alpha = top.3 as f64 / 255.0;
+    let mix = |t: u8, b: u8| (t as f64 * alpha + b as f64 * (1.0 - alpha)).round() as u8;
+    (
+        mix(top.0, bottom.0),
+        mix(top.1, bottom.1),
+        mix(top.2, bottom.2),
+    )
+}
+
+fn contrast_between(first: (u8, u8, u8), second: (u8, u8, u8)) -> f64 {
+    let l1 = relative_luminance(first.0, first.1, first.2);
+    let l2 = relative_luminance(second.0, second.1, second.2);
+
+    let (lighter, darker) = if l1 > l2 { (l1, l2) } else { (l2, l1) };
+    (lighter + 0.05) / (darker + 0.05)
+}
+
+/// WCAG relative luminance of an sRGB color, from 0 (black) to 1 (white).
+pub fn relative_luminance(r: u8, g: u8, b: u8) -> f64 {
     fn linearize(c: u8) -> f64 {
         let c = c as f64 / 255.0;
         if c <= 0.03928 {
@@ -153,9 +365,10 @@ mod tests {
 
     #[test]
     fn test_rgb_conversion() {
-        let colors = QrColors::new("#FF0000".to_string(), "#00FF00".to_string()).unwrap();
-        assert_eq!(colors.foreground_rgb().unwrap(), (255, 0, 0));
-        assert_eq!(colors.background_rgb().unwrap(), (0, 255, 0));
+        let colors = QrColors::new("#FF0000".to_string(), "#00FF0080".to_string()).unwrap();
+        assert_eq!(colors.foreground_rgba().unwrap(), (255, 0, 0, 255));
+        assert_eq!(colors.background_rgba().unwrap(), (0, 255, 0, 128));
+        assert_eq!(hex_to_rgba("Transparent").unwrap(), (0, 0, 0, 0));
     }
 
     #[test]
@@ -175,4 +388,135 @@ mod tests {
         assert!(ratio < 3.0);
         assert!(!colors.has_sufficient_contrast().unwrap());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_eye_colors_resolution() {
+        let mut colors = QrColors::default();
+        assert_eq!(colors.eye_overrides(2), (None, None));
+
+        colors.eyes = vec![EyeColors {
+            outer: Some("#FF0000".to_string()),
+            inner: None,
+        }];
+        assert_eq!(colors.eye_overrides(1), (Some("#FF0000"), None));
+
+        colors.eyes = vec![
+            EyeColors::default(),
+            EyeColors {
+                outer: None,
+                inner: Some("#0000FF".to_string()),
+            },
+            EyeColors::default(),
+        ];
+        assert_eq!(colors.eye_overrides(0), (None, None));
+        assert_eq!(colors.eye_overrides(1), (None, Some("#0000FF")));
+        assert!(colors.validate().is_ok());
+    }
+
+    #[test]
+    fn test_eye_colors_validation() {
+        let mut colors = QrColors {
+            eyes: vec![EyeColors::default(), EyeColors::default()],
+            ..Default::default()
+        };
+        assert!(colors.validate().is_err());
+
+        colors.eyes = vec![EyeColors {
+            outer: Some("red".to_string()),
+            inner: None,
+        }];
+        assert!(colors.validate().is_err());
+    }
+
+    #[test]
+    fn test_eye_colors_low_contrast() {
+        let colors = QrColors {
+            eyes: vec![EyeColors {
+                outer: None,
+                inner: Some("#EEEEEE".to_string()),
+            }],
+            ..Default::default()
+        };
+        assert!(colors.contrast_ratio().unwrap() > 20.0);
+        assert!(!colors.has_sufficient_contrast().unwrap());
+    }
+
+    fn two_stop_gradient(kind: GradientKind, first: &str, second: &str) -> Gradient {
+        Gradient {
+            kind,
+            angle: 45.0,
+            stops: vec![
+                GradientStop {
+                    offset: None,
+                    color: first.to_string(),
+                },
+                GradientStop {
+                    offset: None,
+                    color: second.to_string(),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_gradient_validation() {
+        let mut gradient = two_stop_gradient(GradientKind::Linear, "#000000", "#0000AA");
+        assert!(gradient.validate().is_ok());
+        assert_eq!(gradient.offsets(), vec![0.0, 1.0]);
+
+        gradient.stops.truncate(1);
+        assert!(gradient.validate().is_err());
+
+        let mut gradient = two_stop_gradient(GradientKind::Radial, "#000000", "#0000AA");
+        gradient.stops[0].offset = Some(0.8);
+        gradient.stops[1].offset = Some(0.2);
+        assert!(gradient.validate().is_err());
+
+        let gradient = two_stop_gradient(GradientKind::Linear, "#000000", "blue");
+        assert!(gradient.validate().is_err());
+    }
+
+    #[test]
+    fn test_gradient_contrast_uses_worst_stop() {
+        let mut colors = QrColors {
+            gradient: Some(two_stop_gradient(
+                GradientKind::Linear,
+                "#000000",
+                "#0000AA",
+            )),
+            ..Default::default()
+        };
+        assert!(colors.has_sufficient_contrast().unwrap());
+
+        colors.gradient = Some(two_stop_gradient(
+            GradientKind::Radial,
+            "#000000",
+            "#DDDDDD",
+        ));
+        assert!(!colors.has_sufficient_contrast().unwrap());
+    }
+
+    #[test]
+    fn test_transparent_background_uses_backdrop() {
+        let mut colors = QrColors::new("#000000".to_string(), "transparent".to_string()).unwrap();
+        // Without a backdrop the code is assumed to sit on white
+        assert!(colors.has_sufficient_contrast().unwrap());
+
+        colors.backdrop = Some("#1A1A1A".to_string());
+        assert!(!colors.has_sufficient_contrast().unwrap());
+
+        colors.foreground = "#FFFFFF".to_string();
+        assert!(colors.has_sufficient_contrast().unwrap());
+
+        colors.backdrop = Some("#FFFFFF80".to_string());
+        assert!(colors.validate().is_err());
+    }
+
+    #[test]
+    fn test_translucent_foreground_contrast() {
+        // Half-transparent black on white composites to mid gray
+        let colors = QrColors::new("#00000080".to_string(), "#FFFFFF".to_string()).unwrap();
+        let ratio = colors.contrast_ratio().unwrap();
+        assert!(ratio > 3.0 && ratio < 5.0);
+    }
+}
@@ -0,0 +1,518 @@
+use serde::{Deserialize, Serialize};
+
+/// Characters of the alphanumeric mode besides digits and capital letters.
+const ALPHANUMERIC_SYMBOLS: &str = " $%*+-./:";
+
+/// Most segments accepted in one request.
+const MAX_SEGMENTS: usize = 64;
+
+/// Most offending characters listed in an error message.
+const MAX_LISTED_CHARACTERS: usize = 10;
+
+/// How the content is split into encoding modes.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EncodingOptions {
+    /// Mode for the whole content; segmented automatically when unset
+    #[serde(default)]
+    pub mode: EncodingMode,
+
+    /// Explicit segments, which must spell the content in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub segments: Vec<Segment>,
+
+    /// Fold lowercase ASCII letters to uppercase before encoding, so more
+    /// of the content fits the alphanumeric mode
+    #[serde(default)]
+    pub uppercase: bool,
+
+    /// Encode as GS1 data: FNC1 in the first position, with group
+    /// separators (U+001D) ending variable-length fields. Only set from
+    /// validated `gs1` request data, never read from the request body.
+    #[serde(skip)]
+    pub gs1: bool,
+}
+
+impl EncodingOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.segments.is_empty() {
+            return Ok(());
+        }
+
+        if self.mode != EncodingMode::Auto {
+            return Err("Set either mode or segments, not both".to_string());
+        }
+
+        if self.segments.len() > MAX_SEGMENTS {
+            return Err(format!("Cannot use more than {} segments", MAX_SEGMENTS));
+        }
+
+        for (index, segment) in self.segments.iter().enumerate() {
+            if segment.mode == EncodingMode::Auto {
+                return Err(format!(
+                    "Segment {} needs a mode other than auto",
+                    index + 1
+                ));
+            }
+            if segment.text.is_empty() {
+                return Err(format!("Segment {} cannot be empty", index + 1));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The content as it will be encoded.
+    pub fn fold(&self, content: &str) -> String {
+        if self.uppercase {
+            content.to_ascii_uppercase()
+        } else {
+            content.to_string()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EncodingMode {
+    /// Optimal mix of numeric, alphanumeric and byte segments
+    #[default]
+    Auto,
+    /// Digits 0-9, 10 bits per 3 characters
+    Numeric,
+    /// Digits, capital letters, space and $ % * + - . / :, 11 bits per 2
+    /// characters
+    Alphanumeric,
+    /// UTF-8 bytes, 8 bits each
+    Byte,
+    /// Shift JIS double-byte characters, 13 bits each
+    Kanji,
+}
+
+impl EncodingMode {
+    /// Whether the mode can encode `c`. Every character fits the byte mode.
+    pub fn encodes(&self, c: char) -> bool {
+        match self {
+            EncodingMode::Auto | EncodingMode::Byte => true,
+            EncodingMode::Numeric => c.is_ascii_digit(),
+            EncodingMode::Alphanumeric => {
+                c.is_ascii_digit() || c.is_ascii_uppercase() || ALPHANUMERIC_SYMBOLS.contains(c)
+            }
+            EncodingMode::Kanji => shift_jis_kanji(c).is_some(),
+        }
+    }
+
+    /// Checks that every character of `text` fits the mode, listing the
+    /// ones that do not.
+    pub fn check(&self, text: &str) -> Result<(), String> {
+        let mut rejected: Vec<char> = Vec::new();
+        for c in text.chars() {
+            if !self.encodes(c) && !rejected.contains(&c) {
+                rejected.push(c);
+            }
+        }
+        if rejected.is_empty() {
+            return Ok(());
+        }
+
+        let mut message = format!(
+            "{} mode cannot encode {}",
+            self.name(),
+            list_characters(&rejected)
+        );
+        if *self == EncodingMode::Alphanumeric && rejected.iter().any(char::is_ascii_lowercase) {
+            message.push_str("; set uppercase to fold lowercase letters");
+        }
+        Err(message)
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            EncodingMode::Auto => "Auto",
+            EncodingMode::Numeric => "Numeric",
+            EncodingMode::Alphanumeric => "Alphanumeric",
+            EncodingMode::Byte => "Byte",
+            EncodingMode::Kanji => "Kanji",
+        }
+    }
+}
+
+/// Character set of the byte segments, announced to scanners with an
+/// Extended Channel Interpretation (ECI) designator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Charset {
+    #[serde(rename = "utf-8")]
+    Utf8,
+    #[serde(rename = "iso-8859-1")]
+    Iso8859_1,
+    #[serde(rename = "iso-8859-2")]
+    Iso8859_2,
+    #[serde(rename = "iso-8859-3")]
+    Iso8859_3,
+    #[serde(rename = "iso-8859-4")]
+    Iso8859_4,
+    #[serde(rename = "iso-8859-5")]
+    Iso8859_5,
+    #[serde(rename = "iso-8859-6")]
+    Iso8859_6,
+    #[serde(rename = "iso-8859-7")]
+    Iso8859_7,
+    #[serde(rename = "iso-8859-8")]
+    Iso8859_8,
+    #[serde(rename = "iso-8859-9")]
+    Iso8859_9,
+    #[serde(rename = "iso-8859-10")]
+    Iso8859_10,
+    #[serde(rename = "iso-8859-11")]
+    Iso8859_11,
+    #[serde(rename = "iso-8859-13")]
+    Iso8859_13,
+    #[serde(rename = "iso-8859-14")]
+    Iso8859_14,
+    #[serde(rename = "iso-8859-15")]
+    Iso8859_15,
+    #[serde(rename = "iso-8859-16")]
+    Iso8859_16,
+    #[serde(rename = "shift_jis")]
+    ShiftJis,
+    #[serde(rename = "windows-1250")]
+    Windows1250,
+    #[serde(rename = "windows-1251")]
+    Windows1251,
+    #[serde(rename = "windows-1252")]
+    Windows1252,
+    #[serde(rename = "windows-1256")]
+    Windows1256,
+    #[serde(rename = "utf-16be")]
+    Utf16Be,
+    #[serde(rename = "us-ascii")]
+    Ascii,
+    #[serde(rename = "big5")]
+    Big5,
+    #[serde(rename = "gb18030")]
+    Gb18030,
+    #[serde(rename = "euc-kr")]
+    EucKr,
+}
+
+impl Charset {
+    const ALL: [Charset; 26] = [
+        Charset::Utf8,
+        Charset::Iso8859_1,
+        Charset::Iso8859_2,
+        Charset::Iso8859_3,
+        Charset::Iso8859_4,
+        Charset::Iso8859_5,
+        Charset::Iso8859_6,
+        Charset::Iso8859_7,
+        Charset::Iso8859_8,
+        Charset::Iso8859_9,
+        Charset::Iso8859_10,
+        Charset::Iso8859_11,
+        Charset::Iso8859_13,
+        Charset::Iso8859_14,
+        Charset::Iso8859_15,
+        Charset::Iso8859_16,
+        Charset::ShiftJis,
+        Charset::Windows1250,
+        Charset::Windows1251,
+        Charset::Windows1252,
+        Charset::Windows1256,
+        Charset::Utf16Be,
+        Charset::Ascii,
+        Charset::Big5,
+        Charset::Gb18030,
+        Charset::EucKr,
+    ];
+
+    /// Looks a charset up by its name, ignoring case, or by a common
+    /// alias such as "latin1" or "sjis".
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        let alias = match name.as_str() {
+            "utf8" => Some(Charset::Utf8),
+            "latin1" => Some(Charset::Iso8859_1),
+            "sjis" | "shift-jis" => Some(Charset::ShiftJis),
+            "cp1252" => Some(Charset::Windows1252),
+            "ascii" => Some(Charset::Ascii),
+            _ => None,
+        };
+        alias.or_else(|| Self::ALL.into_iter().find(|charset| charset.name() == name))
+    }
+
+    /// Canonical lowercase name, as accepted in requests.
+    pub fn name(self) -> &'static str {
+        match self {
+            Charset::Utf8 => "utf-8",
+            Charset::Iso8859_1 => "iso-8859-1",
+            Charset::Iso8859_2 => "iso-8859-2",
+            Charset::Iso8859_3 => "iso-8859-3",
+            Charset::Iso8859_4 => "iso-8859-4",
+            Charset::Iso8859_5 => "iso-8859-5",
+            Charset::Iso8859_6 => "iso-8859-6",
+            Charset::Iso8859_7 => "iso-8859-7",
+            Charset::Iso8859_8 => "iso-8859-8",
+            Charset::Iso8859_9 => "iso-8859-9",
+            Charset::Iso8859_10 => "iso-8859-10",
+            Charset::Iso8859_11 => "iso-8859-11",
+            Charset::Iso8859_13 => "iso-8859-13",
+            Charset::Iso8859_14 => "iso-8859-14",
+            Charset::Iso8859_15 => "iso-8859-15",
+            Charset::Iso8859_16 => "iso-8859-16",
+            Charset::ShiftJis => "shift_jis",
+            Charset::Windows1250 => "windows-1250",
+            Charset::Windows1251 => "windows-1251",
+            Charset::Windows1252 => "windows-1252",
+            Charset::Windows1256 => "windows-1256",
+            Charset::Utf16Be => "utf-16be",
+            Charset::Ascii => "us-ascii",
+            Charset::Big5 => "big5",
+            Charset::Gb18030 => "gb18030",
+            Charset::EucKr => "euc-kr",
+        }
+    }
+
+    /// ECI assignment number of the charset.
+    pub fn eci(self) -> u32 {
+        match self {
+            Charset::Iso8859_1 => 3,
+            Charset::Iso8859_2 => 4,
+            Charset::Iso8859_3 => 5,
+            Charset::Iso8859_4 => 6,
+            Charset::Iso8859_5 => 7,
+            Charset::Iso8859_6 => 8,
+            Charset::Iso8859_7 => 9,
+            Charset::Iso8859_8 => 10,
+            Charset::Iso8859_9 => 11,
+            Charset::Iso8859_10 => 12,
+            Charset::Iso8859_11 => 13,
+            Charset::Iso8859_13 => 15,
+            Charset::Iso8859_14 => 16,
+            Charset::Iso8859_15 => 17,
+            Charset::Iso8859_16 => 18,
+            Charset::ShiftJis => 20,
+            Charset::Windows1250 => 21,
+            Charset::Windows1251 => 22,
+            Charset::Windows1252 => 23,
+            Charset::Windows1256 => 24,
+            Charset::Utf16Be => 25,
+            Charset::Utf8 => 26,
+            Charset::Ascii => 27,
+            Charset::Big5 => 28,
+            Charset::Gb18030 => 29,
+            Charset::EucKr => 30,
+        }
+    }
+
+    /// Transcodes `text` into the charset, or lists the characters it
+    /// cannot represent.
+    ///
+    /// The WHATWG tables in encoding_rs read ISO-8859-1, -9 and -11 as their
+    /// Windows supersets, so those charsets also refuse anything the
+    /// superset puts at 0x80-0x9F, where the ISO sets have control codes.
+    pub fn encode(self, text: &str) -> Result<Vec<u8>, String> {
+        let table = match self {
+            Charset::Iso8859_2 => Some(encoding_rs::ISO_8859_2),
+            Charset::Iso8859_3 => Some(encoding_rs::ISO_8859_3),
+            Charset::Iso8859_4 => Some(encoding_rs::ISO_8859_4),
+            Charset::Iso8859_5 => Some(encoding_rs::ISO_8859_5),
+            Charset::Iso8859_6 => Some(encoding_rs::ISO_8859_6),
+            Charset::Iso8859_7 => Some(encoding_rs::ISO_8859_7),
+            Charset::Iso8859_8 => Some(encoding_rs::ISO_8859_8),
+            Charset::Iso8859_9 => Some(encoding_rs::WINDOWS_1254),
+            Charset::Iso8859_10 => Some(encoding_rs::ISO_8859_10),
+            Charset::Iso8859_11 => Some(encoding_rs::WINDOWS_874),
+            Charset::Iso8859_13 => Some(encoding_rs::ISO_8859_13),
+            Charset::Iso8859_14 => Some(encoding_rs::ISO_8859_14),
+            Charset::Iso8859_15 => Some(encoding_rs::ISO_8859_15),
+            Charset::Iso8859_16 => Some(encoding_rs::ISO_8859_16),
+            Charset::ShiftJis => Some(encoding_rs::SHIFT_JIS),
+            Charset::Windows1250 => Some(encoding_rs::WINDOWS_1250),
+            Charset::Windows1251 => Some(encoding_rs::WINDOWS_1251),
+            Charset::Windows1252 => Some(encoding_rs::WINDOWS_1252),
+            Charset::Windows1256 => Some(encoding_rs::WINDOWS_1256),
+            Charset::Big5 => Some(encoding_rs::BIG5),
+            Charset::Gb18030 => Some(encoding_rs::GB18030),
+            Charset::EucKr => Some(encoding_rs::EUC_KR),
+            Charset::Utf8 | Charset::Iso8859_1 | Charset::Utf16Be | Charset::Ascii => None,
+        };
+
+        let mut bytes = Vec::with_capacity(text.len());
+        let mut rejected: Vec<char> = Vec::new();
+        for c in text.chars() {
+            let mut buffer = [0; 4];
+            let encoded = match (self, table) {
+                (Charset::Utf8, _) => Some(c.encode_utf8(&mut buffer).as_bytes().to_vec()),
+                (Charset::Utf16Be, _) => Some(
+                    c.encode_utf16(&mut [0; 2])
+                        .iter()
+                        .flat_map(|unit| unit.to_be_bytes())
+                        .collect(),
+                ),
+                (Charset::Iso8859_1, _) => u8::try_from(c as u32).ok().map(|byte| vec![byte]),
+                (Charset::Ascii, _) => c.is_ascii().then(|| vec![c as u8]),
+                (_, Some(table)) => {
+                    let (encoded, _, unmappable) = table.encode(c.encode_utf8(&mut buffer));
+                    let windows_only = matches!(self, Charset::Iso8859_9 | Charset::Iso8859_11)
+                        && encoded
+                            .iter()
+                            .any(|&byte| (0x80..=0x9F).contains(&byte) && c as u32 != byte as u32);
+                    (!unmappable && !windows_only).then(|| encoded.into_owned())
+                }
+                (_, None) => None,
+            };
+            match encoded {
+                Some(encoded) => bytes.extend(encoded),
+                None if !rejected.contains(&c) => rejected.push(c),
+                None => {}
+            }
+        }
+
+        if rejected.is_empty() {
+            Ok(bytes)
+        } else {
+            Err(format!(
+                "{} cannot represent {}",
+                self.name(),
+                list_characters(&rejected)
+            ))
+        }
+    }
+}
+
+/// The characters quoted and comma-separated, cut off after the first few.
+fn list_characters(characters: &[char]) -> String {
+    let listed: Vec<String> = characters
+        .iter()
+        .take(MAX_LISTED_CHARACTERS)
+        .map(|c| format!("{:?}", c))
+        .collect();
+    let more = characters.len().saturating_sub(MAX_LISTED_CHARACTERS);
+    format!(
+        "{}{}",
+        listed.join(", "),
+        if more > 0 {
+            format!(" and {} more", more)
+        } else {
+            String::new()
+        }
+    )
+}
+
+/// A run of the content encoded in one mode.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Segment {
+    pub mode: EncodingMode,
+    pub text: String,
+}
+
+/// The Shift JIS code of `c` if it lies in the ranges of the Kanji mode,
+/// 0x8140-0x9FFC and 0xE040-0xEBBF.
+pub fn shift_jis_kanji(c: char) -> Option<u16> {
+    if c.is_ascii() {
+        return None;
+    }
+    let mut buffer = [0; 4];
+    let (bytes, _, unmappable) = encoding_rs::SHIFT_JIS.encode(c.encode_utf8(&mut buffer));
+    if unmappable || bytes.len() != 2 {
+        return None;
+    }
+    let code = u16::from_be_bytes([bytes[0], bytes[1]]);
+    matches!(code, 0x8140..=0x9FFC | 0xE040..=0xEBBF).then_some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_character_sets() {
+        assert!(EncodingMode::Numeric.check("0123456789").is_ok());
+        assert!(EncodingMode::Alphanumeric
+            .check("INV-2024/07 $5.00")
+            .is_ok());
+        assert!(EncodingMode::Kanji.check("漢字テスト").is_ok());
+        assert!(EncodingMode::Byte.check("héllo 漢字").is_ok());
+
+        let error = EncodingMode::Alphanumeric.check("inv-42?").unwrap_err();
+        assert!(
+            error.starts_with("Alphanumeric mode cannot encode 'i', 'n', 'v', '?'"),
+            "{}",
+            error
+        );
+        assert!(
+            error.ends_with("set uppercase to fold lowercase letters"),
+            "{}",
+            error
+        );
+        assert!(EncodingMode::Kanji
+            .check("漢A")
+            .unwrap_err()
+            .contains("'A'"));
+        assert_eq!(shift_jis_kanji('漢'), Some(0x8ABF));
+    }
+
+    #[test]
+    fn test_charset_transcoding() {
+        assert_eq!(Charset::Iso8859_1.encode("Café").unwrap(), b"Caf\xE9");
+        assert_eq!(Charset::Windows1252.encode("5€").unwrap(), b"5\x80");
+        assert_eq!(
+            Charset::ShiftJis.encode("東京").unwrap(),
+            [0x93, 0x8C, 0x8B, 0x9E]
+        );
+        assert_eq!(Charset::Utf16Be.encode("é").unwrap(), [0x00, 0xE9]);
+        assert_eq!(Charset::Utf8.encode("é").unwrap(), "é".as_bytes());
+
+        let error = Charset::Iso8859_1.encode("5€ Łódź").unwrap_err();
+        assert_eq!(error, "iso-8859-1 cannot represent '€', 'Ł', 'ź'");
+        // Windows-1254 puts the euro sign where ISO-8859-9 has a control code
+        assert!(Charset::Iso8859_9.encode("€").is_err());
+        assert_eq!(Charset::Iso8859_9.encode("ğ").unwrap(), [0xF0]);
+
+        assert_eq!(Charset::from_name("Shift_JIS"), Some(Charset::ShiftJis));
+        assert_eq!(Charset::from_name("latin1"), Some(Charset::Iso8859_1));
+        assert_eq!(Charset::from_name("iso-8859-12"), None);
+        assert_eq!((Charset::Iso8859_15.eci(), Charset::Utf8.eci()), (17, 26));
+        let parsed: Charset = serde_json::from_str("\"windows-1252\"").unwrap();
+        assert_eq!(parsed, Charset::Windows1252);
+    }
+
+    #[test]
+    fn test_encoding_options_validation() {
+        let segment = |mode, text: &str| Segment {
+            mode,
+            text: text.to_string(),
+        };
+        let options = EncodingOptions {
+            segments: vec![
+                segment(EncodingMode::Alphanumeric, "INV"),
+                segment(EncodingMode::Numeric, "0042"),
+            ],
+            ..Default::default()
+        };
+        assert!(options.validate().is_ok());
+        assert!(EncodingOptions {
+            mode: EncodingMode::Byte,
+            ..options.clone()
+        }
+        .validate()
+        .is_err());
+        assert!(EncodingOptions {
+            segments: vec![segment(EncodingMode::Auto, "A")],
+            ..options.clone()
+        }
+        .validate()
+        .is_err());
+        assert!(EncodingOptions {
+            segments: vec![segment(EncodingMode::Byte, "")],
+            ..options
+        }
+        .validate()
+        .is_err());
+
+        let folding = EncodingOptions {
+            uppercase: true,
+            ..Default::default()
+        };
+        assert_eq!(folding.fold("inv-0042/é"), "INV-0042/é");
+    }
+}
@@ -1,13 +1,29 @@
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "lowercase")]
+/// Smallest and largest rendered image in pixels, whichever way the size
+/// is given.
+const MIN_PIXELS: u32 = 50;
+const MAX_PIXELS: u32 = 2000;
+const MM_PER_INCH: f64 = 25.4;
+const POINTS_PER_INCH: f64 = 72.0;
+/// Resolution of a print size that does not give one.
+pub const DEFAULT_DPI: u32 = 300;
+
+/// Requested image size.
+///
+/// In JSON this is a preset name (`"medium"`), a number of pixels (`300`) or
+/// a print size (`{"width": 30, "unit": "mm", "dpi": 300}`). The older
+/// `{"custom": 300}` form is still accepted.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(from = "SizeRepr", into = "SizeRepr")]
 pub enum QrSize {
     Small,
+    #[default]
     Medium,
     Large,
     Custom(u32),
+    Print(PrintSize),
 }
 
 impl QrSize {
@@ -17,28 +33,33 @@ impl QrSize {
             QrSize::Medium => 300,
             QrSize::Large => 600,
             QrSize::Custom(size) => *size,
+            QrSize::Print(print) => print.pixels(),
+        }
+    }
+
+    /// Physical size to record in the output, if one was requested.
+    pub fn print_size(&self) -> Option<&PrintSize> {
+        match self {
+            QrSize::Print(print) => Some(print),
+            _ => None,
         }
     }
 
     pub fn validate(&self) -> Result<(), String> {
         match self {
-            QrSize::Custom(size) if *size < 50 => {
-                Err("Custom size must be at least 50 pixels".to_string())
-            }
-            QrSize::Custom(size) if *size > 2000 => {
-                Err("Custom size cannot exceed 2000 pixels".to_string())
+            QrSize::Custom(size) if *size < MIN_PIXELS => Err(format!(
+                "Custom size must be at least {} pixels",
+                MIN_PIXELS
+            )),
+            QrSize::Custom(size) if *size > MAX_PIXELS => {
+                Err(format!("Custom size cannot exceed {} pixels", MAX_PIXELS))
             }
+            QrSize::Print(print) => print.validate(),
             _ => Ok(()),
         }
     }
 }
 
-impl Default for QrSize {
-    fn default() -> Self {
-        QrSize::Medium
-    }
-}
-
 impl fmt::Display for QrSize {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -46,14 +67,145 @@ impl fmt::Display for QrSize {
             QrSize::Medium => write!(f, "medium (300px)"),
             QrSize::Large => write!(f, "large (600px)"),
             QrSize::Custom(size) => write!(f, "custom ({}px)", size),
+            QrSize::Print(print) => {
+                write!(f, "{} at {} DPI ({}px)", print, print.dpi, print.pixels())
+            }
+        }
+    }
+}
+
+/// Wire format of `QrSize`.
+#[derive(Serialize, Deserialize)]
+#[serde(
+    untagged,
+    expecting = "size must be 'small', 'medium', 'large', a number of pixels, or {\"width\", \"unit\", \"dpi\"}"
+)]
+enum SizeRepr {
+    Preset(SizePreset),
+    Pixels(u32),
+    Legacy { custom: u32 },
+    Print(PrintSize),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum SizePreset {
+    Small,
+    Medium,
+    Large,
+}
+
+impl From<SizeRepr> for QrSize {
+    fn from(repr: SizeRepr) -> Self {
+        match repr {
+            SizeRepr::Preset(SizePreset::Small) => QrSize::Small,
+            SizeRepr::Preset(SizePreset::Medium) => QrSize::Medium,
+            SizeRepr::Preset(SizePreset::Large) => QrSize::Large,
+            SizeRepr::Pixels(size) | SizeRepr::Legacy { custom: size } => QrSize::Custom(size),
+            SizeRepr::Print(print) => QrSize::Print(print),
+        }
+    }
+}
+
+impl From<QrSize> for SizeRepr {
+    fn from(size: QrSize) -> Self {
+        match size {
+            QrSize::Small => SizeRepr::Preset(SizePreset::Small),
+            QrSize::Medium => SizeRepr::Preset(SizePreset::Medium),
+            QrSize::Large => SizeRepr::Preset(SizePreset::Large),
+            QrSize::Custom(size) => SizeRepr::Pixels(size),
+            QrSize::Print(print) => SizeRepr::Print(print),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LengthUnit {
+    #[default]
+    Mm,
+    In,
+}
+
+/// Edge length of the printed code. The pixel size is the length at `dpi`,
+/// rounded to whole pixels.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PrintSize {
+    pub width: f64,
+    #[serde(default)]
+    pub unit: LengthUnit,
+    #[serde(default = "default_dpi")]
+    pub dpi: u32,
+}
+
+fn default_dpi() -> u32 {
+    DEFAULT_DPI
+}
+
+impl PrintSize {
+    pub fn inches(&self) -> f64 {
+        match self.unit {
+            LengthUnit::Mm => self.width / MM_PER_INCH,
+            LengthUnit::In => self.width,
         }
     }
+
+    /// Edge length in PostScript points, as used for PDF and EPS pages.
+    pub fn points(&self) -> f64 {
+        self.inches() * POINTS_PER_INCH
+    }
+
+    pub fn pixels(&self) -> u32 {
+        (self.inches() * self.dpi as f64).round() as u32
+    }
+
+    /// Pixels per metre, as PNG records resolution.
+    pub fn pixels_per_meter(&self) -> u32 {
+        (self.dpi as f64 * 1000.0 / MM_PER_INCH).round() as u32
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.width.is_finite() && self.width > 0.0) {
+            return Err("Print width must be a positive length".to_string());
+        }
+        if !(72..=2400).contains(&self.dpi) {
+            return Err("DPI must be between 72 and 2400".to_string());
+        }
+
+        let pixels = self.pixels();
+        if !(MIN_PIXELS..=MAX_PIXELS).contains(&pixels) {
+            return Err(format!(
+                "{} at {} DPI is {}px, but the size must be between {} and {} pixels; adjust the width or DPI",
+                self, self.dpi, pixels, MIN_PIXELS, MAX_PIXELS
+            ));
+        }
+        Ok(())
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// The length with its unit, e.g. `30mm` or `1.5in`, also valid as an SVG
+/// length.
+impl fmt::Display for PrintSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.width, self.unit)
+    }
+}
+
+impl fmt::Display for LengthUnit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LengthUnit::Mm => write!(f, "mm"),
+            LengthUnit::In => write!(f, "in"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum ErrorCorrectionLevel {
     L, // Low (~7%)
+    #[default]
     M, // Medium (~15%) - Default
     Q, // Quartile (~25%)
     H, // High (~30%)
@@ -77,11 +229,16 @@ impl ErrorCorrectionLevel {
             ErrorCorrectionLevel::H => "High (~30% recovery)",
         }
     }
-}
 
-impl Default for ErrorCorrectionLevel {
-    fn default() -> Self {
-        ErrorCorrectionLevel::M
+    /// Largest fraction of the codewords a logo may hide, about half of
+    /// what the level can recover so damage elsewhere still decodes.
+    pub fn max_logo_coverage(&self) -> f64 {
+        match self {
+            ErrorCorrectionLevel::L => 0.035,
+            ErrorCorrectionLevel::M => 0.075,
+            ErrorCorrectionLevel::Q => 0.125,
+            ErrorCorrectionLevel::H => 0.15,
+        }
     }
 }
 
@@ -91,12 +248,104 @@ impl fmt::Display for ErrorCorrectionLevel {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Heights of the rMQR sizes in modules.
+pub const RMQR_ROWS: [u8; 6] = [7, 9, 11, 13, 15, 17];
+
+/// Widths of the rMQR sizes in modules. 27 columns only come with 11 or 13
+/// rows.
+pub const RMQR_COLUMNS: [u8; 6] = [27, 43, 59, 77, 99, 139];
+
+/// Kind of symbol the content is encoded in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Symbology {
+    /// QR code, versions 1 to 40
+    #[default]
+    Qr,
+    /// Micro QR code, M1 to M4, with a single finder pattern and a
+    /// 2-module quiet zone
+    MicroQr,
+    /// Rectangular Micro QR code (ISO/IEC 23941), 7 to 17 modules high and
+    /// 27 to 139 wide
+    Rmqr,
+}
+
+impl Symbology {
+    /// Largest version number; Micro QR versions M1-M4 are numbered 1-4.
+    /// rMQR sizes are picked by rows and columns instead, so it has none.
+    pub fn max_version(self) -> u8 {
+        match self {
+            Symbology::Qr => 40,
+            Symbology::MicroQr => 4,
+            Symbology::Rmqr => 0,
+        }
+    }
+
+    /// Largest mask pattern number. rMQR always uses the same pattern.
+    pub fn max_mask(self) -> u8 {
+        match self {
+            Symbology::Qr => 7,
+            Symbology::MicroQr => 3,
+            Symbology::Rmqr => 0,
+        }
+    }
+
+    /// Quiet zone the standard requires, in modules.
+    pub fn default_quiet_zone(self) -> u32 {
+        match self {
+            Symbology::Qr => 4,
+            Symbology::MicroQr | Symbology::Rmqr => 2,
+        }
+    }
+
+    pub fn to_qrcode_version(self, version: u8) -> qrcode::Version {
+        match self {
+            Symbology::Qr => qrcode::Version::Normal(version as i16),
+            Symbology::MicroQr => qrcode::Version::Micro(version as i16),
+            Symbology::Rmqr => unreachable!("rMQR codes are not encoded by the qrcode crate"),
+        }
+    }
+
+    /// Name of a version as used in messages, e.g. "version 5" or "M3".
+    pub fn version_name(self, version: u8) -> String {
+        match self {
+            Symbology::Qr | Symbology::Rmqr => format!("version {}", version),
+            Symbology::MicroQr => format!("M{}", version),
+        }
+    }
+}
+
+impl fmt::Display for Symbology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Symbology::Qr => write!(f, "QR"),
+            Symbology::MicroQr => write!(f, "Micro QR"),
+            Symbology::Rmqr => write!(f, "rMQR"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum OutputFormat {
+    #[default]
     Png,
     Svg,
     Jpeg,
+    Webp,
+    Pdf,
+    Eps,
+    /// Two-color palette GIF
+    Gif,
+    Bmp,
+    Tiff,
+    Ico,
+    /// UTF-8 half blocks, two modules per character cell
+    Text,
+    /// Half blocks colored with 24-bit ANSI escape codes
+    Ansi,
+    /// Plain ASCII, `##` per dark module
+    Ascii,
 }
 
 impl OutputFormat {
@@ -105,6 +354,16 @@ impl OutputFormat {
             OutputFormat::Png => "image/png",
             OutputFormat::Svg => "image/svg+xml",
             OutputFormat::Jpeg => "image/jpeg",
+            OutputFormat::Webp => "image/webp",
+            OutputFormat::Pdf => "application/pdf",
+            OutputFormat::Eps => "application/postscript",
+            OutputFormat::Gif => "image/gif",
+            OutputFormat::Bmp => "image/bmp",
+            OutputFormat::Tiff => "image/tiff",
+            OutputFormat::Ico => "image/x-icon",
+            OutputFormat::Text | OutputFormat::Ansi | OutputFormat::Ascii => {
+                "text/plain; charset=utf-8"
+            }
         }
     }
 
@@ -113,13 +372,28 @@ impl OutputFormat {
             OutputFormat::Png => "png",
             OutputFormat::Svg => "svg",
             OutputFormat::Jpeg => "jpg",
+            OutputFormat::Webp => "webp",
+            OutputFormat::Pdf => "pdf",
+            OutputFormat::Eps => "eps",
+            OutputFormat::Gif => "gif",
+            OutputFormat::Bmp => "bmp",
+            OutputFormat::Tiff => "tiff",
+            OutputFormat::Ico => "ico",
+            OutputFormat::Text | OutputFormat::Ansi | OutputFormat::Ascii => "txt",
         }
     }
-}
 
-impl Default for OutputFormat {
-    fn default() -> Self {
-        OutputFormat::Png
+    /// Whether `quality` selects a lossy encoding for this format.
+    pub fn supports_quality(&self) -> bool {
+        matches!(self, OutputFormat::Webp | OutputFormat::Jpeg)
+    }
+
+    /// Whether the output is text for terminals rather than an image.
+    pub fn is_text(&self) -> bool {
+        matches!(
+            self,
+            OutputFormat::Text | OutputFormat::Ansi | OutputFormat::Ascii
+        )
     }
 }
 
@@ -129,6 +403,119 @@ impl fmt::Display for OutputFormat {
     }
 }
 
+/// Chroma subsampling of JPEG output.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum ChromaSubsampling {
+    /// Full color resolution, for the sharpest colored module edges
+    #[default]
+    #[serde(rename = "4:4:4")]
+    Ratio444,
+    /// Color at half resolution in both directions, for smaller files
+    #[serde(rename = "4:2:0")]
+    Ratio420,
+}
+
+/// Trade-off between encoding speed and file size for PNG output.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PngCompression {
+    Fast,
+    #[default]
+    Balanced,
+    /// Strongest compression and adaptive filtering
+    Smallest,
+}
+
+/// Compression of TIFF output.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TiffCompression {
+    /// Lossless LZW in full color
+    #[default]
+    Lzw,
+    /// 1-bit black and white with CCITT Group 4, for fax and label systems
+    Group4,
+}
+
+/// Shape used to draw data modules. Finder patterns keep their own styling.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModuleStyle {
+    #[default]
+    Square,
+    Dots,
+    Rounded,
+    ConnectedRounded,
+    Diamond,
+    VerticalBars,
+    HorizontalBars,
+}
+
+impl ModuleStyle {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().replace('-', "_").as_str() {
+            "square" => Some(ModuleStyle::Square),
+            "dots" | "dot" | "circle" | "circles" => Some(ModuleStyle::Dots),
+            "rounded" => Some(ModuleStyle::Rounded),
+            "connected_rounded" | "connected" => Some(ModuleStyle::ConnectedRounded),
+            "diamond" | "diamonds" => Some(ModuleStyle::Diamond),
+            "vertical_bars" => Some(ModuleStyle::VerticalBars),
+            "horizontal_bars" => Some(ModuleStyle::HorizontalBars),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ModuleStyle::Square => "square",
+            ModuleStyle::Dots => "dots",
+            ModuleStyle::Rounded => "rounded",
+            ModuleStyle::ConnectedRounded => "connected_rounded",
+            ModuleStyle::Diamond => "diamond",
+            ModuleStyle::VerticalBars => "vertical_bars",
+            ModuleStyle::HorizontalBars => "horizontal_bars",
+        }
+    }
+}
+
+impl fmt::Display for ModuleStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Shape of a finder pattern's outer ring or inner pupil.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EyeShape {
+    #[default]
+    Square,
+    Rounded,
+    Circle,
+    Leaf,
+}
+
+impl EyeShape {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "square" => Some(EyeShape::Square),
+            "rounded" => Some(EyeShape::Rounded),
+            "circle" => Some(EyeShape::Circle),
+            "leaf" => Some(EyeShape::Leaf),
+            _ => None,
+        }
+    }
+}
+
+/// Styling of the finder patterns ("eyes"), independent of the data modules.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EyeStyle {
+    #[serde(default)]
+    pub outer: EyeShape,
+    #[serde(default)]
+    pub inner: EyeShape,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -149,6 +536,61 @@ mod tests {
         assert!(QrSize::Custom(3000).validate().is_err());
     }
 
+    #[test]
+    fn test_qr_size_json_forms() {
+        let parse = |json: &str| serde_json::from_str::<QrSize>(json).unwrap();
+        assert!(matches!(parse(r#""large""#), QrSize::Large));
+        assert!(matches!(parse("300"), QrSize::Custom(300)));
+        assert!(matches!(parse(r#"{"custom": 250}"#), QrSize::Custom(250)));
+        assert!(serde_json::from_str::<QrSize>(r#""huge""#).is_err());
+
+        let print = parse(r#"{"width": 1.5, "unit": "in"}"#);
+        assert_eq!(print.print_size().unwrap().dpi, 300);
+        assert_eq!(
+            serde_json::to_string(&print).unwrap(),
+            r#"{"width":1.5,"unit":"in","dpi":300}"#
+        );
+        assert_eq!(serde_json::to_string(&QrSize::Custom(300)).unwrap(), "300");
+    }
+
+    #[test]
+    fn test_print_size_conversions() {
+        let print = PrintSize {
+            width: 25.4,
+            unit: LengthUnit::Mm,
+            dpi: 600,
+        };
+        assert_eq!(print.pixels(), 600);
+        assert!((print.points() - 72.0).abs() < 1e-9);
+        assert_eq!(print.pixels_per_meter(), 23622);
+        assert_eq!(print.to_string(), "25.4mm");
+        assert!(QrSize::Print(print).validate().is_ok());
+
+        // 40mm at 1200 DPI is 1890px; at 2400 DPI it is too large
+        let large = PrintSize {
+            width: 40.0,
+            unit: LengthUnit::Mm,
+            dpi: 2400,
+        };
+        let message = large.validate().unwrap_err();
+        assert!(message.contains("40mm at 2400 DPI is 3780px"));
+        assert!(PrintSize { dpi: 1200, ..large }.validate().is_ok());
+        assert!(PrintSize {
+            width: 0.0,
+            unit: LengthUnit::Mm,
+            dpi: 300
+        }
+        .validate()
+        .is_err());
+        assert!(PrintSize {
+            width: 10.0,
+            unit: LengthUnit::Mm,
+            dpi: 50
+        }
+        .validate()
+        .is_err());
+    }
+
     #[test]
     fn test_error_correction_mapping() {
         assert!(matches!(
@@ -167,5 +609,35 @@ mod tests {
         assert_eq!(OutputFormat::Svg.content_type(), "image/svg+xml");
         assert_eq!(OutputFormat::Png.file_extension(), "png");
         assert_eq!(OutputFormat::Svg.file_extension(), "svg");
+        assert_eq!(
+            OutputFormat::Ansi.content_type(),
+            "text/plain; charset=utf-8"
+        );
+        assert_eq!(OutputFormat::Ascii.file_extension(), "txt");
+        assert_eq!(OutputFormat::Ico.content_type(), "image/x-icon");
+        assert_eq!(OutputFormat::Tiff.file_extension(), "tiff");
+        assert!(OutputFormat::Text.is_text());
+        assert!(!OutputFormat::Svg.is_text());
+    }
+
+    #[test]
+    fn test_module_style_names() {
+        assert_eq!(ModuleStyle::from_name("dots"), Some(ModuleStyle::Dots));
+        assert_eq!(
+            ModuleStyle::from_name("Vertical-Bars"),
+            Some(ModuleStyle::VerticalBars)
+        );
+        assert_eq!(ModuleStyle::from_name("hexagon"), None);
+        assert_eq!(
+            ModuleStyle::ConnectedRounded.to_string(),
+            "connected_rounded"
+        );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_eye_shape_names() {
+        assert_eq!(EyeShape::from_name("Leaf"), Some(EyeShape::Leaf));
+        assert_eq!(EyeShape::from_name("star"), None);
+        assert_eq!(EyeStyle::default().outer, EyeShape::Square);
+    }
+}
@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+
+use super::{validate_color, QrColors};
+
+/// Call to action shown on frames when no text is given.
+pub const DEFAULT_FRAME_TEXT: &str = "SCAN ME";
+
+/// Longest accepted frame text, in characters.
+const MAX_FRAME_TEXT_LENGTH: usize = 40;
+
+/// Built-in artwork drawn around the code, with a short call to action.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FrameOptions {
+    #[serde(default)]
+    pub style: FrameStyle,
+
+    /// Call to action; "SCAN ME" when unset, none when empty
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+
+    /// Frame color; the foreground color when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+
+    /// Text color; the background color (white if it is transparent) for
+    /// text on the frame, or the frame color for text inside a speech bubble
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_color: Option<String>,
+}
+
+impl FrameOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(text) = &self.text {
+            if text.contains('\n') {
+                return Err("Frame text must be a single line".to_string());
+            }
+            if text.chars().count() > MAX_FRAME_TEXT_LENGTH {
+                return Err(format!(
+                    "Frame text cannot exceed {} characters",
+                    MAX_FRAME_TEXT_LENGTH
+                ));
+            }
+        }
+
+        if let Some(color) = &self.color {
+            validate_color(color, "Frame color")?;
+        }
+
+        if let Some(color) = &self.text_color {
+            validate_color(color, "Frame text color")?;
+        }
+
+        Ok(())
+    }
+
+    /// The call to action, or `None` when it is turned off.
+    pub fn text(&self) -> Option<&str> {
+        let text = self.text.as_deref().unwrap_or(DEFAULT_FRAME_TEXT).trim();
+        (!text.is_empty()).then_some(text)
+    }
+
+    pub fn color<'a>(&'a self, colors: &'a QrColors) -> &'a str {
+        self.color.as_deref().unwrap_or(&colors.foreground)
+    }
+
+    pub fn text_color(&self, colors: &QrColors) -> String {
+        if let Some(color) = &self.text_color {
+            return color.clone();
+        }
+        match self.style {
+            FrameStyle::Bubble => self.color(colors).to_string(),
+            _ if colors
+                .background_rgba()
+                .is_ok_and(|(_, _, _, alpha)| alpha == 0) =>
+            {
+                "#FFFFFF".to_string()
+            }
+            _ => colors.background.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FrameStyle {
+    /// Rounded border with the text on a banner below the code
+    #[default]
+    Banner,
+    /// Speech bubble with the text above the code and a tail at the bottom
+    Bubble,
+    /// Ticket with notched sides and the text on a perforated stub
+    Ticket,
+    /// Phone outline with the code on its screen and the text below it
+    Phone,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_options_defaults() {
+        let options: FrameOptions = serde_json::from_str(r#"{"style": "ticket"}"#).unwrap();
+        let colors = QrColors::default();
+        assert_eq!(options.style, FrameStyle::Ticket);
+        assert_eq!(options.text(), Some("SCAN ME"));
+        assert_eq!(options.color(&colors), "#000000");
+        assert_eq!(options.text_color(&colors), "#FFFFFF");
+
+        let bubble = FrameOptions {
+            style: FrameStyle::Bubble,
+            color: Some("#1A237E".to_string()),
+            ..options
+        };
+        assert_eq!(bubble.text_color(&colors), "#1A237E");
+        assert_eq!(
+            FrameOptions {
+                text: Some(" ".to_string()),
+                ..bubble
+            }
+            .text(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_frame_options_validation() {
+        assert!(FrameOptions::default().validate().is_ok());
+        assert!(FrameOptions {
+            text: Some("x".repeat(41)),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!(FrameOptions {
+            color: Some("blue".to_string()),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!(FrameOptions {
+            text_color: Some("#FFF".to_string()),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!(FrameOptions {
+            text_color: Some("#FFD600".to_string()),
+            ..Default::default()
+        }
+        .validate()
+        .is_ok());
+    }
+}
@@ -0,0 +1,332 @@
+use serde::{Deserialize, Serialize};
+
+/// Group separator ending a variable-length element string field; encoded
+/// as FNC1.
+const GS1_GROUP_SEPARATOR: char = '\u{1D}';
+
+/// Resolver a Digital Link URI points to when no domain is given.
+const DEFAULT_DIGITAL_LINK_DOMAIN: &str = "https://id.gs1.org";
+
+/// Characters of GS1 character set 82, allowed in batch and serial numbers.
+const CSET_82_SYMBOLS: &str = "!\"%&'()*+,-./:;<=>?_";
+
+/// Longest batch or serial number.
+const MAX_ALPHANUMERIC_LENGTH: usize = 20;
+
+/// Product identification from GS1 Application Identifiers (AIs), encoded
+/// instead of a URL.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Gs1Data {
+    /// GTIN-8, -12, -13 or -14 with its check digit (AI 01)
+    pub gtin: String,
+
+    /// Batch or lot number, up to 20 characters (AI 10)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch: Option<String>,
+
+    /// Expiration date as YYMMDD, with day 00 for the end of the month
+    /// (AI 17)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expiry: Option<String>,
+
+    /// Serial number, up to 20 characters (AI 21)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial: Option<String>,
+
+    #[serde(default)]
+    pub output: Gs1Output,
+
+    /// Digital Link URI prefix; https://id.gs1.org when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Gs1Output {
+    /// Element string in GS1 mode, e.g. 010950110153000317251231
+    #[default]
+    ElementString,
+    /// GS1 Digital Link URI, e.g. https://id.gs1.org/01/09501101530003
+    DigitalLink,
+}
+
+impl Gs1Data {
+    pub fn validate(&self) -> Result<(), String> {
+        let gtin = &self.gtin;
+        if !gtin.bytes().all(|byte| byte.is_ascii_digit()) || ![8, 12, 13, 14].contains(&gtin.len())
+        {
+            return Err("GTIN must have 8, 12, 13 or 14 digits".to_string());
+        }
+        let expected = check_digit(&gtin[..gtin.len() - 1]);
+        let given = gtin.as_bytes()[gtin.len() - 1] - b'0';
+        if given != expected {
+            return Err(format!(
+                "GTIN check digit should be {}, not {}",
+                expected, given
+            ));
+        }
+
+        if let Some(expiry) = &self.expiry {
+            if !is_date(expiry) {
+                return Err(format!("Expiry {:?} is not a date as YYMMDD", expiry));
+            }
+        }
+
+        for (value, name) in [(&self.batch, "Batch"), (&self.serial, "Serial")] {
+            let Some(value) = value else { continue };
+            if value.is_empty() || value.chars().count() > MAX_ALPHANUMERIC_LENGTH {
+                return Err(format!(
+                    "{} must be 1-{} characters",
+                    name, MAX_ALPHANUMERIC_LENGTH
+                ));
+            }
+            if let Some(c) = value
+                .chars()
+                .find(|&c| !c.is_ascii_alphanumeric() && !CSET_82_SYMBOLS.contains(c))
+            {
+                return Err(format!(
+                    "{} cannot contain {:?}; GS1 allows letters, digits and {}",
+                    name, c, CSET_82_SYMBOLS
+                ));
+            }
+        }
+
+        let Some(domain) = &self.domain else {
+            return Ok(());
+        };
+        let message = if self.output != Gs1Output::DigitalLink {
+            "Domain applies only to Digital Link output"
+        } else if !domain.starts_with("https://") && !domain.starts_with("http://") {
+            "Domain must start with https:// or http://"
+        } else if domain.contains(['?', '#', ' ']) {
+            "Domain cannot contain a query, a fragment or spaces"
+        } else {
+            return Ok(());
+        };
+        Err(message.to_string())
+    }
+
+    /// The GTIN padded to 14 digits, as every GS1 encoding carries it.
+    pub fn gtin14(&self) -> String {
+        format!("{:0>14}", self.gtin)
+    }
+
+    /// The content to encode for the requested output.
+    pub fn content(&self) -> String {
+        match self.output {
+            Gs1Output::ElementString => self.element_string(),
+            Gs1Output::DigitalLink => self.digital_link(),
+        }
+    }
+
+    /// Fixed-length fields first, then the variable-length ones, each but
+    /// the last ended by a group separator.
+    pub fn element_string(&self) -> String {
+        let mut content = format!("01{}", self.gtin14());
+        if let Some(expiry) = &self.expiry {
+            content.push_str(&format!("17{}", expiry));
+        }
+        let variable: Vec<String> = [("10", &self.batch), ("21", &self.serial)]
+            .into_iter()
+            .filter_map(|(ai, value)| value.as_ref().map(|value| format!("{}{}", ai, value)))
+            .collect();
+        content.push_str(&variable.join(&GS1_GROUP_SEPARATOR.to_string()));
+        content
+    }
+
+    /// The GTIN and its qualifiers, batch before serial, in the path and
+    /// the expiry in the query, with values percent-encoded.
+    pub fn digital_link(&self) -> String {
+        let domain = self
+            .domain
+            .as_deref()
+            .unwrap_or(DEFAULT_DIGITAL_LINK_DOMAIN)
+            .trim_end_matches('/');
+        let mut uri = format!("{}/01/{}", domain, self.gtin14());
+        for (ai, value) in [("10", &self.batch), ("21", &self.serial)] {
+            if let Some(value) = value {
+                uri.push_str(&format!("/{}/{}", ai, percent_encode(value)));
+            }
+        }
+        if let Some(expiry) = &self.expiry {
+            uri.push_str(&format!("?17={}", expiry));
+        }
+        uri
+    }
+}
+
+/// GS1 mod 10 check digit: digits weighted 3 and 1 alternately from the
+/// right.
+fn check_digit(digits: &str) -> u8 {
+    let sum: u32 = digits
+        .bytes()
+        .rev()
+        .enumerate()
+        .map(|(index, byte)| (byte - b'0') as u32 * if index % 2 == 0 { 3 } else { 1 })
+        .sum();
+    ((10 - sum % 10) % 10) as u8
+}
+
+/// Whether `value` is a YYMMDD date. Day 00 stands for the last day of the
+/// month.
+fn is_date(value: &str) -> bool {
+    if value.len() != 6 || !value.bytes().all(|byte| byte.is_ascii_digit()) {
+        return false;
+    }
+    let field = |range: std::ops::Range<usize>| value[range].parse::<u32>().unwrap_or_default();
+    let (year, month, day) = (field(0..2), field(2..4), field(4..6));
+    let days = match month {
+        2 if year % 4 == 0 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return false,
+    };
+    day <= days
+}
+
+/// Percent-encodes everything but the URI unreserved characters.
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn product() -> Gs1Data {
+        Gs1Data {
+            gtin: "9501101530003".to_string(),
+            batch: Some("AB-12".to_string()),
+            expiry: Some("251231".to_string()),
+            serial: Some("0042".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_gs1_validation() {
+        assert!(product().validate().is_ok());
+        assert!(Gs1Data {
+            gtin: "96385074".to_string(),
+            ..Default::default()
+        }
+        .validate()
+        .is_ok());
+        assert!(Gs1Data {
+            expiry: Some("240200".to_string()),
+            ..product()
+        }
+        .validate()
+        .is_ok());
+        assert!(Gs1Data {
+            expiry: Some("240229".to_string()),
+            ..product()
+        }
+        .validate()
+        .is_ok());
+
+        let error = Gs1Data {
+            gtin: "9501101530004".to_string(),
+            ..Default::default()
+        }
+        .validate()
+        .unwrap_err();
+        assert_eq!(error, "GTIN check digit should be 3, not 4");
+
+        let invalid = [
+            Gs1Data {
+                gtin: "950110153000".to_string(),
+                ..product()
+            },
+            Gs1Data {
+                gtin: "95011015300O3".to_string(),
+                ..product()
+            },
+            Gs1Data {
+                expiry: Some("251301".to_string()),
+                ..product()
+            },
+            Gs1Data {
+                expiry: Some("250230".to_string()),
+                ..product()
+            },
+            Gs1Data {
+                expiry: Some("2512".to_string()),
+                ..product()
+            },
+            Gs1Data {
+                batch: Some(String::new()),
+                ..product()
+            },
+            Gs1Data {
+                serial: Some("x".repeat(21)),
+                ..product()
+            },
+            Gs1Data {
+                serial: Some("N°42".to_string()),
+                ..product()
+            },
+            Gs1Data {
+                domain: Some("https://example.com".to_string()),
+                ..product()
+            },
+            Gs1Data {
+                domain: Some("example.com".to_string()),
+                output: Gs1Output::DigitalLink,
+                ..product()
+            },
+        ];
+        for data in invalid {
+            assert!(data.validate().is_err(), "{:?}", data);
+        }
+    }
+
+    #[test]
+    fn test_element_string() {
+        assert_eq!(
+            product().element_string(),
+            "01095011015300031725123110AB-12\u{1D}210042"
+        );
+        let gtin = Gs1Data {
+            gtin: "96385074".to_string(),
+            serial: Some("7".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(gtin.element_string(), "0100000096385074217");
+    }
+
+    #[test]
+    fn test_digital_link() {
+        let data = Gs1Data {
+            batch: Some("AB/12".to_string()),
+            output: Gs1Output::DigitalLink,
+            ..product()
+        };
+        assert_eq!(
+            data.content(),
+            "https://id.gs1.org/01/09501101530003/10/AB%2F12/21/0042?17=251231"
+        );
+
+        let data = Gs1Data {
+            gtin: "96385074".to_string(),
+            output: Gs1Output::DigitalLink,
+            domain: Some("https://brand.example.com/".to_string()),
+            ..Default::default()
+        };
+        assert!(data.validate().is_ok());
+        assert_eq!(
+            data.content(),
+            "https://brand.example.com/01/00000096385074"
+        );
+    }
+}
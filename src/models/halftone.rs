@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+
+use super::ErrorCorrectionLevel;
+
+/// A picture rendered through the code: data modules become dots colored
+/// from the image, with the image showing between them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HalftoneOptions {
+    /// Base64 PNG, JPEG or SVG image; a `data:` URI prefix is allowed
+    #[serde(default)]
+    pub image: String,
+
+    /// Dot diameter as a share of the module width; the smallest size the
+    /// error correction level allows when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dot_size: Option<f64>,
+}
+
+impl HalftoneOptions {
+    pub fn validate(&self, level: &ErrorCorrectionLevel) -> Result<(), String> {
+        if self.image.trim().is_empty() {
+            return Err("Halftone image data cannot be empty".to_string());
+        }
+
+        let Some(dot_size) = self.dot_size else {
+            return Ok(());
+        };
+        if !(0.2..=1.0).contains(&dot_size) {
+            return Err("Halftone dot size must be between 0.2 and 1.0".to_string());
+        }
+
+        // The smaller the dots, the more of each module shows the image
+        // instead of its own color and has to be recovered by error correction
+        if dot_size < min_dot_size(level) {
+            let required = [
+                ErrorCorrectionLevel::L,
+                ErrorCorrectionLevel::M,
+                ErrorCorrectionLevel::Q,
+                ErrorCorrectionLevel::H,
+            ]
+            .into_iter()
+            .find(|level| dot_size >= min_dot_size(level))
+            .unwrap_or(ErrorCorrectionLevel::H);
+            return Err(format!(
+                "A dot size of {} needs error correction level {:?}; level {:?} allows dots of {} or more",
+                dot_size,
+                required,
+                level,
+                min_dot_size(level)
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Dot diameter in modules at the given error correction level.
+    pub fn dot_size(&self, level: &ErrorCorrectionLevel) -> f64 {
+        self.dot_size.unwrap_or_else(|| min_dot_size(level))
+    }
+}
+
+/// Smallest dot each error correction level tolerates.
+fn min_dot_size(level: &ErrorCorrectionLevel) -> f64 {
+    match level {
+        ErrorCorrectionLevel::L => 0.6,
+        ErrorCorrectionLevel::M => 0.5,
+        ErrorCorrectionLevel::Q => 0.4,
+        ErrorCorrectionLevel::H => 0.3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dot_size_is_bounded_by_error_correction() {
+        let options = HalftoneOptions {
+            image: "iVBORw0KGgo=".to_string(),
+            dot_size: None,
+        };
+        assert_eq!(options.dot_size(&ErrorCorrectionLevel::M), 0.5);
+        assert_eq!(options.dot_size(&ErrorCorrectionLevel::H), 0.3);
+
+        let small = HalftoneOptions {
+            dot_size: Some(0.35),
+            ..options.clone()
+        };
+        assert!(small.validate(&ErrorCorrectionLevel::H).is_ok());
+        let error = small.validate(&ErrorCorrectionLevel::M).unwrap_err();
+        assert!(error.contains("level H"), "{}", error);
+
+        assert!(HalftoneOptions {
+            dot_size: Some(1.5),
+            ..options.clone()
+        }
+        .validate(&ErrorCorrectionLevel::L)
+        .is_err());
+        assert!(HalftoneOptions {
+            image: String::new(),
+            ..options
+        }
+        .validate(&ErrorCorrectionLevel::H)
+        .is_err());
+    }
+}
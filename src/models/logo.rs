@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+
+use super::validate_color;
+
+/// Largest accepted logo payload, in base64 characters (about 1 MB decoded).
+const MAX_LOGO_DATA_LENGTH: usize = 1_400_000;
+
+/// Image placed over the center of the code.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogoOptions {
+    /// Base64-encoded PNG, JPEG or SVG, optionally as a `data:` URI
+    #[serde(default)]
+    pub data: String,
+
+    /// Edge of the box the logo is fitted into, as a fraction of the
+    /// symbol width
+    #[serde(default = "default_logo_size")]
+    pub size: f64,
+
+    /// Modules cleared around the logo box
+    #[serde(default = "default_logo_padding")]
+    pub padding: u32,
+
+    /// Color of a plate drawn over the cleared area behind the logo; the
+    /// page background shows through when unset
+    #[serde(default)]
+    pub background: Option<String>,
+
+    /// Raise the error correction level to H so the hidden modules can be
+    /// recovered
+    #[serde(default = "default_auto_error_correction")]
+    pub auto_error_correction: bool,
+}
+
+impl Default for LogoOptions {
+    fn default() -> Self {
+        Self {
+            data: String::new(),
+            size: default_logo_size(),
+            padding: default_logo_padding(),
+            background: None,
+            auto_error_correction: default_auto_error_correction(),
+        }
+    }
+}
+
+impl LogoOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.data.trim().is_empty() {
+            return Err("Logo data cannot be empty".to_string());
+        }
+
+        if self.data.len() > MAX_LOGO_DATA_LENGTH {
+            return Err("Logo image cannot exceed 1 MB".to_string());
+        }
+
+        if !(0.05..=0.4).contains(&self.size) {
+            return Err("Logo size must be between 0.05 and 0.4 of the symbol width".to_string());
+        }
+
+        if self.padding > 5 {
+            return Err("Logo padding cannot exceed 5 modules".to_string());
+        }
+
+        if let Some(background) = &self.background {
+            validate_color(background, "Logo background")?;
+        }
+
+        Ok(())
+    }
+}
+
+fn default_logo_size() -> f64 {
+    0.2
+}
+
+fn default_logo_padding() -> u32 {
+    1
+}
+
+fn default_auto_error_correction() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_logo_options_defaults() {
+        let options: LogoOptions = serde_json::from_str(r#"{"data": "iVBORw0KGgo="}"#).unwrap();
+        assert_eq!(options.size, 0.2);
+        assert_eq!(options.padding, 1);
+        assert!(options.auto_error_correction);
+        assert!(options.validate().is_ok());
+    }
+
+    #[test]
+    fn test_logo_options_validation() {
+        let options = LogoOptions {
+            data: "iVBORw0KGgo=".to_string(),
+            ..Default::default()
+        };
+
+        assert!(LogoOptions {
+            data: String::new(),
+            ..options.clone()
+        }
+        .validate()
+        .is_err());
+        assert!(LogoOptions {
+            size: 0.5,
+            ..options.clone()
+        }
+        .validate()
+        .is_err());
+        assert!(LogoOptions {
+            padding: 6,
+            ..options.clone()
+        }
+        .validate()
+        .is_err());
+        assert!(LogoOptions {
+            background: Some("white".to_string()),
+            ..options.clone()
+        }
+        .validate()
+        .is_err());
+        assert!(LogoOptions {
+            background: Some("#FAFAFA".to_string()),
+            ..options
+        }
+        .validate()
+        .is_ok());
+    }
+}
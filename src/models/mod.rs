@@ -1,10 +1,34 @@
 pub mod enums;
+pub mod caption;
 pub mod colors;
+pub mod encoding;
+pub mod frame;
+pub mod gs1;
+pub mod halftone;
+pub mod logo;
 pub mod requests;
+pub mod structured_append;
 
-pub use enums::{QrSize, ErrorCorrectionLevel, OutputFormat};
-pub use colors::QrColors;
-pub use requests::{QrCustomization, QrRequest, QrResponse};
+pub use caption::{CaptionOptions, CaptionPosition, TextAlign};
+pub use colors::{
+    composite, hex_to_rgba, relative_luminance, validate_color, EyeColors, Gradient, GradientKind,
+    GradientStop, QrColors,
+};
+pub use encoding::{shift_jis_kanji, Charset, EncodingMode, EncodingOptions, Segment};
+pub use enums::{
+    ChromaSubsampling, ErrorCorrectionLevel, EyeShape, EyeStyle, LengthUnit, ModuleStyle,
+    OutputFormat, PngCompression, PrintSize, QrSize, Symbology, TiffCompression, DEFAULT_DPI,
+    RMQR_COLUMNS, RMQR_ROWS,
+};
+pub use frame::{FrameOptions, FrameStyle};
+pub use gs1::{Gs1Data, Gs1Output};
+pub use halftone::HalftoneOptions;
+pub use logo::LogoOptions;
+pub use requests::{GenerationDetails, ImageDimensions, QrCustomization, QrRequest, QrResponse};
+pub use structured_append::{
+    AppendLayout, AppendedSymbol, StructuredAppendDetails, StructuredAppendOptions,
+    MAX_APPEND_SYMBOLS,
+};
 
 use serde::Serialize;
 
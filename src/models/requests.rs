@@ -1,35 +1,111 @@
-use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+use super::{
+    AppendLayout, CaptionOptions, Charset, ChromaSubsampling, EncodingOptions,
+    ErrorCorrectionLevel, EyeStyle, FrameOptions, Gs1Data, Gs1Output, HalftoneOptions, LogoOptions,
+    ModuleStyle, OutputFormat, PngCompression, QrColors, QrSize, Segment, StructuredAppendDetails,
+    StructuredAppendOptions, Symbology, TiffCompression, RMQR_COLUMNS, RMQR_ROWS,
+};
 use crate::errors::ApiError;
-use super::{QrColors, QrSize, ErrorCorrectionLevel, OutputFormat};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct QrCustomization {
     #[serde(default)]
     pub size: QrSize,
     
     #[serde(default)]
     pub error_correction: ErrorCorrectionLevel,
+
+    #[serde(default)]
+    pub symbology: Symbology,
+
+    /// Symbol version, 1-40 for QR codes and 1-4 (M1-M4) for Micro QR;
+    /// the smallest that fits when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u8>,
+
+    /// Smallest version to pick when the version is chosen automatically
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_version: Option<u8>,
+
+    /// Mask pattern, 0-7 for QR codes and 0-3 for Micro QR; the one with
+    /// the lowest penalty when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mask: Option<u8>,
+
+    /// rMQR height in modules, 7-17; the smallest rectangle that fits when
+    /// unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rows: Option<u8>,
+
+    /// rMQR width in modules, 27-139; the smallest rectangle that fits when
+    /// unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub columns: Option<u8>,
+
+    /// Tallest rMQR height to pick when the rows are chosen automatically
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_rows: Option<u8>,
+
+    /// Encoding mode or explicit segments; optimal segmentation when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<EncodingOptions>,
+
+    /// Character set of the byte segments, announced with an ECI
+    /// designator; UTF-8 without a designator when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub charset: Option<Charset>,
+
+    /// Split content that does not fit one symbol across several
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structured_append: Option<StructuredAppendOptions>,
     
     #[serde(default)]
     pub colors: QrColors,
     
-    #[serde(default = "default_border_width")]
-    pub border_width: u32,
+    /// Quiet zone around the symbol, in modules; 4 for QR codes and 2 for
+    /// Micro QR when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub border_width: Option<u32>,
     
     #[serde(default)]
     pub format: OutputFormat,
-}
 
-impl Default for QrCustomization {
-    fn default() -> Self {
-        Self {
-            size: QrSize::default(),
-            error_correction: ErrorCorrectionLevel::default(),
-            colors: QrColors::default(),
-            border_width: default_border_width(),
-            format: OutputFormat::default(),
-        }
-    }
+    /// Lossy encoding quality from 1 to 100 for JPEG (75 when unset) and
+    /// WebP (lossless when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quality: Option<u8>,
+
+    /// JPEG chroma subsampling; 4:4:4 when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chroma_subsampling: Option<ChromaSubsampling>,
+
+    /// PNG compression effort; balanced when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub png_compression: Option<PngCompression>,
+
+    /// TIFF compression; LZW in full color when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tiff_compression: Option<TiffCompression>,
+
+    #[serde(default)]
+    pub module_style: ModuleStyle,
+
+    #[serde(default)]
+    pub eye_style: EyeStyle,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logo: Option<LogoOptions>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub halftone: Option<HalftoneOptions>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub caption: Option<CaptionOptions>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame: Option<FrameOptions>,
 }
 
 impl QrCustomization {
@@ -43,13 +119,192 @@ impl QrCustomization {
             .map_err(|e| ApiError::ValidationError(format!("Color validation failed: {}", e)))?;
 
         // Validate border width
-        if self.border_width > 50 {
+        if self.quiet_zone() > 50 {
+            return Err(ApiError::ValidationError(
+                "Border width cannot exceed 50 modules".to_string(),
+            ));
+        }
+
+        if self.symbology == Symbology::Rmqr {
+            self.validate_rmqr()?;
+        } else if self.rows.is_some() || self.columns.is_some() || self.max_rows.is_some() {
+            return Err(ApiError::ValidationError(
+                "Rows, columns and max_rows apply only to rMQR codes".to_string(),
+            ));
+        }
+
+        let max_version = self.symbology.max_version();
+        for (version, name) in [
+            (self.version, "Version"),
+            (self.min_version, "Minimum version"),
+        ] {
+            if version.is_some_and(|version| !(1..=max_version).contains(&version)) {
+                return Err(ApiError::ValidationError(format!(
+                    "{} must be between 1 and {} for {} codes",
+                    name, max_version, self.symbology
+                )));
+            }
+        }
+
+        if self.version.is_some() && self.min_version.is_some() {
+            return Err(ApiError::ValidationError(
+                "Set either version or min_version, not both".to_string(),
+            ));
+        }
+
+        let max_mask = self.symbology.max_mask();
+        if self.mask.is_some_and(|mask| mask > max_mask) {
+            return Err(ApiError::ValidationError(format!(
+                "Mask must be between 0 and {} for {} codes",
+                max_mask, self.symbology
+            )));
+        }
+
+        if self.symbology == Symbology::MicroQr {
+            self.validate_micro_qr()?;
+        }
+
+        if let Some(encoding) = &self.encoding {
+            encoding.validate().map_err(|e| {
+                ApiError::ValidationError(format!("Encoding validation failed: {}", e))
+            })?;
+            if encoding.gs1 {
+                self.validate_gs1()?;
+            }
+        }
+
+        if let Some(structured_append) = &self.structured_append {
+            structured_append.validate().map_err(|e| {
+                ApiError::ValidationError(format!("Structured append validation failed: {}", e))
+            })?;
+            self.validate_structured_append(structured_append)?;
+        }
+
+        if let Some(quality) = self.quality {
+            if !(1..=100).contains(&quality) {
+                return Err(ApiError::ValidationError(
+                    "Quality must be between 1 and 100".to_string(),
+                ));
+            }
+            if !self.format.supports_quality() {
+                return Err(ApiError::ValidationError(format!(
+                    "Quality does not apply to {} output",
+                    self.format.file_extension()
+                )));
+            }
+        }
+
+        if self.chroma_subsampling.is_some() && !matches!(self.format, OutputFormat::Jpeg) {
+            return Err(ApiError::ValidationError(format!(
+                "Chroma subsampling does not apply to {} output",
+                self.format.file_extension()
+            )));
+        }
+
+        if self.png_compression.is_some() && !matches!(self.format, OutputFormat::Png) {
+            return Err(ApiError::ValidationError(format!(
+                "PNG compression does not apply to {} output",
+                self.format.file_extension()
+            )));
+        }
+
+        if self.tiff_compression.is_some() && !matches!(self.format, OutputFormat::Tiff) {
+            return Err(ApiError::ValidationError(format!(
+                "TIFF compression does not apply to {} output",
+                self.format.file_extension()
+            )));
+        }
+
+        if matches!(self.format, OutputFormat::Gif)
+            && (self.colors.gradient.is_some() || !self.colors.eyes.is_empty())
+        {
+            return Err(ApiError::ValidationError(
+                "GIF output uses a two-color palette; gradients and eye colors are not supported"
+                    .to_string(),
+            ));
+        }
+
+        if matches!(self.format, OutputFormat::Ico) && self.size.to_pixels() > 256 {
             return Err(ApiError::ValidationError(
-                "Border width cannot exceed 50 pixels".to_string()
+                "ICO images cannot exceed 256x256 pixels".to_string(),
             ));
         }
 
-        // Check contrast for accessibility
+        if let Some(logo) = &self.logo {
+            if let Some(output) = self.image_unsupported_output() {
+                return Err(ApiError::ValidationError(format!(
+                    "Logos cannot be drawn in {} output",
+                    output
+                )));
+            }
+            logo.validate()
+                .map_err(|e| ApiError::ValidationError(format!("Logo validation failed: {}", e)))?;
+        }
+
+        if let Some(halftone) = &self.halftone {
+            if let Some(output) = self.image_unsupported_output() {
+                return Err(ApiError::ValidationError(format!(
+                    "Halftone images cannot be drawn in {} output",
+                    output
+                )));
+            }
+            if self.colors.gradient.is_some() {
+                return Err(ApiError::ValidationError(
+                    "Halftone codes take their module colors from the image; gradients are not supported".to_string()
+                ));
+            }
+            halftone
+                .validate(&self.effective_error_correction())
+                .map_err(|e| {
+                    ApiError::ValidationError(format!("Halftone validation failed: {}", e))
+                })?;
+        }
+
+        if let Some(caption) = &self.caption {
+            if self.format.is_text() {
+                return Err(ApiError::ValidationError(format!(
+                    "Captions cannot be drawn in {} output",
+                    self.format.file_extension()
+                )));
+            }
+            caption.validate().map_err(|e| {
+                ApiError::ValidationError(format!("Caption validation failed: {}", e))
+            })?;
+            let recolored = caption
+                .color
+                .as_ref()
+                .is_some_and(|color| !color.eq_ignore_ascii_case(&self.colors.foreground));
+            if matches!(self.format, OutputFormat::Gif) && recolored {
+                return Err(ApiError::ValidationError(
+                    "GIF output uses a two-color palette; the caption color must match the foreground".to_string()
+                ));
+            }
+        }
+
+        if let Some(frame) = &self.frame {
+            if self.format.is_text() {
+                return Err(ApiError::ValidationError(format!(
+                    "Frames cannot be drawn in {} output",
+                    self.format.file_extension()
+                )));
+            }
+            frame.validate().map_err(|e| {
+                ApiError::ValidationError(format!("Frame validation failed: {}", e))
+            })?;
+            let text_color = frame.text_color(&self.colors);
+            let two_color = frame
+                .color(&self.colors)
+                .eq_ignore_ascii_case(&self.colors.foreground)
+                && (text_color.eq_ignore_ascii_case(&self.colors.foreground)
+                    || text_color.eq_ignore_ascii_case(&self.colors.background));
+            if matches!(self.format, OutputFormat::Gif) && !two_color {
+                return Err(ApiError::ValidationError(
+                    "GIF output uses a two-color palette; the frame color must match the foreground and the frame text color the foreground or background".to_string()
+                ));
+            }
+        }
+
+        // Check contrast for accessibility, including the eye colors
         if !self.colors.has_sufficient_contrast()
             .map_err(|e| ApiError::ValidationError(format!("Contrast check failed: {}", e)))? {
             return Err(ApiError::ValidationError(
@@ -59,11 +314,160 @@ impl QrCustomization {
 
         Ok(())
     }
+
+    /// Name of the output when it has no way to draw a logo or halftone
+    /// image: text and the two-color formats.
+    fn image_unsupported_output(&self) -> Option<&'static str> {
+        match (&self.format, &self.tiff_compression) {
+            (OutputFormat::Text, _) => Some("text"),
+            (OutputFormat::Ansi, _) => Some("ansi"),
+            (OutputFormat::Ascii, _) => Some("ascii"),
+            (OutputFormat::Gif, _) => Some("GIF"),
+            (OutputFormat::Tiff, Some(TiffCompression::Group4)) => Some("Group 4 TIFF"),
+            _ => None,
+        }
+    }
+
+    /// Checks the error correction level against the Micro QR versions that
+    /// support it: M1 only detects errors, M2 and M3 have L and M, and M4
+    /// adds Q. No version has H, and none can designate a charset.
+    fn validate_micro_qr(&self) -> Result<(), ApiError> {
+        if self.logo.is_some() {
+            return Err(ApiError::ValidationError(
+                "Logos cannot be placed on Micro QR codes".to_string(),
+            ));
+        }
+
+        if self.charset.is_some() {
+            return Err(ApiError::ValidationError(
+                "Micro QR codes have no ECI mode to designate a charset; use a QR or rMQR code"
+                    .to_string(),
+            ));
+        }
+
+        let level = &self.error_correction;
+        let message = match (level, self.version) {
+            (ErrorCorrectionLevel::H, _) => {
+                "Micro QR codes support error correction levels L, M and Q, not H"
+            }
+            (ErrorCorrectionLevel::L, _) => return Ok(()),
+            (_, Some(1)) => "Micro QR M1 only detects errors; set error_correction to L to use it",
+            (ErrorCorrectionLevel::Q, Some(2 | 3)) => "Error correction level Q needs Micro QR M4",
+            _ => return Ok(()),
+        };
+        Err(ApiError::ValidationError(message.to_string()))
+    }
+
+    /// Checks the rMQR size and the features rMQR codes leave out: the size
+    /// is set by rows and columns rather than a version, the mask pattern
+    /// is fixed, only levels M and H exist, and nothing may cover or
+    /// surround the rectangle.
+    fn validate_rmqr(&self) -> Result<(), ApiError> {
+        let message = if self.version.is_some() || self.min_version.is_some() {
+            "rMQR codes are sized with rows, columns and max_rows rather than a version"
+        } else if self.mask.is_some() {
+            "rMQR codes always use the same mask pattern"
+        } else if matches!(
+            self.error_correction,
+            ErrorCorrectionLevel::L | ErrorCorrectionLevel::Q
+        ) {
+            "rMQR codes support error correction levels M and H"
+        } else if self.logo.is_some() {
+            "Logos cannot be placed on rMQR codes"
+        } else if self.halftone.is_some() {
+            "Halftone images cannot be used with rMQR codes"
+        } else if self.frame.is_some() {
+            "Frames cannot be drawn around rMQR codes"
+        } else if self.rows.is_some_and(|rows| !RMQR_ROWS.contains(&rows)) {
+            "Rows must be 7, 9, 11, 13, 15 or 17 for rMQR codes"
+        } else if self
+            .columns
+            .is_some_and(|columns| !RMQR_COLUMNS.contains(&columns))
+        {
+            "Columns must be 27, 43, 59, 77, 99 or 139 for rMQR codes"
+        } else if self.rows.is_some() && self.max_rows.is_some() {
+            "Set either rows or max_rows, not both"
+        } else if self.max_rows.is_some_and(|rows| rows < RMQR_ROWS[0]) {
+            "Max rows must be at least 7, the height of the shortest rMQR code"
+        } else {
+            return Ok(());
+        };
+        Err(ApiError::ValidationError(message.to_string()))
+    }
+
+    /// Checks the options that cannot be combined with splitting the
+    /// content: other symbologies, explicit segments, and artwork that
+    /// covers or surrounds a single symbol when the set is tiled.
+    fn validate_structured_append(
+        &self,
+        options: &StructuredAppendOptions,
+    ) -> Result<(), ApiError> {
+        let tiled = options.layout == AppendLayout::Tiled;
+        let message = if self.symbology != Symbology::Qr {
+            "Structured append is only available for QR codes"
+        } else if self
+            .encoding
+            .as_ref()
+            .is_some_and(|encoding| !encoding.segments.is_empty())
+        {
+            "Explicit segments cannot be split across symbols; set an encoding mode instead"
+        } else if self.version.is_some() && options.max_version.is_some() {
+            "Set either version or structured_append.max_version, not both"
+        } else if self.min_version > options.max_version && options.max_version.is_some() {
+            "Minimum version cannot exceed structured_append.max_version"
+        } else if tiled && self.logo.is_some() {
+            "Logos cannot be placed on tiled structured append codes; use the separate layout"
+        } else if tiled && self.halftone.is_some() {
+            "Halftone images cannot be used with tiled structured append codes; use the separate layout"
+        } else if tiled && self.frame.is_some() {
+            "Frames cannot be drawn around tiled structured append codes; use the separate layout"
+        } else {
+            return Ok(());
+        };
+        Err(ApiError::ValidationError(message.to_string()))
+    }
+
+    /// Checks the options GS1 data rules out: it is plain ASCII behind an
+    /// FNC1 indicator in a single symbol, and Micro QR has no FNC1 mode.
+    fn validate_gs1(&self) -> Result<(), ApiError> {
+        let message = if self.symbology == Symbology::MicroQr {
+            "Micro QR codes have no FNC1 mode for GS1 data; use a QR or rMQR code"
+        } else if self.charset.is_some() {
+            "GS1 data is plain ASCII and takes no charset"
+        } else if self.structured_append.is_some() {
+            "GS1 data cannot be split across symbols"
+        } else {
+            return Ok(());
+        };
+        Err(ApiError::ValidationError(message.to_string()))
+    }
+
+    /// Quiet zone in modules: the border width, or the one the symbology
+    /// requires when unset.
+    pub fn quiet_zone(&self) -> u32 {
+        self.border_width
+            .unwrap_or_else(|| self.symbology.default_quiet_zone())
+    }
+
+    /// Error correction level used for encoding: H when a logo asks for it,
+    /// otherwise the requested level.
+    pub fn effective_error_correction(&self) -> ErrorCorrectionLevel {
+        match &self.logo {
+            Some(logo) if logo.auto_error_correction => ErrorCorrectionLevel::H,
+            _ => self.error_correction.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct QrRequest {
+    /// URL or text to encode; empty when `gs1` is set
+    #[serde(default)]
     pub url: String,
+
+    /// GS1 product data to encode instead of a URL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gs1: Option<Gs1Data>,
     
     #[serde(default)]
     pub customization: QrCustomization,
@@ -71,6 +475,17 @@ pub struct QrRequest {
 
 impl QrRequest {
     pub fn validate(&self, max_url_length: usize) -> Result<(), ApiError> {
+        if let Some(gs1) = &self.gs1 {
+            if !self.url.is_empty() {
+                return Err(ApiError::ValidationError(
+                    "Set either url or gs1, not both".to_string(),
+                ));
+            }
+            gs1.validate()
+                .map_err(|e| ApiError::ValidationError(format!("GS1 validation failed: {}", e)))?;
+            return self.customization().validate();
+        }
+
         // Validate URL
         if self.url.trim().is_empty() {
             return Err(ApiError::ValidationError("URL cannot be empty".to_string()));
@@ -91,11 +506,50 @@ impl QrRequest {
         Ok(())
     }
 
+    /// The content to encode: the URL, or the GS1 data as an element string
+    /// or a Digital Link URI.
+    pub fn content(&self) -> String {
+        match &self.gs1 {
+            Some(gs1) => gs1.content(),
+            None => self.url.clone(),
+        }
+    }
+
+    /// The customization to encode the content with, in GS1 mode for
+    /// element strings.
+    pub fn customization(&self) -> Cow<'_, QrCustomization> {
+        match &self.gs1 {
+            Some(gs1) if gs1.output == Gs1Output::ElementString => {
+                let mut customization = self.customization.clone();
+                customization
+                    .encoding
+                    .get_or_insert_with(Default::default)
+                    .gs1 = true;
+                Cow::Owned(customization)
+            }
+            _ => Cow::Borrowed(&self.customization),
+        }
+    }
+
     fn validate_url_format(&self) -> Result<(), ApiError> {
         let url = self.url.trim();
+        let lower_url = url.to_lowercase();
+        let suspicious_patterns = [
+            "javascript:", "data:", "vbscript:", "file:", "ftp:",
+        ];
         
         // Check for basic URL format
         if !url.contains("://") && !url.starts_with("http") && !url.starts_with("mailto:") {
+            // Content without "://" can still start with a script scheme
+            if let Some(pattern) = suspicious_patterns
+                .iter()
+                .find(|&pattern| lower_url.starts_with(pattern))
+            {
+                return Err(ApiError::ValidationError(format!(
+                    "URL contains potentially unsafe protocol: {}",
+                    pattern
+                )));
+            }
             // Allow plain text for QR codes, but validate it's reasonable
             if url.contains('\n') || url.contains('\r') {
                 return Err(ApiError::ValidationError(
@@ -108,11 +562,6 @@ impl QrRequest {
         // For URL-like content, do basic validation
         if url.starts_with("http://") || url.starts_with("https://") {
             // Check for malicious patterns
-            let suspicious_patterns = [
-                "javascript:", "data:", "vbscript:", "file:", "ftp:",
-            ];
-            
-            let lower_url = url.to_lowercase();
             for pattern in &suspicious_patterns {
                 if lower_url.contains(pattern) {
                     return Err(ApiError::ValidationError(
@@ -133,6 +582,34 @@ impl QrRequest {
     }
 }
 
+/// Actual geometry of a rendered image
+#[derive(Debug, Clone, Serialize)]
+pub struct ImageDimensions {
+    pub width: u32,
+    pub height: u32,
+    /// Edge length of one module in pixels
+    pub module_size: u32,
+    /// Quiet zone in modules
+    pub quiet_zone: u32,
+    /// Symbol width in modules, excluding the quiet zone
+    pub modules: u32,
+    /// Symbol height in modules, the same as `modules` except for rMQR
+    pub rows: u32,
+}
+
+/// What the service reports about a generated code besides its data.
+#[derive(Debug, Clone)]
+pub struct GenerationDetails {
+    pub dimensions: ImageDimensions,
+    /// Non-fatal problems with the request, e.g. modules too small to
+    /// survive JPEG compression
+    pub warnings: Vec<String>,
+    /// Segments the content was encoded in
+    pub segments: Vec<Segment>,
+    /// How the content was split, when it took more than one symbol
+    pub structured_append: Option<StructuredAppendDetails>,
+}
+
 #[derive(Debug, Serialize)]
 pub struct QrResponse {
     pub qr_code: String,
@@ -141,36 +618,49 @@ pub struct QrResponse {
     pub error_correction: String,
     pub colors: QrColors,
     pub border_width: u32,
+    pub module_style: String,
+    pub eye_style: EyeStyle,
+    pub dimensions: ImageDimensions,
+    pub segments: Vec<Segment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub structured_append: Option<StructuredAppendDetails>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
 }
 
 impl QrResponse {
     pub fn new(
         qr_code: String,
         customization: &QrCustomization,
+        details: GenerationDetails,
     ) -> Self {
         Self {
             qr_code,
             format: customization.format.to_string().to_lowercase(),
             size: customization.size.to_string(),
-            error_correction: format!("{:?}", customization.error_correction),
+            error_correction: format!("{:?}", customization.effective_error_correction()),
             colors: customization.colors.clone(),
-            border_width: customization.border_width,
+            border_width: customization.quiet_zone(),
+            module_style: customization.module_style.to_string(),
+            eye_style: customization.eye_style.clone(),
+            dimensions: details.dimensions,
+            segments: details.segments,
+            structured_append: details.structured_append,
+            warnings: details.warnings,
         }
     }
 }
 
-fn default_border_width() -> u32 {
-    4
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::EncodingMode;
 
     #[test]
     fn test_qr_request_validation_success() {
         let request = QrRequest {
             url: "https://example.com".to_string(),
+            gs1: None,
             customization: QrCustomization::default(),
         };
         assert!(request.validate(2048).is_ok());
@@ -180,6 +670,7 @@ mod tests {
     fn test_qr_request_validation_empty_url() {
         let request = QrRequest {
             url: "".to_string(),
+            gs1: None,
             customization: QrCustomization::default(),
         };
         assert!(request.validate(2048).is_err());
@@ -189,6 +680,7 @@ mod tests {
     fn test_qr_request_validation_url_too_long() {
         let request = QrRequest {
             url: "a".repeat(3000),
+            gs1: None,
             customization: QrCustomization::default(),
         };
         assert!(request.validate(2048).is_err());
@@ -198,6 +690,7 @@ mod tests {
     fn test_qr_request_validation_suspicious_url() {
         let request = QrRequest {
             url: "javascript:alert('xss')".to_string(),
+            gs1: None,
             customization: QrCustomization::default(),
         };
         assert!(request.validate(2048).is_err());
@@ -207,11 +700,93 @@ mod tests {
     fn test_qr_request_validation_plain_text() {
         let request = QrRequest {
             url: "Hello, World!".to_string(),
+            gs1: None,
             customization: QrCustomization::default(),
         };
         assert!(request.validate(2048).is_ok());
     }
 
+    #[test]
+    fn test_qr_request_gs1() {
+        let gs1 = Gs1Data {
+            gtin: "9501101530003".to_string(),
+            batch: Some("AB-12".to_string()),
+            ..Default::default()
+        };
+        let request = QrRequest {
+            url: String::new(),
+            gs1: Some(gs1.clone()),
+            customization: QrCustomization::default(),
+        };
+        assert!(request.validate(2048).is_ok());
+        assert_eq!(request.content(), "010950110153000310AB-12");
+        assert!(request
+            .customization()
+            .encoding
+            .as_ref()
+            .is_some_and(|encoding| encoding.gs1));
+
+        let link = QrRequest {
+            gs1: Some(Gs1Data {
+                output: Gs1Output::DigitalLink,
+                ..gs1.clone()
+            }),
+            ..request
+        };
+        assert_eq!(
+            link.content(),
+            "https://id.gs1.org/01/09501101530003/10/AB-12"
+        );
+        assert!(link.customization().encoding.is_none());
+
+        let invalid = [
+            QrRequest {
+                url: "https://example.com".to_string(),
+                gs1: Some(gs1.clone()),
+                customization: QrCustomization::default(),
+            },
+            QrRequest {
+                url: String::new(),
+                gs1: Some(Gs1Data {
+                    gtin: "123".to_string(),
+                    ..Default::default()
+                }),
+                customization: QrCustomization::default(),
+            },
+            QrRequest {
+                url: String::new(),
+                gs1: Some(gs1.clone()),
+                customization: QrCustomization {
+                    symbology: Symbology::MicroQr,
+                    ..Default::default()
+                },
+            },
+            QrRequest {
+                url: String::new(),
+                gs1: Some(gs1),
+                customization: QrCustomization {
+                    charset: Some(Charset::Iso8859_1),
+                    ..Default::default()
+                },
+            },
+        ];
+        for request in invalid {
+            assert!(request.validate(2048).is_err(), "{:?}", request);
+        }
+
+        // GS1 mode comes only from validated gs1 data, not the request body
+        let request: QrRequest = serde_json::from_str(
+            r#"{"url": "01123", "customization": {"encoding": {"gs1": true}}}"#,
+        )
+        .unwrap();
+        assert!(request.validate(2048).is_ok());
+        assert!(!request
+            .customization()
+            .encoding
+            .as_ref()
+            .is_some_and(|encoding| encoding.gs1));
+    }
+
     #[test]
     fn test_qr_customization_validation_success() {
         let customization = QrCustomization::default();
@@ -220,8 +795,420 @@ mod tests {
 
     #[test]
     fn test_qr_customization_validation_large_border() {
+        let customization = QrCustomization {
+            border_width: Some(100),
+            ..Default::default()
+        };
+        assert!(customization.validate().is_err());
+    }
+
+    #[test]
+    fn test_qr_customization_validation_eye_contrast() {
+        use crate::models::EyeColors;
+
         let mut customization = QrCustomization::default();
-        customization.border_width = 100;
+        customization.colors.eyes = vec![EyeColors {
+            outer: Some("#C0C0C0".to_string()),
+            inner: None,
+        }];
+        assert!(customization.validate().is_err());
+
+        customization.colors.eyes[0].outer = Some("#8B0000".to_string());
+        assert!(customization.validate().is_ok());
+    }
+
+    #[test]
+    fn test_qr_customization_validation_version_and_mask() {
+        let customization = QrCustomization {
+            version: Some(10),
+            mask: Some(7),
+            ..Default::default()
+        };
+        assert!(customization.validate().is_ok());
+        assert!(QrCustomization {
+            version: Some(41),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!(QrCustomization {
+            min_version: Some(0),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!(QrCustomization {
+            mask: Some(8),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!(QrCustomization {
+            min_version: Some(5),
+            ..customization
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_micro_qr_validation() {
+        let micro = QrCustomization {
+            symbology: Symbology::MicroQr,
+            ..Default::default()
+        };
+        assert!(micro.validate().is_ok());
+        assert_eq!(micro.quiet_zone(), 2);
+
+        let invalid = [
+            QrCustomization {
+                version: Some(5),
+                ..micro.clone()
+            },
+            QrCustomization {
+                mask: Some(4),
+                ..micro.clone()
+            },
+            QrCustomization {
+                error_correction: ErrorCorrectionLevel::H,
+                ..micro.clone()
+            },
+            QrCustomization {
+                version: Some(1),
+                ..micro.clone()
+            },
+            QrCustomization {
+                version: Some(3),
+                error_correction: ErrorCorrectionLevel::Q,
+                ..micro.clone()
+            },
+            QrCustomization {
+                logo: Some(LogoOptions {
+                    data: "iVBORw0KGgo=".to_string(),
+                    ..Default::default()
+                }),
+                ..micro.clone()
+            },
+            QrCustomization {
+                charset: Some(Charset::ShiftJis),
+                ..micro.clone()
+            },
+        ];
+        for customization in invalid {
+            assert!(customization.validate().is_err(), "{:?}", customization);
+        }
+        assert!(QrCustomization {
+            version: Some(1),
+            error_correction: ErrorCorrectionLevel::L,
+            ..micro.clone()
+        }
+        .validate()
+        .is_ok());
+        assert!(QrCustomization {
+            version: Some(4),
+            error_correction: ErrorCorrectionLevel::Q,
+            ..micro
+        }
+        .validate()
+        .is_ok());
+    }
+
+    #[test]
+    fn test_rmqr_validation() {
+        let rmqr = QrCustomization {
+            symbology: Symbology::Rmqr,
+            ..Default::default()
+        };
+        assert!(rmqr.validate().is_ok());
+        assert_eq!(rmqr.quiet_zone(), 2);
+        assert!(QrCustomization {
+            rows: Some(11),
+            columns: Some(43),
+            ..rmqr.clone()
+        }
+        .validate()
+        .is_ok());
+        assert!(QrCustomization {
+            max_rows: Some(9),
+            error_correction: ErrorCorrectionLevel::H,
+            ..rmqr.clone()
+        }
+        .validate()
+        .is_ok());
+
+        let invalid = [
+            QrCustomization {
+                version: Some(1),
+                ..rmqr.clone()
+            },
+            QrCustomization {
+                mask: Some(0),
+                ..rmqr.clone()
+            },
+            QrCustomization {
+                error_correction: ErrorCorrectionLevel::L,
+                ..rmqr.clone()
+            },
+            QrCustomization {
+                rows: Some(8),
+                ..rmqr.clone()
+            },
+            QrCustomization {
+                columns: Some(40),
+                ..rmqr.clone()
+            },
+            QrCustomization {
+                rows: Some(7),
+                max_rows: Some(9),
+                ..rmqr.clone()
+            },
+            QrCustomization {
+                max_rows: Some(5),
+                ..rmqr.clone()
+            },
+            QrCustomization {
+                logo: Some(LogoOptions {
+                    data: "iVBORw0KGgo=".to_string(),
+                    ..Default::default()
+                }),
+                ..rmqr
+            },
+            QrCustomization {
+                rows: Some(7),
+                ..Default::default()
+            },
+        ];
+        for customization in invalid {
+            assert!(customization.validate().is_err(), "{:?}", customization);
+        }
+    }
+
+    #[test]
+    fn test_structured_append_validation() {
+        let split = QrCustomization {
+            structured_append: Some(StructuredAppendOptions {
+                max_version: Some(10),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert!(split.validate().is_ok());
+        assert!(QrCustomization {
+            min_version: Some(5),
+            ..split.clone()
+        }
+        .validate()
+        .is_ok());
+
+        let tiled = StructuredAppendOptions {
+            layout: AppendLayout::Tiled,
+            ..Default::default()
+        };
+        let logo = LogoOptions {
+            data: "iVBORw0KGgo=".to_string(),
+            ..Default::default()
+        };
+        assert!(QrCustomization {
+            logo: Some(logo.clone()),
+            ..split.clone()
+        }
+        .validate()
+        .is_ok());
+
+        let invalid = [
+            QrCustomization {
+                symbology: Symbology::MicroQr,
+                ..split.clone()
+            },
+            QrCustomization {
+                version: Some(5),
+                ..split.clone()
+            },
+            QrCustomization {
+                min_version: Some(11),
+                ..split.clone()
+            },
+            QrCustomization {
+                encoding: Some(EncodingOptions {
+                    segments: vec![Segment {
+                        mode: EncodingMode::Byte,
+                        text: "x".to_string(),
+                    }],
+                    ..Default::default()
+                }),
+                ..split.clone()
+            },
+            QrCustomization {
+                logo: Some(logo),
+                structured_append: Some(tiled.clone()),
+                ..Default::default()
+            },
+            QrCustomization {
+                frame: Some(FrameOptions::default()),
+                structured_append: Some(tiled),
+                ..Default::default()
+            },
+        ];
+        for customization in invalid {
+            assert!(customization.validate().is_err(), "{:?}", customization);
+        }
+    }
+
+    #[test]
+    fn test_qr_customization_validation_quality() {
+        let customization = QrCustomization {
+            format: OutputFormat::Webp,
+            quality: Some(80),
+            ..Default::default()
+        };
+        assert!(customization.validate().is_ok());
+
+        assert!(QrCustomization {
+            quality: Some(0),
+            ..customization.clone()
+        }
+        .validate()
+        .is_err());
+        assert!(QrCustomization {
+            format: OutputFormat::Jpeg,
+            ..customization.clone()
+        }
+        .validate()
+        .is_ok());
+        assert!(QrCustomization {
+            format: OutputFormat::Png,
+            ..customization.clone()
+        }
+        .validate()
+        .is_err());
+
+        let subsampled = QrCustomization {
+            format: OutputFormat::Jpeg,
+            chroma_subsampling: Some(ChromaSubsampling::Ratio420),
+            ..customization
+        };
+        assert!(subsampled.validate().is_ok());
+        assert!(QrCustomization {
+            format: OutputFormat::Webp,
+            ..subsampled
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_qr_customization_validation_legacy_formats() {
+        use crate::models::{EyeColors, TiffCompression};
+
+        let tiff = QrCustomization {
+            format: OutputFormat::Tiff,
+            tiff_compression: Some(TiffCompression::Group4),
+            ..Default::default()
+        };
+        assert!(tiff.validate().is_ok());
+        assert!(QrCustomization {
+            format: OutputFormat::Png,
+            ..tiff
+        }
+        .validate()
+        .is_err());
+
+        let png = QrCustomization {
+            png_compression: Some(PngCompression::Smallest),
+            ..Default::default()
+        };
+        assert!(png.validate().is_ok());
+        assert!(QrCustomization {
+            format: OutputFormat::Webp,
+            ..png
+        }
+        .validate()
+        .is_err());
+
+        let gif = QrCustomization {
+            format: OutputFormat::Gif,
+            ..Default::default()
+        };
+        assert!(gif.validate().is_ok());
+        let mut colors = QrColors::default();
+        colors.eyes.push(EyeColors {
+            outer: Some("#E53935".to_string()),
+            inner: None,
+        });
+        assert!(QrCustomization { colors, ..gif }.validate().is_err());
+
+        let ico = QrCustomization {
+            format: OutputFormat::Ico,
+            size: QrSize::Custom(256),
+            ..Default::default()
+        };
+        assert!(ico.validate().is_ok());
+        assert!(QrCustomization {
+            size: QrSize::Medium,
+            ..ico
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_logo_raises_error_correction() {
+        use crate::models::LogoOptions;
+
+        let mut customization = QrCustomization {
+            logo: Some(LogoOptions {
+                data: "iVBORw0KGgo=".to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(
+            customization.effective_error_correction(),
+            ErrorCorrectionLevel::H
+        );
+
+        customization.logo.as_mut().unwrap().auto_error_correction = false;
+        assert_eq!(
+            customization.effective_error_correction(),
+            ErrorCorrectionLevel::M
+        );
+
+        // Text output has no way to draw the logo
+        customization.format = OutputFormat::Text;
         assert!(customization.validate().is_err());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_frame_validation_per_format() {
+        use crate::models::FrameOptions;
+
+        let framed = QrCustomization {
+            frame: Some(FrameOptions::default()),
+            ..Default::default()
+        };
+        assert!(framed.validate().is_ok());
+        assert!(QrCustomization {
+            format: OutputFormat::Ansi,
+            ..framed.clone()
+        }
+        .validate()
+        .is_err());
+
+        // GIF has only the foreground and background colors to draw with
+        let gif = QrCustomization {
+            format: OutputFormat::Gif,
+            ..framed
+        };
+        assert!(gif.validate().is_ok());
+        let recolored = FrameOptions {
+            color: Some("#E53935".to_string()),
+            ..Default::default()
+        };
+        assert!(QrCustomization {
+            frame: Some(recolored),
+            ..gif
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+
+use super::Segment;
+
+/// Most symbols a structured append header can number.
+pub const MAX_APPEND_SYMBOLS: usize = 16;
+
+/// Splitting content that does not fit one symbol across a set of QR codes
+/// linked by structured append headers.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StructuredAppendOptions {
+    /// Largest version of each symbol, 1-40; 40 when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_version: Option<u8>,
+
+    #[serde(default)]
+    pub layout: AppendLayout,
+
+    /// Symbols per row when tiled; a roughly square grid when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub columns: Option<u8>,
+}
+
+impl StructuredAppendOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if self
+            .max_version
+            .is_some_and(|version| !(1..=40).contains(&version))
+        {
+            return Err("Max version must be between 1 and 40".to_string());
+        }
+
+        if self
+            .columns
+            .is_some_and(|columns| !(1..=MAX_APPEND_SYMBOLS as u8).contains(&columns))
+        {
+            return Err(format!(
+                "Columns must be between 1 and {}",
+                MAX_APPEND_SYMBOLS
+            ));
+        }
+
+        if self.columns.is_some() && self.layout != AppendLayout::Tiled {
+            return Err("Columns only apply to the tiled layout".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Symbols per row when `count` symbols are tiled.
+    pub fn columns(&self, count: usize) -> usize {
+        match self.columns {
+            Some(columns) => (columns as usize).min(count),
+            None => (1..=count)
+                .find(|columns| columns * columns >= count)
+                .unwrap_or(1),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AppendLayout {
+    /// One image per symbol
+    #[default]
+    Separate,
+    /// Every symbol in one image, left to right and top to bottom
+    Tiled,
+}
+
+/// How the content was split, reported alongside the generated codes.
+#[derive(Debug, Clone, Serialize)]
+pub struct StructuredAppendDetails {
+    /// Number of symbols in the set
+    pub total: u8,
+    /// XOR of every byte of the content, shared by all symbols
+    pub parity: u8,
+    pub symbols: Vec<AppendedSymbol>,
+}
+
+/// One symbol of a structured append set.
+#[derive(Debug, Clone, Serialize)]
+pub struct AppendedSymbol {
+    /// Position in the set, from 0
+    pub index: u8,
+    /// The symbol's image, for the separate layout
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub qr_code: Option<String>,
+    pub segments: Vec<Segment>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_structured_append_options() {
+        let options: StructuredAppendOptions =
+            serde_json::from_str(r#"{"layout": "tiled"}"#).unwrap();
+        assert!(options.validate().is_ok());
+        assert_eq!(
+            (
+                options.columns(1),
+                options.columns(4),
+                options.columns(5),
+                options.columns(16)
+            ),
+            (1, 2, 3, 4)
+        );
+        assert_eq!(
+            StructuredAppendOptions {
+                columns: Some(8),
+                ..options.clone()
+            }
+            .columns(3),
+            3
+        );
+
+        assert!(StructuredAppendOptions {
+            max_version: Some(41),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!(StructuredAppendOptions {
+            columns: Some(17),
+            ..options
+        }
+        .validate()
+        .is_err());
+        assert!(StructuredAppendOptions {
+            columns: Some(2),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -0,0 +1,450 @@
+use std::collections::BTreeSet;
+use std::sync::OnceLock;
+
+use ttf_parser::{Face, OutlineBuilder};
+
+use super::{
+    layout::QrLayout,
+    paint::Paint,
+    shapes::{Layer, Outline, PathOp, Shape},
+};
+use crate::{
+    errors::ApiError,
+    models::{CaptionOptions, CaptionPosition, TextAlign},
+};
+
+/// DejaVu Sans, bundled so captions look the same in every output and on
+/// every host.
+static FONT_DATA: &[u8] = include_bytes!("../../assets/fonts/DejaVuSans.ttf");
+
+const MAX_CAPTION_LINES: usize = 10;
+
+fn font() -> &'static Face<'static> {
+    static FONT: OnceLock<Face<'static>> = OnceLock::new();
+    FONT.get_or_init(|| {
+        Face::parse(FONT_DATA, 0).expect("the bundled font is a valid TrueType font")
+    })
+}
+
+/// One line of a caption, in canvas pixels.
+pub struct CaptionLine {
+    pub text: String,
+    /// Horizontal position of the alignment point: the left edge, center or
+    /// right edge of the line, depending on the alignment.
+    pub anchor: f64,
+    pub baseline: f64,
+    width: f64,
+}
+
+/// Caption text broken into lines and positioned on the canvas.
+pub struct PlacedCaption {
+    lines: Vec<CaptionLine>,
+    font_size: f64,
+    color: String,
+    align: TextAlign,
+}
+
+impl PlacedCaption {
+    /// Lays out the caption and extends the canvas above or below the code
+    /// area to make room for it.
+    ///
+    /// Lines span the width of the symbol, so left and right aligned text
+    /// lines up with its edges. The caption keeps clear of the symbol by at
+    /// least the quiet zone and half the font size, whichever is larger.
+    pub fn new(
+        options: &CaptionOptions,
+        layout: &mut QrLayout,
+        foreground: &str,
+    ) -> Result<Self, ApiError> {
+        let face = font();
+        let font_size = match options.font_size {
+            Some(size) => size as f64,
+            None => (layout.width as f64 / 15.0).round().max(8.0),
+        };
+        let scale = font_size / face.units_per_em() as f64;
+
+        check_drawable(&options.text, "Caption")?;
+
+        let (symbol_x, _, symbol_width) = layout.symbol_bounds();
+        let measure = |text: &str| text_width(text, font_size);
+        let texts = if options.wrap {
+            wrap(&options.text, symbol_width as f64, &measure)
+        } else {
+            let lines: Vec<String> = options
+                .text
+                .lines()
+                .map(|line| line.trim().to_string())
+                .collect();
+            if let Some(line) = lines
+                .iter()
+                .find(|line| measure(line) > layout.width as f64)
+            {
+                return Err(ApiError::ValidationError(format!(
+                    "Caption line \"{}\" is wider than the {}px image; enable wrap or use a smaller font_size",
+                    line, layout.width
+                )));
+            }
+            lines
+        };
+        if texts.len() > MAX_CAPTION_LINES {
+            return Err(ApiError::ValidationError(format!(
+                "Caption needs {} lines but at most {} are allowed; shorten it or use a smaller font_size",
+                texts.len(),
+                MAX_CAPTION_LINES
+            )));
+        }
+
+        let ascender = face.ascender() as f64 * scale;
+        let line_height = (face.ascender() - face.descender() + face.line_gap()) as f64 * scale;
+        let margin = font_size / 2.0;
+        let quiet_zone = (layout.padding + layout.quiet_zone * layout.module_size) as f64;
+        // A frame reaches the edge of the canvas, so only an unframed code
+        // has its quiet zone to count towards the margin
+        let gap = if layout.is_framed() {
+            margin
+        } else {
+            (margin - quiet_zone).max(0.0)
+        };
+        let text_height = texts.len() as f64 * line_height;
+        let band = (gap + text_height + margin).ceil() as u32;
+
+        // Top of the first line on the extended canvas
+        let text_top = match options.position {
+            CaptionPosition::Below => {
+                let top = layout.height as f64 + gap;
+                layout.extend(0, band);
+                top
+            }
+            CaptionPosition::Above => {
+                layout.extend(band, 0);
+                margin
+            }
+        };
+
+        let left = symbol_x as f64;
+        let right = left + symbol_width as f64;
+        let lines = texts
+            .into_iter()
+            .enumerate()
+            .map(|(index, text)| {
+                let width = measure(&text);
+                let anchor = match options.align {
+                    TextAlign::Left => left,
+                    TextAlign::Center => layout.width as f64 / 2.0,
+                    TextAlign::Right => right,
+                };
+                CaptionLine {
+                    text,
+                    anchor,
+                    baseline: text_top + index as f64 * line_height + ascender,
+                    width,
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            lines,
+            font_size,
+            color: options
+                .color
+                .clone()
+                .unwrap_or_else(|| foreground.to_string()),
+            align: options.align,
+        })
+    }
+
+    pub fn lines(&self) -> &[CaptionLine] {
+        &self.lines
+    }
+
+    pub fn font_size(&self) -> f64 {
+        self.font_size
+    }
+
+    pub fn color(&self) -> &str {
+        &self.color
+    }
+
+    pub fn align(&self) -> TextAlign {
+        self.align
+    }
+
+    /// The glyph outlines of every line as one layer, in the module
+    /// coordinates of `layout`, for renderers without text support.
+    pub fn layer(&self, layout: &QrLayout) -> Layer {
+        let mut shapes = Vec::new();
+        for line in &self.lines {
+            let left = match self.align {
+                TextAlign::Left => line.anchor,
+                TextAlign::Center => line.anchor - line.width / 2.0,
+                TextAlign::Right => line.anchor - line.width,
+            };
+            shapes.extend(glyph_shapes(
+                &line.text,
+                self.font_size,
+                left,
+                line.baseline,
+                layout,
+            ));
+        }
+
+        Layer {
+            paint: Paint::Solid(self.color.clone()),
+            shapes,
+        }
+    }
+}
+
+/// Rejects control characters and characters the bundled font has no glyph
+/// for, naming them; `what` names the text in the error.
+pub fn check_drawable(text: &str, what: &str) -> Result<(), ApiError> {
+    let face = font();
+    let missing: BTreeSet<char> = text
+        .chars()
+        .filter(|&c| c != '\n' && (c.is_control() || face.glyph_index(c).is_none()))
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let list: Vec<String> = missing.iter().map(|c| format!("{:?}", c)).collect();
+    Err(ApiError::ValidationError(format!(
+        "{} contains characters the bundled font cannot draw: {}",
+        what,
+        list.join(", ")
+    )))
+}
+
+/// Advance width of a line of text in pixels.
+pub fn text_width(text: &str, font_size: f64) -> f64 {
+    let face = font();
+    let units: f64 = text
+        .chars()
+        .filter_map(|c| face.glyph_index(c))
+        .map(|glyph| face.glyph_hor_advance(glyph).unwrap_or(0) as f64)
+        .sum();
+    units * font_size / face.units_per_em() as f64
+}
+
+/// Height of capital letters above the baseline in pixels.
+pub fn cap_height(font_size: f64) -> f64 {
+    let face = font();
+    let units = face.capital_height().unwrap_or(face.ascender());
+    units as f64 * font_size / face.units_per_em() as f64
+}
+
+/// Glyph outlines of a line of text starting at `left` on `baseline`, given
+/// in canvas pixels, as shapes in the module coordinates of `layout`.
+pub fn glyph_shapes(
+    text: &str,
+    font_size: f64,
+    left: f64,
+    baseline: f64,
+    layout: &QrLayout,
+) -> Vec<Shape> {
+    let face = font();
+    let scale = font_size / face.units_per_em() as f64;
+    let (ox, oy) = layout.origin();
+    let module = layout.module_size as f64;
+
+    let mut shapes = Vec::new();
+    let mut pen = left;
+    for c in text.chars() {
+        let Some(glyph) = face.glyph_index(c) else {
+            continue;
+        };
+        let mut builder = GlyphPath {
+            ops: Vec::new(),
+            current: (0.0, 0.0),
+            transform: |x: f32, y: f32| {
+                (
+                    (pen + x as f64 * scale - ox as f64) / module,
+                    (baseline - y as f64 * scale - oy as f64) / module,
+                )
+            },
+        };
+        if face.outline_glyph(glyph, &mut builder).is_some() {
+            shapes.push(Shape::Outline(Outline::new(builder.ops)));
+        }
+        pen += face.glyph_hor_advance(glyph).unwrap_or(0) as f64 * scale;
+    }
+    shapes
+}
+
+/// Breaks text into lines no wider than `max_width`: at explicit line
+/// breaks, then between words, and inside words that do not fit on a line
+/// of their own.
+fn wrap(text: &str, max_width: f64, measure: &impl Fn(&str) -> f64) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{} {}", line, word)
+            };
+            if measure(&candidate) <= max_width {
+                line = candidate;
+                continue;
+            }
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            // Split words that are too long by themselves
+            for c in word.chars() {
+                let mut candidate = line.clone();
+                candidate.push(c);
+                if measure(&candidate) > max_width && !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                line.push(c);
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Collects a glyph outline as path operations, converting TrueType
+/// quadratic curves to cubic ones.
+struct GlyphPath<F: Fn(f32, f32) -> (f64, f64)> {
+    ops: Vec<PathOp>,
+    current: (f64, f64),
+    transform: F,
+}
+
+impl<F: Fn(f32, f32) -> (f64, f64)> OutlineBuilder for GlyphPath<F> {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let (x, y) = (self.transform)(x, y);
+        self.ops.push(PathOp::MoveTo(x, y));
+        self.current = (x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let (x, y) = (self.transform)(x, y);
+        self.ops.push(PathOp::LineTo(x, y));
+        self.current = (x, y);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (qx, qy) = (self.transform)(x1, y1);
+        let (x, y) = (self.transform)(x, y);
+        let (x0, y0) = self.current;
+        self.ops.push(PathOp::CubicTo(
+            x0 + 2.0 / 3.0 * (qx - x0),
+            y0 + 2.0 / 3.0 * (qy - y0),
+            x + 2.0 / 3.0 * (qx - x),
+            y + 2.0 / 3.0 * (qy - y),
+            x,
+            y,
+        ));
+        self.current = (x, y);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (x1, y1) = (self.transform)(x1, y1);
+        let (x2, y2) = (self.transform)(x2, y2);
+        let (x, y) = (self.transform)(x, y);
+        self.ops.push(PathOp::CubicTo(x1, y1, x2, y2, x, y));
+        self.current = (x, y);
+    }
+
+    fn close(&mut self) {
+        self.ops.push(PathOp::Close);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::matrix::QrMatrix;
+    use qrcode::{EcLevel, QrCode, Version};
+
+    fn layout() -> QrLayout {
+        // 21 + 2 * 4 = 29 modules of 10 pixels; the symbol spans 40..250
+        let qr_code = QrCode::with_version(b"hello", Version::Normal(1), EcLevel::M).unwrap();
+        QrLayout::compute(&QrMatrix::from_qr_code(&qr_code, 4), 290).unwrap()
+    }
+
+    fn caption(text: &str) -> CaptionOptions {
+        CaptionOptions {
+            text: text.to_string(),
+            font_size: Some(20),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_caption_below_extends_canvas() {
+        let mut layout = layout();
+        let placed = PlacedCaption::new(&caption("example.com"), &mut layout, "#000000").unwrap();
+
+        // One line of 20px DejaVu Sans is 23.3px tall, plus a 10px margin;
+        // the quiet zone already separates it from the symbol
+        assert_eq!((layout.width, layout.height, layout.top), (290, 324, 0));
+        let line = &placed.lines()[0];
+        assert_eq!(line.anchor, 145.0);
+        assert!(line.baseline > 290.0 && line.baseline < 314.0);
+        assert_eq!(placed.color(), "#000000");
+    }
+
+    #[test]
+    fn test_caption_above_moves_code_down() {
+        let mut layout = layout();
+        let options = CaptionOptions {
+            position: CaptionPosition::Above,
+            align: TextAlign::Left,
+            ..caption("Scan me")
+        };
+        let placed = PlacedCaption::new(&options, &mut layout, "#000000").unwrap();
+
+        assert_eq!((layout.top, layout.height), (34, 324));
+        assert_eq!(layout.origin(), (40, 74));
+        assert_eq!(placed.lines()[0].anchor, 40.0);
+    }
+
+    #[test]
+    fn test_wrap_breaks_at_spaces_then_inside_words() {
+        let measure = |text: &str| text.chars().count() as f64;
+        assert_eq!(
+            wrap("scan to join us", 8.0, &measure),
+            ["scan to", "join us"]
+        );
+        assert_eq!(
+            wrap("abcdefghij\nxy", 4.0, &measure),
+            ["abcd", "efgh", "ij", "xy"]
+        );
+    }
+
+    #[test]
+    fn test_caption_limits() {
+        let mut layout = layout();
+        let long = CaptionOptions {
+            wrap: false,
+            ..caption("https://example.com/a/very/long/path")
+        };
+        assert!(PlacedCaption::new(&long, &mut layout, "#000000").is_err());
+
+        let many = caption(&"word ".repeat(60));
+        assert!(PlacedCaption::new(&many, &mut layout, "#000000").is_err());
+
+        match PlacedCaption::new(&caption("tab\there"), &mut layout, "#000000") {
+            Err(ApiError::ValidationError(message)) => assert!(message.contains("'\\t'")),
+            _ => panic!("expected the tab to be rejected"),
+        }
+    }
+
+    #[test]
+    fn test_glyphs_become_outlines() {
+        let mut layout = layout();
+        let placed = PlacedCaption::new(&caption("Hi o"), &mut layout, "#1A237E").unwrap();
+        let layer = placed.layer(&layout);
+
+        // The space has no outline
+        assert_eq!(layer.shapes.len(), 3);
+        assert_eq!(layer.paint, Paint::Solid("#1A237E".to_string()));
+        // Glyphs sit below the symbol, which ends at module 25 (with the quiet zone)
+        assert!(layer.shapes.iter().all(|shape| shape.bounds().1 > 25.0));
+    }
+}
@@ -0,0 +1,324 @@
+/// Encodes `pixels` (row-major, `true` for black) with CCITT Group 4
+/// (ITU-T T.6) compression, as read by fax machines and label printers, and
+/// ends the stream with the end-of-facsimile-block marker.
+///
+/// Every row is coded against the row above it (an imaginary white row for
+/// the first), which suits QR codes well: most rows repeat the previous one
+/// and compress to a handful of vertical mode codes.
+pub fn encode_group4(pixels: &[bool], width: usize, height: usize) -> Vec<u8> {
+    let mut writer = BitWriter::default();
+    let white_row = vec![false; width];
+
+    for y in 0..height {
+        let reference = if y == 0 {
+            &white_row[..]
+        } else {
+            &pixels[(y - 1) * width..y * width]
+        };
+        let coding = &pixels[y * width..(y + 1) * width];
+        encode_row(&mut writer, reference, coding);
+    }
+
+    // EOFB: two EOL codes
+    writer.push("000000000001");
+    writer.push("000000000001");
+    writer.finish()
+}
+
+fn encode_row(writer: &mut BitWriter, reference: &[bool], coding: &[bool]) {
+    let width = coding.len() as isize;
+    // a0 starts on an imaginary white pixel just before the row
+    let mut a0: isize = -1;
+    let mut color = false;
+
+    while a0 < width {
+        let a1 = next_change(coding, a0, !color);
+        let b1 = next_change(reference, a0, !color);
+        let b2 = next_change(reference, b1, color);
+
+        if b2 < a1 {
+            writer.push(PASS);
+            a0 = b2;
+        } else if (a1 - b1).abs() <= 3 {
+            writer.push(VERTICAL[(a1 - b1 + 3) as usize]);
+            a0 = a1;
+            color = !color;
+        } else {
+            let a2 = next_change(coding, a1, color);
+            writer.push(HORIZONTAL);
+            writer.push_run(a1 - a0.max(0), color);
+            writer.push_run(a2 - a1, !color);
+            a0 = a2;
+        }
+    }
+}
+
+/// Position of the first pixel after `from` that starts a run of `color`,
+/// or the row width when there is none. Pixels before the row are white.
+fn next_change(row: &[bool], from: isize, color: bool) -> isize {
+    let start = (from + 1).max(0) as usize;
+    (start..row.len())
+        .find(|&x| row[x] == color && (x > 0 && row[x - 1]) != color)
+        .map_or(row.len() as isize, |x| x as isize)
+}
+
+const PASS: &str = "0001";
+const HORIZONTAL: &str = "001";
+/// Vertical mode codes for a1 - b1 from -3 to 3.
+const VERTICAL: [&str; 7] = ["0000010", "000010", "010", "1", "011", "000011", "0000011"];
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    current: u8,
+    used: u8,
+}
+
+impl BitWriter {
+    fn push(&mut self, code: &str) {
+        for bit in code.bytes() {
+            self.current = (self.current << 1) | (bit == b'1') as u8;
+            self.used += 1;
+            if self.used == 8 {
+                self.bytes.push(self.current);
+                self.current = 0;
+                self.used = 0;
+            }
+        }
+    }
+
+    /// Writes a run length as make-up codes followed by a terminating code.
+    fn push_run(&mut self, mut run: isize, black: bool) {
+        let (terminating, makeup) = if black {
+            (&BLACK_TERMINATING, &BLACK_MAKEUP)
+        } else {
+            (&WHITE_TERMINATING, &WHITE_MAKEUP)
+        };
+
+        while run >= 2560 {
+            self.push(EXTENDED_MAKEUP[EXTENDED_MAKEUP.len() - 1]);
+            run -= 2560;
+        }
+        if run >= 64 {
+            let index = (run / 64 - 1) as usize;
+            if index < makeup.len() {
+                self.push(makeup[index]);
+            } else {
+                self.push(EXTENDED_MAKEUP[index - makeup.len()]);
+            }
+            run %= 64;
+        }
+        self.push(terminating[run as usize]);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.used > 0 {
+            self.bytes.push(self.current << (8 - self.used));
+        }
+        self.bytes
+    }
+}
+
+/// Terminating codes for white runs of 0 to 63 pixels.
+#[rustfmt::skip]
+const WHITE_TERMINATING: [&str; 64] = [
+    "00110101", "000111", "0111", "1000", "1011", "1100", "1110", "1111",
+    "10011", "10100", "00111", "01000", "001000", "000011", "110100", "110101",
+    "101010", "101011", "0100111", "0001100", "0001000", "0010111", "0000011", "0000100",
+    "0101000", "0101011", "0010011", "0100100", "0011000", "00000010", "00000011", "00011010",
+    "00011011", "00010010", "00010011", "00010100", "00010101", "00010110", "00010111", "00101000",
+    "00101001", "00101010", "00101011", "00101100", "00101101", "00000100", "00000101", "00001010",
+    "00001011", "01010010", "01010011", "01010100", "01010101", "00100100", "00100101", "01011000",
+    "01011001", "01011010", "01011011", "01001010", "01001011", "00110010", "00110011", "00110100",
+];
+
+/// Make-up codes for white runs of 64 to 1728 pixels, in steps of 64.
+#[rustfmt::skip]
+const WHITE_MAKEUP: [&str; 27] = [
+    "11011", "10010", "010111", "0110111", "00110110", "00110111", "01100100", "01100101",
+    "01101000", "01100111", "011001100", "011001101", "011010010", "011010011", "011010100",
+    "011010101", "011010110", "011010111", "011011000", "011011001", "011011010", "011011011",
+    "010011000", "010011001", "010011010", "011000", "010011011",
+];
+
+/// Terminating codes for black runs of 0 to 63 pixels.
+#[rustfmt::skip]
+const BLACK_TERMINATING: [&str; 64] = [
+    "0000110111", "010", "11", "10", "011", "0011", "0010", "00011",
+    "000101", "000100", "0000100", "0000101", "0000111", "00000100", "00000111", "000011000",
+    "0000010111", "0000011000", "0000001000", "00001100111", "00001101000", "00001101100", "00000110111", "00000101000",
+    "00000010111", "00000011000", "000011001010", "000011001011", "000011001100", "000011001101", "000001101000", "000001101001",
+    "000001101010", "000001101011", "000011010010", "000011010011", "000011010100", "000011010101", "000011010110", "000011010111",
+    "000001101100", "000001101101", "000011011010", "000011011011", "000001010100", "000001010101", "000001010110", "000001010111",
+    "000001100100", "000001100101", "000001010010", "000001010011", "000000100100", "000000110111", "000000111000", "000000100111",
+    "000000101000", "000001011000", "000001011001", "000000101011", "000000101100", "000001011010", "000001100110", "000001100111",
+];
+
+/// Make-up codes for black runs of 64 to 1728 pixels, in steps of 64.
+#[rustfmt::skip]
+const BLACK_MAKEUP: [&str; 27] = [
+    "0000001111", "000011001000", "000011001001", "000001011011", "000000110011", "000000110100",
+    "000000110101", "0000001101100", "0000001101101", "0000001001010", "0000001001011",
+    "0000001001100", "0000001001101", "0000001110010", "0000001110011", "0000001110100",
+    "0000001110101", "0000001110110", "0000001110111", "0000001010010", "0000001010011",
+    "0000001010100", "0000001010101", "0000001011010", "0000001011011", "0000001100100",
+    "0000001100101",
+];
+
+/// Make-up codes shared by both colors for runs of 1792 to 2560 pixels.
+#[rustfmt::skip]
+const EXTENDED_MAKEUP: [&str; 13] = [
+    "00000001000", "00000001100", "00000001101", "000000010010", "000000010011", "000000010100",
+    "000000010101", "000000010110", "000000010111", "000000011100", "000000011101",
+    "000000011110", "000000011111",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads a run length, mirroring `BitWriter::push_run`.
+    fn read_run(bits: &mut impl Iterator<Item = char>, black: bool) -> usize {
+        let (terminating, makeup) = if black {
+            (&BLACK_TERMINATING, &BLACK_MAKEUP)
+        } else {
+            (&WHITE_TERMINATING, &WHITE_MAKEUP)
+        };
+
+        let mut total = 0;
+        let mut code = String::new();
+        loop {
+            code.push(bits.next().expect("run code ended early"));
+            if let Some(run) = terminating.iter().position(|&c| c == code) {
+                return total + run;
+            }
+            if let Some(index) = makeup
+                .iter()
+                .chain(&EXTENDED_MAKEUP)
+                .position(|&c| c == code)
+            {
+                total += (index + 1) * 64;
+                code.clear();
+            }
+            assert!(code.len() <= 13, "unknown run code {}", code);
+        }
+    }
+
+    /// Minimal T.6 decoder used to check the encoder round-trips.
+    fn decode_group4(data: &[u8], width: usize, height: usize) -> Vec<bool> {
+        let mut bits = data.iter().flat_map(|byte| {
+            (0..8)
+                .rev()
+                .map(move |i| if byte >> i & 1 == 1 { '1' } else { '0' })
+        });
+        let mut pixels = Vec::with_capacity(width * height);
+        let mut reference = vec![false; width];
+
+        for _ in 0..height {
+            let mut coding = vec![false; width];
+            let (mut a0, mut color) = (-1isize, false);
+            let fill = |row: &mut Vec<bool>, from: isize, to: isize, black: bool| {
+                for x in from.max(0)..to {
+                    row[x as usize] = black;
+                }
+            };
+
+            while a0 < width as isize {
+                let b1 = next_change(&reference, a0, !color);
+                let b2 = next_change(&reference, b1, color);
+                let mut code = String::new();
+                loop {
+                    code.push(bits.next().expect("mode code ended early"));
+                    if code == PASS {
+                        fill(&mut coding, a0, b2, color);
+                        a0 = b2;
+                        break;
+                    } else if code == HORIZONTAL {
+                        let start = a0.max(0);
+                        let first = read_run(&mut bits, color) as isize;
+                        let second = read_run(&mut bits, !color) as isize;
+                        fill(&mut coding, start, start + first, color);
+                        fill(&mut coding, start + first, start + first + second, !color);
+                        a0 = start + first + second;
+                        break;
+                    } else if let Some(offset) = VERTICAL.iter().position(|&c| c == code) {
+                        let a1 = b1 + offset as isize - 3;
+                        fill(&mut coding, a0, a1, color);
+                        a0 = a1;
+                        color = !color;
+                        break;
+                    }
+                    assert!(code.len() <= 7, "unknown mode code {}", code);
+                }
+            }
+            pixels.extend_from_slice(&coding);
+            reference = coding;
+        }
+
+        let eofb: String = bits.by_ref().take(24).collect();
+        assert_eq!(eofb, "000000000001000000000001");
+        pixels
+    }
+
+    #[test]
+    fn test_code_tables_are_prefix_free() {
+        for (terminating, makeup) in [
+            (&WHITE_TERMINATING, &WHITE_MAKEUP),
+            (&BLACK_TERMINATING, &BLACK_MAKEUP),
+        ] {
+            let codes: Vec<&str> = terminating
+                .iter()
+                .chain(makeup)
+                .chain(&EXTENDED_MAKEUP)
+                .copied()
+                .collect();
+            for (i, a) in codes.iter().enumerate() {
+                for (j, b) in codes.iter().enumerate() {
+                    assert!(i == j || !b.starts_with(a), "{} is a prefix of {}", a, b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_known_answer() {
+        // Two rows with black pixels 2-5, then a white row
+        let row = [false, false, true, true, true, true, false, false];
+        let pixels: Vec<bool> = [row, row, [false; 8]].concat();
+
+        // Row 0: horizontal (001) white 2 (0111) black 4 (011), V0 (1)
+        // Row 1: V0 V0 V0 (111)
+        // Row 2: pass (0001) over the black run, V0 (1)
+        // EOFB, then padding to a whole byte
+        let expected = [
+            0b0010_1110,
+            0b1111_1100,
+            0b0110_0000,
+            0b0000_0010,
+            0b0000_0000,
+            0b0010_0000,
+        ];
+        assert_eq!(encode_group4(&pixels, 8, 3), expected);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        // A mix of long runs, isolated pixels and rows unlike the one above
+        let (width, height) = (2700, 6);
+        let pixels: Vec<bool> = (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                match y {
+                    0 => x > 5 && x < 2650,
+                    1 => x % 7 == 0 || x % 11 == 3,
+                    2 => x >= 2600,
+                    3 => false,
+                    _ => (x * 31 + y * 17) % 13 < 6,
+                }
+            })
+            .collect();
+
+        let encoded = encode_group4(&pixels, width, height);
+        assert_eq!(decode_group4(&encoded, width, height), pixels);
+    }
+}
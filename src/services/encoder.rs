@@ -0,0 +1,1098 @@
+use std::borrow::Cow;
+
+use qrcode::{
+    bits::Bits,
+    canvas::{Canvas, MaskPattern},
+    ec, optimize,
+    types::{Mode, QrError},
+    EcLevel, QrResult, Version,
+};
+
+use super::{matrix::QrMatrix, rmqr::encode_rmqr};
+use crate::{
+    errors::ApiError,
+    models::{
+        shift_jis_kanji, Charset, EncodingMode, QrCustomization, Segment, Symbology,
+        MAX_APPEND_SYMBOLS,
+    },
+};
+
+/// Mask patterns in the order of their QR code pattern numbers.
+const QR_MASK_PATTERNS: [MaskPattern; 8] = [
+    MaskPattern::Checkerboard,
+    MaskPattern::HorizontalLines,
+    MaskPattern::VerticalLines,
+    MaskPattern::DiagonalLines,
+    MaskPattern::LargeCheckerboard,
+    MaskPattern::Fields,
+    MaskPattern::Diamonds,
+    MaskPattern::Meadow,
+];
+
+/// Mask patterns in the order of their Micro QR pattern numbers.
+const MICRO_QR_MASK_PATTERNS: [MaskPattern; 4] = [
+    MaskPattern::HorizontalLines,
+    MaskPattern::LargeCheckerboard,
+    MaskPattern::Diamonds,
+    MaskPattern::Meadow,
+];
+
+/// Length of a structured append header in bits: the mode indicator, the
+/// symbol's position, the last position and the parity byte.
+const APPEND_HEADER_BITS: usize = 4 + 4 + 4 + 8;
+
+/// An encoded symbol and the segments its content was split into.
+#[derive(Debug)]
+pub struct EncodedSymbol {
+    pub matrix: QrMatrix,
+    pub segments: Vec<Segment>,
+}
+
+/// The symbols of a structured append set in order, and the parity byte
+/// their headers share.
+#[derive(Debug)]
+pub struct EncodedSet {
+    pub symbols: Vec<EncodedSymbol>,
+    pub parity: u8,
+}
+
+/// Mode indicators that come before the segments: a charset's ECI
+/// designator and the FNC1 indicator of GS1 data.
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct Preamble {
+    pub charset: Option<Charset>,
+    pub gs1: bool,
+}
+
+impl Preamble {
+    pub fn of(customization: &QrCustomization) -> Self {
+        Self {
+            charset: customization.charset,
+            gs1: customization
+                .encoding
+                .as_ref()
+                .is_some_and(|encoding| encoding.gs1),
+        }
+    }
+
+    /// Text of a segment as written in its mode. In GS1 data the
+    /// alphanumeric `%` stands for FNC1, so a literal one is doubled.
+    pub fn escape<'a>(&self, segment: &'a Segment) -> Cow<'a, str> {
+        if self.gs1 && segment.mode == EncodingMode::Alphanumeric {
+            Cow::Owned(segment.text.replace('%', "%%"))
+        } else {
+            Cow::Borrowed(&segment.text)
+        }
+    }
+}
+
+/// Position of a symbol in a structured append set.
+#[derive(Debug, Clone, Copy)]
+struct AppendHeader {
+    index: usize,
+    total: usize,
+    parity: u8,
+}
+
+/// Encodes `data` into a symbol at the error correction level the
+/// customization asks for.
+///
+/// The content is split into segments as the encoding options say:
+/// optimally, in one forced mode, or as given. The version is the requested
+/// one, or else the smallest that fits from `min_version` up; Micro QR M1
+/// cannot correct errors and is only used when requested. The mask is the
+/// requested one, or else the pattern with the lowest penalty score. With a
+/// charset, an ECI designator leads the data and byte segments are
+/// transcoded to it; GS1 data starts with FNC1 instead.
+pub fn encode(data: &str, customization: &QrCustomization) -> Result<EncodedSymbol, ApiError> {
+    let level = customization.effective_error_correction();
+    let ec_level = level.to_qrcode_ecc();
+    let symbology = customization.symbology;
+    let (content, fixed) = content_segments(data, customization)?;
+    if symbology == Symbology::Rmqr {
+        return encode_rmqr(&content, fixed.as_deref(), customization);
+    }
+
+    let attempt = |version: u8| {
+        let version = symbology.to_qrcode_version(version);
+        fit(
+            &content,
+            fixed.as_deref(),
+            version,
+            ec_level,
+            None,
+            Preamble::of(customization),
+        )
+        .map(|(codewords, segments)| (version, codewords, segments))
+    };
+    let max_version = symbology.max_version();
+
+    let (version, codewords, segments) = match customization.version {
+        Some(version) => attempt(version).map_err(|_| {
+            let message = match (version + 1..=max_version).find(|&v| attempt(v).is_ok()) {
+                Some(required) => format!(
+                    "Content needs {} at error correction level {:?}; {} is too small",
+                    symbology.version_name(required),
+                    level,
+                    symbology.version_name(version)
+                ),
+                None => format!(
+                    "Content does not fit any {} version at error correction level {:?}",
+                    symbology, level
+                ),
+            };
+            ApiError::ValidationError(message)
+        })?,
+        None => {
+            let first = match symbology {
+                Symbology::MicroQr => 2,
+                _ => 1,
+            };
+            (customization.min_version.unwrap_or(first).max(first)..=max_version)
+                .find_map(|version| attempt(version).ok())
+                .ok_or_else(|| {
+                    let hint = match symbology {
+                        Symbology::Qr => "set structured_append to split it across several codes",
+                        _ => "shorten it or use a QR code",
+                    };
+                    ApiError::ValidationError(format!(
+                        "Content does not fit {} {} at error correction level {:?}; {}",
+                        symbology,
+                        symbology.version_name(max_version),
+                        level,
+                        hint
+                    ))
+                })?
+        }
+    };
+
+    let matrix = draw(version, ec_level, &codewords, customization)?;
+    Ok(EncodedSymbol { matrix, segments })
+}
+
+/// Encodes `data` as a structured append set of QR codes.
+///
+/// Content that fits one symbol of at most the maximum version (the fixed
+/// version, `structured_append.max_version`, or 40) is encoded as a single
+/// plain symbol. Otherwise it is split at character boundaries into the
+/// fewest parts that fit, as evenly as possible, each behind a header
+/// numbering it within the set. Every symbol uses the same version, the
+/// smallest that holds the largest part, so the set prints at one size.
+pub fn encode_set(data: &str, customization: &QrCustomization) -> Result<EncodedSet, ApiError> {
+    let level = customization.effective_error_correction();
+    let ec_level = level.to_qrcode_ecc();
+    let (content, fixed) = content_segments(data, customization)?;
+    // Explicit segments are rejected by validation, so fixed segments are
+    // one forced mode that each part keeps
+    let forced = fixed.as_ref().map(|segments| segments[0].mode);
+    let parity = parity(&content, forced, customization.charset);
+
+    let max_version = customization
+        .version
+        .or(customization
+            .structured_append
+            .as_ref()
+            .and_then(|options| options.max_version))
+        .unwrap_or(Symbology::Qr.max_version());
+    let first = customization
+        .version
+        .or(customization.min_version)
+        .unwrap_or(1);
+    let versions = || (first..=max_version).map(|version| Version::Normal(version as i16));
+
+    let preamble = Preamble::of(customization);
+    if versions().any(|version| {
+        fit(
+            &content,
+            fixed.as_deref(),
+            version,
+            ec_level,
+            None,
+            preamble,
+        )
+        .is_ok()
+    }) {
+        return Ok(EncodedSet {
+            symbols: vec![encode(data, customization)?],
+            parity,
+        });
+    }
+
+    let encode_part = |part: &str, version: Version, header: AppendHeader| {
+        let segments = forced.map(|mode| {
+            vec![Segment {
+                mode,
+                text: part.to_string(),
+            }]
+        });
+        fit(
+            part,
+            segments.as_deref(),
+            version,
+            ec_level,
+            Some(header),
+            preamble,
+        )
+    };
+    // The header takes the same bits whatever its values
+    let header = AppendHeader {
+        index: 0,
+        total: MAX_APPEND_SYMBOLS,
+        parity,
+    };
+    let fits = |part: &str, version: Version| encode_part(part, version, header).is_ok();
+
+    let boundaries: Vec<usize> = content
+        .char_indices()
+        .map(|(index, _)| index)
+        .chain([content.len()])
+        .collect();
+    let largest = Version::Normal(max_version as i16);
+    let mut parts = greedy_parts(&content, &boundaries, |part| fits(part, largest));
+    if parts.len() > MAX_APPEND_SYMBOLS {
+        return Err(ApiError::ValidationError(format!(
+            "Content needs more than {} symbols of version {} at error correction level {:?}; raise structured_append.max_version or lower the error correction level",
+            MAX_APPEND_SYMBOLS, max_version, level
+        )));
+    }
+    let even = even_parts(&content, &boundaries, parts.len());
+    if even.iter().all(|part| fits(part, largest)) {
+        parts = even;
+    }
+
+    let version = versions()
+        .find(|&version| parts.iter().all(|part| fits(part, version)))
+        .unwrap_or(largest);
+    let total = parts.len();
+    let symbols = parts
+        .into_iter()
+        .enumerate()
+        .map(|(index, part)| {
+            let (codewords, segments) = encode_part(
+                part,
+                version,
+                AppendHeader {
+                    index,
+                    total,
+                    parity,
+                },
+            )
+            .map_err(|e| ApiError::GenerationError(format!("Failed to generate QR code: {}", e)))?;
+            let matrix = draw(version, ec_level, &codewords, customization)?;
+            Ok(EncodedSymbol { matrix, segments })
+        })
+        .collect::<Result<Vec<_>, ApiError>>()?;
+    Ok(EncodedSet { symbols, parity })
+}
+
+/// The content after uppercase folding, and the segments the encoding
+/// options fix it to: one forced mode or the explicit segments. `None`
+/// leaves the segmentation to each version. Fails if the charset cannot
+/// represent what would be written in byte segments.
+fn content_segments(
+    data: &str,
+    customization: &QrCustomization,
+) -> Result<(String, Option<Vec<Segment>>), ApiError> {
+    let options = customization.encoding.clone().unwrap_or_default();
+    let content = options.fold(data);
+
+    // Forced and explicit segments are the same at every version; optimal
+    // ones depend on the length fields of the version
+    let fixed = if !options.segments.is_empty() {
+        let spelled: String = options
+            .segments
+            .iter()
+            .map(|segment| segment.text.as_str())
+            .collect();
+        if spelled != content {
+            return Err(ApiError::ValidationError(format!(
+                "Segments spell {:?} but the content is {:?}",
+                spelled, content
+            )));
+        }
+        for (index, segment) in options.segments.iter().enumerate() {
+            segment
+                .mode
+                .check(&segment.text)
+                .map_err(|e| ApiError::ValidationError(format!("Segment {}: {}", index + 1, e)))?;
+        }
+        Some(options.segments.clone())
+    } else if options.mode != EncodingMode::Auto {
+        options
+            .mode
+            .check(&content)
+            .map_err(ApiError::ValidationError)?;
+        Some(vec![Segment {
+            mode: options.mode,
+            text: content.clone(),
+        }])
+    } else {
+        None
+    };
+
+    // Kanji segments are Shift JIS whatever the charset
+    if let Some(charset) = customization.charset {
+        let transcoded: Vec<&str> = match &fixed {
+            Some(segments) => segments
+                .iter()
+                .filter(|segment| segment.mode != EncodingMode::Kanji)
+                .map(|segment| segment.text.as_str())
+                .collect(),
+            None => vec![content.as_str()],
+        };
+        for text in transcoded {
+            charset.encode(text).map_err(ApiError::ValidationError)?;
+        }
+    }
+    Ok((content, fixed))
+}
+
+/// Parity byte of a structured append set: the XOR of every byte of the
+/// content as encoded, Shift JIS in the Kanji mode and otherwise the
+/// charset, UTF-8 by default.
+fn parity(content: &str, forced: Option<EncodingMode>, charset: Option<Charset>) -> u8 {
+    let bytes = match (forced, charset) {
+        (Some(EncodingMode::Kanji), _) => content
+            .chars()
+            .filter_map(shift_jis_kanji)
+            .flat_map(u16::to_be_bytes)
+            .collect(),
+        (_, Some(charset)) => charset.encode(content).unwrap_or_default(),
+        (_, None) => content.as_bytes().to_vec(),
+    };
+    bytes.into_iter().fold(0, |parity, byte| parity ^ byte)
+}
+
+/// Splits the content at the character `boundaries` into parts that each
+/// take as many characters as `fits` allows. Stops once there are more
+/// parts than a set can hold.
+fn greedy_parts<'a>(
+    content: &'a str,
+    boundaries: &[usize],
+    fits: impl Fn(&str) -> bool,
+) -> Vec<&'a str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    while start + 1 < boundaries.len() && parts.len() <= MAX_APPEND_SYMBOLS {
+        // Every part takes at least one character; search for the last
+        // boundary that still fits
+        let (mut low, mut high) = (start + 1, boundaries.len() - 1);
+        while low < high {
+            let middle = (low + high).div_ceil(2);
+            if fits(&content[boundaries[start]..boundaries[middle]]) {
+                low = middle;
+            } else {
+                high = middle - 1;
+            }
+        }
+        parts.push(&content[boundaries[start]..boundaries[low]]);
+        start = low;
+    }
+    parts
+}
+
+/// Splits the content at the character `boundaries` into `count` parts of
+/// nearly the same number of characters.
+fn even_parts<'a>(content: &'a str, boundaries: &[usize], count: usize) -> Vec<&'a str> {
+    let chars = boundaries.len() - 1;
+    (0..count)
+        .map(|index| {
+            &content[boundaries[index * chars / count]..boundaries[(index + 1) * chars / count]]
+        })
+        .collect()
+}
+
+/// Encodes the content into the data codewords of a symbol of `version`,
+/// behind a structured append header if given, in the fixed segments or
+/// else the optimal ones for the version.
+fn fit(
+    content: &str,
+    fixed: Option<&[Segment]>,
+    version: Version,
+    ec_level: EcLevel,
+    header: Option<AppendHeader>,
+    preamble: Preamble,
+) -> QrResult<(Vec<u8>, Vec<Segment>)> {
+    let segments = fixed.map_or_else(|| optimal_segments(content, version), <[Segment]>::to_vec);
+    let codewords = encode_bits(&segments, version, ec_level, header, preamble)?;
+    Ok((codewords, segments))
+}
+
+/// Draws a symbol of `version` holding the data codewords and their error
+/// correction, in the requested mask or else the pattern with the lowest
+/// penalty score.
+fn draw(
+    version: Version,
+    ec_level: EcLevel,
+    codewords: &[u8],
+    customization: &QrCustomization,
+) -> Result<QrMatrix, ApiError> {
+    let (data, ec_data) = ec::construct_codewords(codewords, version, ec_level)
+        .map_err(|e| ApiError::GenerationError(format!("Failed to generate QR code: {}", e)))?;
+    let mut canvas = Canvas::new(version, ec_level);
+    canvas.draw_all_functional_patterns();
+    canvas.draw_data(&data, &ec_data);
+    let canvas = match customization.mask {
+        Some(mask) => {
+            canvas.apply_mask(match customization.symbology {
+                Symbology::MicroQr => MICRO_QR_MASK_PATTERNS[mask as usize],
+                _ => QR_MASK_PATTERNS[mask as usize],
+            });
+            canvas
+        }
+        None => canvas.apply_best_mask(),
+    };
+    Ok(QrMatrix::from_colors(
+        version,
+        canvas.into_colors(),
+        customization.quiet_zone() as usize,
+    ))
+}
+
+/// Splits the content into the numeric, alphanumeric and byte segments
+/// that take the fewest bits at `version`.
+///
+/// Runs of characters are classified here rather than by the qrcode
+/// parser, which reads byte pairs as Shift JIS and would put parts of
+/// UTF-8 characters into Kanji segments. The Kanji mode is only used when
+/// asked for.
+fn optimal_segments(content: &str, version: Version) -> Vec<Segment> {
+    let mut runs: Vec<optimize::Segment> = Vec::new();
+    for (begin, c) in content.char_indices() {
+        let mode = if c.is_ascii_digit() {
+            Mode::Numeric
+        } else if EncodingMode::Alphanumeric.encodes(c) {
+            Mode::Alphanumeric
+        } else {
+            Mode::Byte
+        };
+        let end = begin + c.len_utf8();
+        match runs.last_mut() {
+            Some(run) if run.mode == mode => run.end = end,
+            _ => runs.push(optimize::Segment { mode, begin, end }),
+        }
+    }
+
+    optimize::Optimizer::new(runs.into_iter(), version)
+        .map(|segment| Segment {
+            mode: match segment.mode {
+                Mode::Numeric => EncodingMode::Numeric,
+                Mode::Alphanumeric => EncodingMode::Alphanumeric,
+                Mode::Byte | Mode::Kanji => EncodingMode::Byte,
+            },
+            text: content[segment.begin..segment.end].to_string(),
+        })
+        .collect()
+}
+
+/// Encodes the segments into the data codewords of a symbol of `version`,
+/// with the terminator and padding, failing if they do not fit. A charset's
+/// ECI designator comes first, and byte segments are transcoded to it; GS1
+/// data starts with the FNC1 indicator.
+///
+/// The qrcode crate cannot push arbitrary bits, so a structured append
+/// header is put in front of the encoded segments here, and the terminator
+/// and padding follow as `Bits::push_terminator` adds them.
+fn encode_bits(
+    segments: &[Segment],
+    version: Version,
+    ec_level: EcLevel,
+    header: Option<AppendHeader>,
+    preamble: Preamble,
+) -> QrResult<Vec<u8>> {
+    let mut bits = Bits::new(version);
+    if let Some(charset) = preamble.charset {
+        bits.push_eci_designator(charset.eci())?;
+    }
+    if preamble.gs1 {
+        bits.push_fnc1_first_position()?;
+    }
+    for segment in segments {
+        let text = segment.text.as_bytes();
+        match segment.mode {
+            EncodingMode::Numeric => bits.push_numeric_data(text),
+            EncodingMode::Alphanumeric => {
+                bits.push_alphanumeric_data(preamble.escape(segment).as_bytes())
+            }
+            EncodingMode::Auto | EncodingMode::Byte => match preamble.charset {
+                Some(charset) => {
+                    let transcoded = charset
+                        .encode(&segment.text)
+                        .map_err(|_| QrError::UnsupportedCharacterSet)?;
+                    bits.push_byte_data(&transcoded)
+                }
+                None => bits.push_byte_data(text),
+            },
+            EncodingMode::Kanji => {
+                let shift_jis: Vec<u8> = segment
+                    .text
+                    .chars()
+                    .filter_map(shift_jis_kanji)
+                    .flat_map(u16::to_be_bytes)
+                    .collect();
+                bits.push_kanji_data(&shift_jis)
+            }
+        }?;
+    }
+    let Some(header) = header else {
+        bits.push_terminator(ec_level)?;
+        return Ok(bits.into_bytes());
+    };
+
+    let capacity = bits.max_len(ec_level)?;
+    let length = bits.len();
+    if APPEND_HEADER_BITS + length > capacity {
+        return Err(QrError::DataTooLong);
+    }
+    let fields = 0b0011 << 16
+        | (header.index as u32) << 12
+        | (header.total as u32 - 1) << 8
+        | header.parity as u32;
+    let mut stream: Vec<bool> = (0..APPEND_HEADER_BITS)
+        .rev()
+        .map(|bit| fields >> bit & 1 == 1)
+        .collect();
+    let data = bits.into_bytes();
+    stream.extend((0..length).map(|bit| data[bit / 8] >> (7 - bit % 8) & 1 == 1));
+    let terminator = (capacity - stream.len()).min(4);
+    stream.resize((stream.len() + terminator).div_ceil(8) * 8, false);
+
+    let mut codewords: Vec<u8> = stream
+        .chunks(8)
+        .map(|byte| byte.iter().fold(0, |acc, &bit| acc << 1 | bit as u8))
+        .collect();
+    let padding = capacity / 8 - codewords.len();
+    codewords.extend([0xEC, 0x11].into_iter().cycle().take(padding));
+    Ok(codewords)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{EncodingOptions, ErrorCorrectionLevel};
+
+    fn with_encoding(encoding: EncodingOptions) -> QrCustomization {
+        QrCustomization {
+            encoding: Some(encoding),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_fixed_and_minimum_version() {
+        let customization = QrCustomization::default();
+        assert_eq!(
+            encode("https://example.com", &customization)
+                .unwrap()
+                .matrix
+                .width(),
+            25
+        );
+
+        let fixed = QrCustomization {
+            version: Some(10),
+            ..customization.clone()
+        };
+        assert_eq!(
+            encode("https://example.com", &fixed)
+                .unwrap()
+                .matrix
+                .width(),
+            57
+        );
+
+        let minimum = QrCustomization {
+            min_version: Some(4),
+            ..customization.clone()
+        };
+        assert_eq!(
+            encode("https://example.com", &minimum)
+                .unwrap()
+                .matrix
+                .width(),
+            33
+        );
+        // A minimum below what the content needs has no effect
+        assert_eq!(
+            encode(&"A".repeat(200), &minimum).unwrap().matrix.width(),
+            encode(&"A".repeat(200), &customization)
+                .unwrap()
+                .matrix
+                .width()
+        );
+    }
+
+    #[test]
+    fn test_version_too_small() {
+        let customization = QrCustomization {
+            version: Some(1),
+            error_correction: ErrorCorrectionLevel::H,
+            ..Default::default()
+        };
+        let error = encode("https://example.com/some/longer/path", &customization).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("needs version 5 at error correction level H"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_fixed_mask() {
+        let customization = QrCustomization {
+            version: Some(1),
+            ..Default::default()
+        };
+        let auto = encode("hello", &customization).unwrap().matrix;
+        let masks: Vec<QrMatrix> = (0..8)
+            .map(|mask| {
+                encode(
+                    "hello",
+                    &QrCustomization {
+                        mask: Some(mask),
+                        ..customization.clone()
+                    },
+                )
+                .unwrap()
+                .matrix
+            })
+            .collect();
+
+        let modules = |matrix: &QrMatrix| {
+            (0..21)
+                .flat_map(|y| (0..21).map(move |x| (x, y)))
+                .map(|(x, y)| matrix.is_dark(x, y))
+                .collect::<Vec<_>>()
+        };
+        // Every mask gives a different symbol, and the automatic choice is one of them
+        for (i, a) in masks.iter().enumerate() {
+            for b in &masks[i + 1..] {
+                assert_ne!(modules(a), modules(b));
+            }
+        }
+        assert!(masks.iter().any(|matrix| modules(matrix) == modules(&auto)));
+    }
+
+    #[test]
+    fn test_optimal_segments() {
+        let symbol = encode(
+            "https://example.com/ITEM/12345678901234567890",
+            &QrCustomization::default(),
+        )
+        .unwrap();
+        let modes: Vec<EncodingMode> = symbol.segments.iter().map(|segment| segment.mode).collect();
+        assert_eq!(
+            modes,
+            [
+                EncodingMode::Byte,
+                EncodingMode::Alphanumeric,
+                EncodingMode::Numeric
+            ]
+        );
+        assert_eq!(symbol.segments[2].text, "12345678901234567890");
+
+        // UTF-8 text stays in byte segments instead of being read as Kanji
+        let symbol = encode("東京 2024", &QrCustomization::default()).unwrap();
+        assert_eq!(
+            symbol.segments[0],
+            Segment {
+                mode: EncodingMode::Byte,
+                text: "東京 ".to_string()
+            }
+        );
+        assert!(symbol
+            .segments
+            .iter()
+            .all(|segment| segment.mode != EncodingMode::Kanji));
+        assert_eq!(
+            symbol
+                .segments
+                .iter()
+                .map(|segment| segment.text.as_str())
+                .collect::<String>(),
+            "東京 2024"
+        );
+    }
+
+    #[test]
+    fn test_forced_mode() {
+        let id = "inv-2024-000123-abcdefgh-0099";
+        let byte = encode(
+            id,
+            &with_encoding(EncodingOptions {
+                mode: EncodingMode::Byte,
+                ..Default::default()
+            }),
+        )
+        .unwrap();
+        let error = encode(
+            id,
+            &with_encoding(EncodingOptions {
+                mode: EncodingMode::Alphanumeric,
+                ..Default::default()
+            }),
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("set uppercase"), "{}", error);
+
+        let folded = encode(
+            id,
+            &with_encoding(EncodingOptions {
+                mode: EncodingMode::Alphanumeric,
+                uppercase: true,
+                ..Default::default()
+            }),
+        )
+        .unwrap();
+        assert_eq!(
+            folded.segments,
+            [Segment {
+                mode: EncodingMode::Alphanumeric,
+                text: id.to_ascii_uppercase()
+            }]
+        );
+        assert!(folded.matrix.width() < byte.matrix.width());
+
+        let kanji = encode(
+            "漢字",
+            &with_encoding(EncodingOptions {
+                mode: EncodingMode::Kanji,
+                ..Default::default()
+            }),
+        )
+        .unwrap();
+        assert_eq!(kanji.segments[0].mode, EncodingMode::Kanji);
+    }
+
+    #[test]
+    fn test_explicit_segments() {
+        let segment = |mode, text: &str| Segment {
+            mode,
+            text: text.to_string(),
+        };
+        let segments = vec![
+            segment(EncodingMode::Alphanumeric, "SKU"),
+            segment(EncodingMode::Numeric, "000123"),
+        ];
+        let encoding = EncodingOptions {
+            segments: segments.clone(),
+            ..Default::default()
+        };
+        assert_eq!(
+            encode("SKU000123", &with_encoding(encoding.clone()))
+                .unwrap()
+                .segments,
+            segments
+        );
+
+        let error = encode("SKU000124", &with_encoding(encoding)).unwrap_err();
+        assert!(
+            error.to_string().contains("Segments spell \"SKU000123\""),
+            "{}",
+            error
+        );
+
+        let wrong = EncodingOptions {
+            segments: vec![segment(EncodingMode::Numeric, "SKU1")],
+            ..Default::default()
+        };
+        let error = encode("SKU1", &with_encoding(wrong)).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("Segment 1: Numeric mode cannot encode 'S', 'K', 'U'"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_micro_qr() {
+        let micro = QrCustomization {
+            symbology: Symbology::MicroQr,
+            ..Default::default()
+        };
+        let symbol = encode("12345", &micro).unwrap();
+        // M1 has no error correction, so M2 is the smallest picked automatically
+        assert_eq!((symbol.matrix.width(), symbol.matrix.quiet_zone()), (13, 2));
+        assert_eq!(symbol.matrix.finder_origins(), &[(0, 0)]);
+
+        let m1 = QrCustomization {
+            version: Some(1),
+            error_correction: ErrorCorrectionLevel::L,
+            ..micro.clone()
+        };
+        assert_eq!(encode("12345", &m1).unwrap().matrix.width(), 11);
+        let error = encode("123456", &m1).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("Content needs M2 at error correction level L; M1 is too small"),
+            "{}",
+            error
+        );
+
+        // M2 has no byte mode, so lowercase text needs M3
+        assert_eq!(encode("abc", &micro).unwrap().matrix.width(), 15);
+        let error = encode("https://example.com/a/long/path", &micro).unwrap_err();
+        assert!(
+            error.to_string().contains("does not fit Micro QR M4"),
+            "{}",
+            error
+        );
+
+        let masks: Vec<Vec<bool>> = (0..4)
+            .map(|mask| {
+                let matrix = encode(
+                    "12345",
+                    &QrCustomization {
+                        mask: Some(mask),
+                        ..micro.clone()
+                    },
+                )
+                .unwrap()
+                .matrix;
+                (0..13)
+                    .flat_map(|y| (0..13).map(move |x| (x, y)))
+                    .map(|(x, y)| matrix.is_dark(x, y))
+                    .collect()
+            })
+            .collect();
+        assert!(masks
+            .iter()
+            .enumerate()
+            .all(|(i, a)| masks[i + 1..].iter().all(|b| a != b)));
+    }
+
+    #[test]
+    fn test_structured_append_header() {
+        let segments = [Segment {
+            mode: EncodingMode::Byte,
+            text: "AB".to_string(),
+        }];
+        let header = AppendHeader {
+            index: 2,
+            total: 5,
+            parity: 0xA5,
+        };
+        let codewords = encode_bits(
+            &segments,
+            Version::Normal(1),
+            EcLevel::L,
+            Some(header),
+            Preamble::default(),
+        )
+        .unwrap();
+        // 0011, index 2, last index 4, parity, then the byte segment, the
+        // terminator and padding
+        assert_eq!(
+            codewords[..9],
+            [0x32, 0x4A, 0x54, 0x02, 0x41, 0x42, 0x00, 0xEC, 0x11]
+        );
+        assert_eq!(codewords.len(), 19);
+
+        let long = [Segment {
+            mode: EncodingMode::Byte,
+            text: "x".repeat(16),
+        }];
+        assert!(encode_bits(
+            &long,
+            Version::Normal(1),
+            EcLevel::L,
+            None,
+            Preamble::default()
+        )
+        .is_ok());
+        assert!(encode_bits(
+            &long,
+            Version::Normal(1),
+            EcLevel::L,
+            Some(header),
+            Preamble::default()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_charset_designator() {
+        let segments = [Segment {
+            mode: EncodingMode::Byte,
+            text: "é".to_string(),
+        }];
+        let codewords = encode_bits(
+            &segments,
+            Version::Normal(1),
+            EcLevel::L,
+            None,
+            Preamble {
+                charset: Some(Charset::Iso8859_1),
+                gs1: false,
+            },
+        )
+        .unwrap();
+        // ECI 0111 with assignment 3, then one byte in ISO-8859-1
+        assert_eq!(codewords[..5], [0x70, 0x34, 0x01, 0xE9, 0x00]);
+        let header = AppendHeader {
+            index: 0,
+            total: 2,
+            parity: 0,
+        };
+        let codewords = encode_bits(
+            &segments,
+            Version::Normal(1),
+            EcLevel::L,
+            Some(header),
+            Preamble {
+                charset: Some(Charset::Utf8),
+                gs1: false,
+            },
+        )
+        .unwrap();
+        // The structured append header comes before the designator
+        assert_eq!(codewords[..5], [0x30, 0x10, 0x07, 0x1A, 0x40]);
+
+        let customization = QrCustomization {
+            charset: Some(Charset::Iso8859_1),
+            ..Default::default()
+        };
+        let symbol = encode("Crème brûlée 250g", &customization).unwrap();
+        assert_eq!(
+            symbol
+                .segments
+                .iter()
+                .map(|segment| segment.text.as_str())
+                .collect::<String>(),
+            "Crème brûlée 250g"
+        );
+        let error = encode("Crème 5€", &customization).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("iso-8859-1 cannot represent '€'"),
+            "{}",
+            error
+        );
+
+        // Kanji segments are Shift JIS whatever the charset
+        let kanji = QrCustomization {
+            encoding: Some(EncodingOptions {
+                mode: EncodingMode::Kanji,
+                ..Default::default()
+            }),
+            ..customization.clone()
+        };
+        assert!(encode("漢字", &kanji).is_ok());
+        let rmqr = QrCustomization {
+            symbology: Symbology::Rmqr,
+            charset: Some(Charset::ShiftJis),
+            ..Default::default()
+        };
+        assert!(encode("東京タワー", &rmqr).is_ok());
+    }
+
+    #[test]
+    fn test_gs1_mode() {
+        let gs1 = Preamble {
+            charset: None,
+            gs1: true,
+        };
+        let segments = [Segment {
+            mode: EncodingMode::Alphanumeric,
+            text: "A%".to_string(),
+        }];
+        let codewords = encode_bits(&segments, Version::Normal(1), EcLevel::L, None, gs1).unwrap();
+        // FNC1 0101, then alphanumeric 0010 with the literal % doubled to a
+        // count of 3
+        assert_eq!(codewords[..2], [0x52, 0x01]);
+        assert_eq!(codewords[2] >> 7, 1);
+
+        let customization = with_encoding(EncodingOptions {
+            gs1: true,
+            ..Default::default()
+        });
+        let symbol = encode(
+            "01095011015300031725123110AB-12\u{1D}210042",
+            &customization,
+        )
+        .unwrap();
+        assert_eq!(
+            symbol.segments[0],
+            Segment {
+                mode: EncodingMode::Numeric,
+                text: "01095011015300031725123110".to_string()
+            }
+        );
+        let rmqr = QrCustomization {
+            symbology: Symbology::Rmqr,
+            ..customization
+        };
+        assert!(encode("0109501101530003", &rmqr).is_ok());
+    }
+
+    #[test]
+    fn test_structured_append_set() {
+        use crate::models::StructuredAppendOptions;
+
+        let split = |max_version| QrCustomization {
+            structured_append: Some(StructuredAppendOptions {
+                max_version: Some(max_version),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let content = format!("config:{}", "ab12".repeat(40));
+
+        // Content that fits stays one plain symbol
+        let single = encode_set("https://example.com", &split(40)).unwrap();
+        assert_eq!(single.symbols.len(), 1);
+
+        let set = encode_set(&content, &split(3)).unwrap();
+        assert_eq!(
+            set.parity,
+            content.bytes().fold(0, |parity, byte| parity ^ byte)
+        );
+        assert!(
+            (3..=16).contains(&set.symbols.len()),
+            "{}",
+            set.symbols.len()
+        );
+        assert!(set
+            .symbols
+            .iter()
+            .all(|symbol| symbol.matrix.width() == set.symbols[0].matrix.width()));
+        assert!(set.symbols[0].matrix.width() <= 29);
+        let spelled: String = set
+            .symbols
+            .iter()
+            .flat_map(|symbol| &symbol.segments)
+            .map(|segment| segment.text.as_str())
+            .collect();
+        assert_eq!(spelled, content);
+
+        let error = encode_set(&"x".repeat(400), &split(1)).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("more than 16 symbols of version 1"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_even_parts() {
+        let content = "aé漢bcdefg";
+        let boundaries: Vec<usize> = content
+            .char_indices()
+            .map(|(index, _)| index)
+            .chain([content.len()])
+            .collect();
+        assert_eq!(even_parts(content, &boundaries, 3), ["aé漢", "bcd", "efg"]);
+        assert_eq!(
+            greedy_parts(content, &boundaries, |part| part.chars().count() <= 4),
+            ["aé漢b", "cdef", "g"]
+        );
+    }
+}
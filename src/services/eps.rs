@@ -0,0 +1,303 @@
+use std::fmt::Write as _;
+use std::io::Write as _;
+
+use image::RgbaImage;
+
+use flate2::{write::ZlibEncoder, Compression};
+
+use super::{
+    layout::QrLayout,
+    logo::PlacedLogo,
+    paint::Paint,
+    pdf::{path_ops, points_per_pixel, rgb, shading_dictionary},
+    shapes::Layer,
+    svg::{fixed, num},
+};
+use crate::{
+    errors::ApiError,
+    models::{composite, hex_to_rgba, GradientStop, PrintSize, QrColors},
+};
+
+/// Pixels of logo image embedded per point, as for PDF output.
+const LOGO_RESOLUTION: f64 = 4.0;
+const MAX_LOGO_PIXELS: f64 = 2048.0;
+
+/// Renders the layers as Encapsulated PostScript (LanguageLevel 3).
+///
+/// The bounding box covers the whole canvas at one point per pixel, or the
+/// physical size when one was requested, and the modules are vector paths
+/// in the same module coordinates as the PDF renderer. PostScript has no
+/// transparency, so translucent colors are flattened onto the background as
+/// it would be seen (see `QrColors::effective_background`), and a fully
+/// transparent background is simply not painted.
+pub fn render_eps(
+    layers: &[Layer],
+    layout: &QrLayout,
+    print: Option<&PrintSize>,
+    colors: &QrColors,
+    logo: Option<&PlacedLogo>,
+) -> Result<Vec<u8>, ApiError> {
+    let (width, height) = (layout.width, layout.height);
+    let points = points_per_pixel(layout, print);
+    let (page_width, page_height) = (width as f64 * points, height as f64 * points);
+    let scale = layout.module_size as f64;
+    let (ox, oy) = layout.origin();
+    let base = colors
+        .effective_background()
+        .map_err(ApiError::ValidationError)?;
+
+    let mut eps = String::new();
+    let _ = write!(
+        eps,
+        concat!(
+            "%!PS-Adobe-3.0 EPSF-3.0\n",
+            "%%BoundingBox: 0 0 {bounds_x} {bounds_y}\n",
+            "%%HiResBoundingBox: 0 0 {page_x} {page_y}\n",
+            "%%Creator: qr-api\n",
+            "%%LanguageLevel: 3\n",
+            "%%Pages: 1\n",
+            "%%EndComments\n",
+            "%%BeginProlog\n",
+            "/m {{moveto}} bind def /l {{lineto}} bind def\n",
+            "/c {{curveto}} bind def /h {{closepath}} bind def\n",
+            "%%EndProlog\n",
+            "%%Page: 1 1\n",
+            "gsave\n",
+            // Flip to the top-left origin used by every other renderer and
+            // scale canvas pixels to the page
+            "0 {page_y} translate {points} {flipped} scale\n",
+        ),
+        bounds_x = page_width.ceil(),
+        bounds_y = page_height.ceil(),
+        page_x = num(page_width),
+        page_y = num(page_height),
+        points = fixed(points, 6),
+        flipped = fixed(-points, 6),
+    );
+
+    if hex_to_rgba(&colors.background)
+        .map_err(ApiError::ValidationError)?
+        .3
+        > 0
+    {
+        let (r, g, b) = base;
+        let _ = writeln!(
+            eps,
+            "{} setrgbcolor 0 0 {} {} rectfill",
+            rgb(r, g, b),
+            width,
+            height
+        );
+    }
+
+    let _ = writeln!(
+        eps,
+        "{} {} translate {} {} scale",
+        ox,
+        oy,
+        num(scale),
+        num(scale)
+    );
+    for layer in layers {
+        fill_layer(&mut eps, layer, layout, base)?;
+    }
+
+    if let Some(logo) = logo {
+        // The logo sits on its plate, or on the background where there is none
+        let behind = match logo.plate_color() {
+            Some(plate) => composite(hex_to_rgba(plate).map_err(ApiError::ValidationError)?, base),
+            None => base,
+        };
+        let (x, y, width, height) = logo.bounds();
+        let pixels = |modules: f64| {
+            (modules * scale * LOGO_RESOLUTION)
+                .clamp(1.0, MAX_LOGO_PIXELS)
+                .round() as u32
+        };
+        let image = logo.render(pixels(width), pixels(height))?;
+        write_image(&mut eps, &image, (x, y, width, height), behind)?;
+        for layer in logo.patterns() {
+            fill_layer(&mut eps, layer, layout, base)?;
+        }
+    }
+
+    eps.push_str("grestore\nshowpage\n%%Trailer\n%%EOF\n");
+    Ok(eps.into_bytes())
+}
+
+/// Fills one layer's shapes with its paint, in module coordinates, with
+/// translucent colors flattened onto the opaque `base`.
+fn fill_layer(
+    eps: &mut String,
+    layer: &Layer,
+    layout: &QrLayout,
+    base: (u8, u8, u8),
+) -> Result<(), ApiError> {
+    let flatten = |color: &str| -> Result<String, ApiError> {
+        let (r, g, b) = composite(hex_to_rgba(color).map_err(ApiError::ValidationError)?, base);
+        Ok(rgb(r, g, b))
+    };
+
+    let _ = writeln!(eps, "gsave newpath");
+    for shape in &layer.shapes {
+        path_ops(eps, &shape.path());
+    }
+    match &layer.paint {
+        Paint::Solid(color) => {
+            let _ = writeln!(eps, "{} setrgbcolor fill", flatten(color)?);
+        }
+        Paint::Gradient(gradient) => {
+            let mut flattened = gradient.clone();
+            for stop in &mut flattened.stops {
+                let (r, g, b) = composite(
+                    hex_to_rgba(&stop.color).map_err(ApiError::ValidationError)?,
+                    base,
+                );
+                *stop = GradientStop {
+                    offset: stop.offset,
+                    color: format!("#{:02X}{:02X}{:02X}", r, g, b),
+                };
+            }
+            let shading =
+                shading_dictionary(&flattened, (layout.modules as f64, layout.rows as f64))?;
+            let _ = writeln!(eps, "clip\n{} shfill", shading);
+        }
+        // Clip to the shapes and draw the image through them
+        Paint::Image(fill) => {
+            let _ = writeln!(eps, "clip newpath");
+            write_image(
+                eps,
+                &fill.image,
+                (fill.x, fill.y, fill.size, fill.size),
+                base,
+            )?;
+        }
+    }
+    let _ = writeln!(eps, "grestore");
+    Ok(())
+}
+
+/// Draws an image into the box `(x, y, width, height)` of the current
+/// coordinates, flattening its alpha onto the opaque `behind` color.
+fn write_image(
+    eps: &mut String,
+    image: &RgbaImage,
+    (x, y, width, height): (f64, f64, f64, f64),
+    behind: (u8, u8, u8),
+) -> Result<(), ApiError> {
+    let mut samples = Vec::with_capacity((image.width() * image.height() * 3) as usize);
+    for pixel in image.pixels() {
+        let (r, g, b) = composite((pixel[0], pixel[1], pixel[2], pixel[3]), behind);
+        samples.extend_from_slice(&[r, g, b]);
+    }
+
+    let _ = write!(
+        eps,
+        concat!(
+            "gsave {x} {y} translate {width} {height} scale\n",
+            "/DeviceRGB setcolorspace\n",
+            "<< /ImageType 1 /Width {w} /Height {h} /BitsPerComponent 8 /Decode [0 1 0 1 0 1]\n",
+            "/ImageMatrix [{w} 0 0 {h} 0 0]\n",
+            "/DataSource currentfile /ASCII85Decode filter /FlateDecode filter >> image\n",
+            "{data}\n",
+            "grestore\n"
+        ),
+        x = num(x),
+        y = num(y),
+        width = num(width),
+        height = num(height),
+        w = image.width(),
+        h = image.height(),
+        data = ascii85(&deflate(&samples)?),
+    );
+    Ok(())
+}
+
+fn deflate(data: &[u8]) -> Result<Vec<u8>, ApiError> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(data)
+        .and_then(|_| encoder.finish())
+        .map_err(|e| ApiError::GenerationError(format!("Failed to compress EPS image: {}", e)))
+}
+
+/// ASCII85-encodes data with its `~>` terminator, wrapped at 76 columns.
+fn ascii85(data: &[u8]) -> String {
+    let mut encoded = String::new();
+    for chunk in data.chunks(4) {
+        let mut group = [0u8; 4];
+        group[..chunk.len()].copy_from_slice(chunk);
+        let mut value = u32::from_be_bytes(group);
+
+        if chunk.len() == 4 && value == 0 {
+            encoded.push('z');
+            continue;
+        }
+        let mut digits = [0u8; 5];
+        for digit in digits.iter_mut().rev() {
+            *digit = (value % 85) as u8 + b'!';
+            value /= 85;
+        }
+        // A partial final group emits one more character than its bytes
+        encoded.extend(digits[..chunk.len() + 1].iter().map(|&d| d as char));
+    }
+
+    let mut wrapped = String::with_capacity(encoded.len() + encoded.len() / 76 + 2);
+    for (index, ch) in encoded.chars().enumerate() {
+        if index > 0 && index % 76 == 0 {
+            wrapped.push('\n');
+        }
+        wrapped.push(ch);
+    }
+    wrapped.push_str("~>");
+    wrapped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{EyeStyle, ModuleStyle};
+    use crate::services::{matrix::QrMatrix, shapes::symbol_layers};
+    use qrcode::QrCode;
+
+    fn eps_for(colors: &QrColors) -> String {
+        let qr_code = QrCode::new(b"hello").unwrap();
+        let matrix = QrMatrix::from_qr_code(&qr_code, 4);
+        let layout = QrLayout::compute(&matrix, 300).unwrap();
+        let layers = symbol_layers(&matrix, &ModuleStyle::Rounded, &EyeStyle::default(), colors);
+        String::from_utf8(render_eps(&layers, &layout, None, colors, None).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_render_eps_header_and_paths() {
+        let eps = eps_for(&QrColors::default());
+
+        assert!(eps.starts_with("%!PS-Adobe-3.0 EPSF-3.0\n"));
+        assert!(eps.contains("%%BoundingBox: 0 0 300 300\n"));
+        assert!(eps.contains("1 1 1 setrgbcolor 0 0 300 300 rectfill"));
+        assert!(eps.contains("0 0 0 setrgbcolor fill"));
+        assert!(eps.contains(" c\n"));
+        assert!(eps.trim_end().ends_with("%%EOF"));
+    }
+
+    #[test]
+    fn test_translucent_colors_are_flattened() {
+        let colors = QrColors {
+            foreground: "#00000080".to_string(),
+            background: "transparent".to_string(),
+            ..Default::default()
+        };
+        let eps = eps_for(&colors);
+
+        // No background is painted; half black over white is mid gray
+        assert!(!eps.contains("rectfill"));
+        assert!(eps.contains("0.498 0.498 0.498 setrgbcolor fill"));
+    }
+
+    #[test]
+    fn test_ascii85() {
+        assert_eq!(ascii85(b"Man "), "9jqo^~>");
+        assert_eq!(ascii85(&[0, 0, 0, 0]), "z~>");
+        assert_eq!(ascii85(b"."), "/c~>");
+    }
+}
@@ -0,0 +1,488 @@
+use super::{
+    caption::{cap_height, check_drawable, glyph_shapes, text_width},
+    layout::QrLayout,
+    matrix::QrMatrix,
+    paint::Paint,
+    shapes::{Layer, Outline, PathOp, RoundedRect, Shape},
+};
+use crate::{
+    errors::ApiError,
+    models::{FrameOptions, FrameStyle, QrColors},
+};
+
+/// Smallest font size the frame text may shrink to in order to fit.
+const MIN_FRAME_FONT_SIZE: f64 = 6.0;
+
+/// Share of its box the frame text may fill: its height before shrinking,
+/// and its width at most.
+const TEXT_HEIGHT: f64 = 0.5;
+const TEXT_WIDTH: f64 = 0.88;
+
+/// Rectangle on the canvas in pixels: `(x, y, width, height)`.
+type Area = (f64, f64, f64, f64);
+
+/// Outline of a frame style in canvas pixels, with the openings left for
+/// the code and the text.
+struct Artwork {
+    /// Closed contours filled with the nonzero rule; holes wind the other way.
+    contours: Vec<PathOp>,
+    code: Area,
+    text: Area,
+}
+
+/// Frame text fitted into its box, in canvas pixels.
+struct FrameText {
+    text: String,
+    font_size: f64,
+    left: f64,
+    baseline: f64,
+}
+
+/// A frame laid out on a square canvas of the requested size, with the
+/// code shrunk to fit the opening it leaves.
+pub struct PlacedFrame {
+    size: u32,
+    contours: Vec<PathOp>,
+    /// The code area: `(x, y, size)` in whole pixels.
+    code_area: (u32, u32, u32),
+    text: Option<FrameText>,
+    color: String,
+    text_color: String,
+}
+
+impl PlacedFrame {
+    pub fn new(options: &FrameOptions, colors: &QrColors, size: u32) -> Result<Self, ApiError> {
+        let text = options.text();
+        if let Some(text) = text {
+            check_drawable(text, "Frame text")?;
+        }
+
+        let canvas = size as f64;
+        let stroke = (canvas * 0.03).max(2.0);
+        let radius = canvas * 0.06;
+        let artwork = match options.style {
+            FrameStyle::Banner => banner(canvas, stroke, radius),
+            FrameStyle::Bubble => bubble(canvas, stroke, radius, text.is_some()),
+            FrameStyle::Ticket => ticket(canvas, stroke, radius),
+            FrameStyle::Phone => phone(canvas),
+        };
+
+        let (x, y, width, height) = artwork.code;
+        let side = width.min(height).floor();
+        let code_area = (
+            (x + (width - side) / 2.0).round() as u32,
+            (y + (height - side) / 2.0).round() as u32,
+            side as u32,
+        );
+
+        Ok(Self {
+            size,
+            contours: artwork.contours,
+            code_area,
+            text: text
+                .map(|text| fit_text(text, artwork.text, size))
+                .transpose()?,
+            color: options.color(colors).to_string(),
+            text_color: options.text_color(colors),
+        })
+    }
+
+    /// Layout of the code inside the frame, on a canvas of the frame's size.
+    pub fn layout(&self, matrix: &QrMatrix) -> Result<QrLayout, ApiError> {
+        let (x, y, side) = self.code_area;
+        let total_modules = matrix.total_width() as u32;
+        if side < total_modules {
+            return Err(ApiError::ValidationError(format!(
+                "A {}px frame leaves {}px for the code, which is too small for a {}-module code including its quiet zone; use a larger size or a smaller border_width",
+                self.size, side, total_modules
+            )));
+        }
+
+        let mut layout = QrLayout::compute(matrix, side)?;
+        layout.place(self.size, self.size, x, y);
+        Ok(layout)
+    }
+
+    /// The frame and its text as layers, in the module coordinates of
+    /// `layout`.
+    pub fn layers(&self, layout: &QrLayout) -> Vec<Layer> {
+        let (ox, oy) = layout.origin();
+        let module = layout.module_size as f64;
+        let to_modules = |x: f64, y: f64| ((x - ox as f64) / module, (y - oy as f64) / module);
+        let ops = self
+            .contours
+            .iter()
+            .map(|op| match *op {
+                PathOp::MoveTo(x, y) => {
+                    let (x, y) = to_modules(x, y);
+                    PathOp::MoveTo(x, y)
+                }
+                PathOp::LineTo(x, y) => {
+                    let (x, y) = to_modules(x, y);
+                    PathOp::LineTo(x, y)
+                }
+                PathOp::CubicTo(x1, y1, x2, y2, x, y) => {
+                    let ((x1, y1), (x2, y2), (x, y)) =
+                        (to_modules(x1, y1), to_modules(x2, y2), to_modules(x, y));
+                    PathOp::CubicTo(x1, y1, x2, y2, x, y)
+                }
+                PathOp::Close => PathOp::Close,
+            })
+            .collect();
+
+        let mut layers = vec![Layer {
+            paint: Paint::Solid(self.color.clone()),
+            shapes: vec![Shape::Outline(Outline::new(ops))],
+        }];
+        if let Some(text) = &self.text {
+            layers.push(Layer {
+                paint: Paint::Solid(self.text_color.clone()),
+                shapes: glyph_shapes(&text.text, text.font_size, text.left, text.baseline, layout),
+            });
+        }
+        layers
+    }
+}
+
+/// Rounded border that widens into a banner below the code.
+fn banner(canvas: f64, stroke: f64, radius: f64) -> Artwork {
+    let band = canvas * 0.18;
+    let inner = (radius - stroke).max(0.0);
+    let opening = (
+        stroke,
+        stroke,
+        canvas - 2.0 * stroke,
+        canvas - band - stroke,
+    );
+
+    let mut contours = RoundedRect::uniform(0.0, 0.0, canvas, canvas, radius).path(false);
+    contours.extend(
+        RoundedRect {
+            x: opening.0,
+            y: opening.1,
+            width: opening.2,
+            height: opening.3,
+            radii: [inner, inner, 0.0, 0.0],
+        }
+        .path(true),
+    );
+
+    Artwork {
+        contours,
+        code: opening,
+        text: (0.0, canvas - band, canvas, band),
+    }
+}
+
+/// Rounded speech bubble with a tail below it and the text above the code.
+fn bubble(canvas: f64, stroke: f64, radius: f64, has_text: bool) -> Artwork {
+    let tail = canvas * 0.1;
+    let band = if has_text { canvas * 0.15 } else { 0.0 };
+    let body = canvas - tail;
+    let inner = (radius - stroke).max(0.0);
+
+    let mut contours = RoundedRect::uniform(0.0, 0.0, canvas, body, radius).path(false);
+    contours.extend(
+        RoundedRect::uniform(
+            stroke,
+            stroke,
+            canvas - 2.0 * stroke,
+            body - 2.0 * stroke,
+            inner,
+        )
+        .path(true),
+    );
+    // The tail overlaps the bottom edge in the same direction, so the
+    // nonzero rule merges the two
+    contours.extend([
+        PathOp::MoveTo(canvas * 0.42, body - stroke),
+        PathOp::LineTo(canvas * 0.58, body - stroke),
+        PathOp::LineTo(canvas * 0.4, canvas),
+        PathOp::Close,
+    ]);
+
+    Artwork {
+        contours,
+        code: (
+            stroke,
+            stroke + band,
+            canvas - 2.0 * stroke,
+            body - 2.0 * stroke - band,
+        ),
+        text: (stroke, stroke, canvas - 2.0 * stroke, band),
+    }
+}
+
+/// Solid ticket with a window for the code and notches on both sides of a
+/// perforated stub that carries the text.
+fn ticket(canvas: f64, stroke: f64, radius: f64) -> Artwork {
+    let stub = canvas * 0.2;
+    let perforation = canvas - stub;
+    let notch = canvas * 0.05;
+
+    let mut contours = vec![
+        PathOp::MoveTo(radius, 0.0),
+        PathOp::LineTo(canvas - radius, 0.0),
+    ];
+    arc(&mut contours, (canvas - radius, radius), radius, -90.0, 0.0);
+    contours.push(PathOp::LineTo(canvas, perforation - notch));
+    arc(&mut contours, (canvas, perforation), notch, 270.0, 90.0);
+    contours.push(PathOp::LineTo(canvas, canvas - radius));
+    arc(
+        &mut contours,
+        (canvas - radius, canvas - radius),
+        radius,
+        0.0,
+        90.0,
+    );
+    contours.push(PathOp::LineTo(radius, canvas));
+    arc(
+        &mut contours,
+        (radius, canvas - radius),
+        radius,
+        90.0,
+        180.0,
+    );
+    contours.push(PathOp::LineTo(0.0, perforation + notch));
+    arc(&mut contours, (0.0, perforation), notch, 90.0, -90.0);
+    contours.push(PathOp::LineTo(0.0, radius));
+    arc(&mut contours, (radius, radius), radius, 180.0, 270.0);
+    contours.push(PathOp::Close);
+
+    let window = (
+        stroke,
+        stroke,
+        canvas - 2.0 * stroke,
+        perforation - notch - 2.0 * stroke,
+    );
+    contours.extend(
+        RoundedRect::uniform(
+            window.0,
+            window.1,
+            window.2,
+            window.3,
+            (radius - stroke).max(0.0),
+        )
+        .path(true),
+    );
+
+    // Dashes cut out along the perforation, centered between the notches
+    let (dash, thickness) = (canvas * 0.025, (stroke / 2.0).max(1.0));
+    let span = canvas - 4.0 * notch;
+    let count = ((span + dash) / (2.0 * dash)).floor();
+    let start = (canvas - (count * 2.0 - 1.0) * dash) / 2.0;
+    for index in 0..count as usize {
+        let x = start + index as f64 * 2.0 * dash;
+        contours.extend(
+            RoundedRect::square(x, perforation - thickness / 2.0, dash, thickness).path(true),
+        );
+    }
+
+    Artwork {
+        contours,
+        code: window,
+        text: (notch, perforation, canvas - 2.0 * notch, stub),
+    }
+}
+
+/// Portrait phone with the code on its screen and the text on the bottom
+/// bezel.
+fn phone(canvas: f64) -> Artwork {
+    let width = canvas * 0.68;
+    let left = (canvas - width) / 2.0;
+    let radius = width * 0.14;
+    let (side, top, bottom) = (width * 0.06, canvas * 0.12, canvas * 0.16);
+    let screen = (left + side, top, width - 2.0 * side, canvas - top - bottom);
+    let (speaker_width, speaker_height) = (width * 0.24, top * 0.14);
+
+    let mut contours = RoundedRect::uniform(left, 0.0, width, canvas, radius).path(false);
+    contours.extend(
+        RoundedRect::uniform(screen.0, screen.1, screen.2, screen.3, radius * 0.3).path(true),
+    );
+    contours.extend(
+        RoundedRect::uniform(
+            (canvas - speaker_width) / 2.0,
+            (top - speaker_height) / 2.0,
+            speaker_width,
+            speaker_height,
+            speaker_height / 2.0,
+        )
+        .path(true),
+    );
+
+    Artwork {
+        contours,
+        code: screen,
+        text: (screen.0, canvas - bottom, screen.2, bottom),
+    }
+}
+
+/// Appends a circular arc from `start` to `end` degrees, measured clockwise
+/// from the positive x axis, as cubic curves of at most a quarter turn.
+/// The path must already be at the start of the arc.
+fn arc(ops: &mut Vec<PathOp>, (cx, cy): (f64, f64), radius: f64, start: f64, end: f64) {
+    let segments = ((end - start).abs() / 90.0).ceil().max(1.0);
+    let step = (end - start) / segments;
+    for index in 0..segments as usize {
+        let (a0, a1) = (
+            (start + step * index as f64).to_radians(),
+            (start + step * (index + 1) as f64).to_radians(),
+        );
+        let handle = 4.0 / 3.0 * ((a1 - a0) / 4.0).tan() * radius;
+        let ((sin0, cos0), (sin1, cos1)) = (a0.sin_cos(), a1.sin_cos());
+        let (x0, y0) = (cx + radius * cos0, cy + radius * sin0);
+        let (x1, y1) = (cx + radius * cos1, cy + radius * sin1);
+        ops.push(PathOp::CubicTo(
+            x0 - handle * sin0,
+            y0 + handle * cos0,
+            x1 + handle * sin1,
+            y1 - handle * cos1,
+            x1,
+            y1,
+        ));
+    }
+}
+
+/// Centers one line of text in `area`, shrinking it to fit the width.
+fn fit_text(text: &str, (x, y, width, height): Area, size: u32) -> Result<FrameText, ApiError> {
+    let mut font_size = height * TEXT_HEIGHT;
+    let natural = text_width(text, font_size);
+    if natural > width * TEXT_WIDTH {
+        font_size *= width * TEXT_WIDTH / natural;
+    }
+    if font_size < MIN_FRAME_FONT_SIZE {
+        return Err(ApiError::ValidationError(format!(
+            "Frame text \"{}\" does not fit a {}px frame; shorten it or use a larger size",
+            text, size
+        )));
+    }
+
+    Ok(FrameText {
+        text: text.to_string(),
+        font_size,
+        left: x + (width - text_width(text, font_size)) / 2.0,
+        baseline: y + (height + cap_height(font_size)) / 2.0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qrcode::{EcLevel, QrCode, Version};
+
+    fn matrix_with_quiet_zone(quiet_zone: usize) -> QrMatrix {
+        let qr_code = QrCode::with_version(b"hello", Version::Normal(1), EcLevel::M).unwrap();
+        QrMatrix::from_qr_code(&qr_code, quiet_zone)
+    }
+
+    fn matrix() -> QrMatrix {
+        matrix_with_quiet_zone(4)
+    }
+
+    fn frame(style: FrameStyle) -> FrameOptions {
+        FrameOptions {
+            style,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_every_frame_keeps_the_canvas_size() {
+        let colors = QrColors::default();
+        for style in [
+            FrameStyle::Banner,
+            FrameStyle::Bubble,
+            FrameStyle::Ticket,
+            FrameStyle::Phone,
+        ] {
+            let placed = PlacedFrame::new(&frame(style), &colors, 400).unwrap();
+            let layout = placed.layout(&matrix()).unwrap();
+            assert_eq!((layout.width, layout.height), (400, 400), "{:?}", style);
+
+            // The frame surrounds the symbol without covering it
+            let (x, y, size) = layout.symbol_bounds();
+            assert!(
+                x > 0 && y > 0 && x + size < 400 && y + size < 400,
+                "{:?}",
+                style
+            );
+            let layers = placed.layers(&layout);
+            assert_eq!(layers.len(), 2);
+            let center = (layout.modules as f64 / 2.0, layout.modules as f64 / 2.0);
+            assert!(
+                !layers[0].shapes[0].contains(center.0, center.1),
+                "{:?}",
+                style
+            );
+        }
+    }
+
+    #[test]
+    fn test_banner_layout() {
+        let placed =
+            PlacedFrame::new(&frame(FrameStyle::Banner), &QrColors::default(), 400).unwrap();
+        let layout = placed.layout(&matrix()).unwrap();
+
+        // A 12px border and a 72px banner leave a 316px opening for the code
+        assert_eq!(placed.code_area, (42, 12, 316));
+        assert_eq!(layout.module_size, 10);
+        assert_eq!(layout.origin(), (42 + 13 + 40, 12 + 13 + 40));
+
+        let text = placed.text.as_ref().unwrap();
+        assert_eq!(text.text, "SCAN ME");
+        assert_eq!(text.font_size, 36.0);
+        assert!(text.baseline > 328.0 && text.baseline < 400.0);
+        assert_eq!(placed.text_color, "#FFFFFF");
+    }
+
+    #[test]
+    fn test_ticket_outline() {
+        let placed =
+            PlacedFrame::new(&frame(FrameStyle::Ticket), &QrColors::default(), 400).unwrap();
+        // 10px modules with the symbol at (40, 40), to convert back to pixels
+        let layout = QrLayout::compute(&matrix(), 290).unwrap();
+        let outline = &placed.layers(&layout)[0].shapes[0];
+        let at = |x: f64, y: f64| outline.contains(x / 10.0 - 4.0, y / 10.0 - 4.0);
+
+        // Solid stub, notched sides, perforation and the window for the code
+        assert!(at(200.0, 380.0));
+        assert!(!at(5.0, 320.0) && !at(395.0, 320.0));
+        assert!(at(5.0, 360.0));
+        assert!(!at(200.0, 320.0) || !at(210.0, 320.0));
+        assert!(!at(200.0, 150.0));
+    }
+
+    #[test]
+    fn test_frame_limits() {
+        let colors = QrColors::default();
+        let long = FrameOptions {
+            text: Some("Scan to claim your free ticket today".to_string()),
+            ..Default::default()
+        };
+        assert!(PlacedFrame::new(&long, &colors, 100).is_err());
+        assert!(PlacedFrame::new(&long, &colors, 800).is_ok());
+
+        // The screen of a 50px phone has room for 29 modules but not 41
+        let phone = FrameOptions {
+            text: Some(String::new()),
+            ..frame(FrameStyle::Phone)
+        };
+        assert!(PlacedFrame::new(&frame(FrameStyle::Phone), &colors, 50).is_err());
+        let placed = PlacedFrame::new(&phone, &colors, 50).unwrap();
+        assert!(placed.layout(&matrix()).is_ok());
+        assert!(matches!(
+            placed.layout(&matrix_with_quiet_zone(10)),
+            Err(ApiError::ValidationError(_))
+        ));
+
+        // Without text the bubble gives its band to the code
+        let silent = FrameOptions {
+            style: FrameStyle::Bubble,
+            text: Some(String::new()),
+            ..Default::default()
+        };
+        let placed = PlacedFrame::new(&silent, &colors, 400).unwrap();
+        assert!(placed.text.is_none());
+        assert_eq!(placed.layers(&placed.layout(&matrix()).unwrap()).len(), 1);
+    }
+}
@@ -0,0 +1,292 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+
+use super::{
+    layout::QrLayout,
+    logo::Logo,
+    matrix::QrMatrix,
+    paint::{ImagePaint, Paint},
+    png::encode_png,
+    shapes::{symbol_layers, Layer, RoundedRect, Shape},
+};
+use crate::{
+    errors::ApiError,
+    models::{
+        composite, relative_luminance, ErrorCorrectionLevel, EyeStyle, HalftoneOptions,
+        ModuleStyle, PngCompression, QrColors,
+    },
+};
+
+/// Contrast every dark dot keeps against every light dot. Dots are pulled
+/// toward the foreground or background until they clear it.
+const DOT_CONTRAST: f64 = 3.0;
+
+/// Dot colors are rounded to this many levels per channel, which keeps the
+/// number of layers (and SVG and PDF paths) small.
+const COLOR_LEVELS: f64 = 7.0;
+
+/// A picture rendered through the code: the image fills the symbol, each
+/// data module becomes a dot colored from the image underneath, and the
+/// function patterns are drawn solid so the code still scans.
+pub struct Halftone {
+    image: ImagePaint,
+    dot_size: f64,
+    /// The darkest and lightest colors dots may be pulled toward.
+    dark: (u8, u8, u8),
+    light: (u8, u8, u8),
+}
+
+impl Halftone {
+    /// Decodes the image and renders it over the symbol, cropped to a
+    /// centered square and flattened onto the background.
+    pub fn new(
+        options: &HalftoneOptions,
+        level: &ErrorCorrectionLevel,
+        layout: &QrLayout,
+        colors: &QrColors,
+    ) -> Result<Self, ApiError> {
+        let light = colors
+            .effective_background()
+            .map_err(ApiError::ValidationError)?;
+        let dark = composite(
+            colors
+                .foreground_rgba()
+                .map_err(ApiError::ValidationError)?,
+            light,
+        );
+
+        let source = Logo::decode_as(&options.image, "Halftone")?;
+        let mut image = source.render_square(layout.modules * layout.module_size)?;
+        for pixel in image.pixels_mut() {
+            let (r, g, b) = composite((pixel[0], pixel[1], pixel[2], pixel[3]), light);
+            pixel.0 = [r, g, b, 255];
+        }
+        let png = encode_png(&image, &PngCompression::Balanced, None)?;
+
+        Ok(Self {
+            image: ImagePaint {
+                image: Arc::new(image),
+                data_uri: format!("data:image/png;base64,{}", BASE64.encode(png)).into(),
+                x: 0.0,
+                y: 0.0,
+                size: layout.modules as f64,
+            },
+            dot_size: options.dot_size(level),
+            dark,
+            light,
+        })
+    }
+
+    /// Builds the layers of the symbol in painting order: the image, the
+    /// light modules of the function patterns, the data dots and finally
+    /// the dark function patterns and eyes.
+    pub fn layers(&self, matrix: &QrMatrix, eye_style: &EyeStyle, colors: &QrColors) -> Vec<Layer> {
+        let size = self.image.size;
+        let mut layers = vec![Layer {
+            paint: Paint::Image(self.image.clone()),
+            shapes: vec![Shape::Rect(RoundedRect::square(0.0, 0.0, size, size))],
+        }];
+
+        let mut paper = Vec::new();
+        let mut dots: BTreeMap<String, Vec<Shape>> = BTreeMap::new();
+        let inset = (1.0 - self.dot_size) / 2.0;
+        for y in 0..matrix.height() {
+            for x in 0..matrix.width() {
+                let dark = matrix.is_dark(x, y);
+                if matrix.is_functional(x, y) {
+                    if !dark {
+                        paper.push(Shape::Rect(RoundedRect::square(
+                            x as f64, y as f64, 1.0, 1.0,
+                        )));
+                    }
+                    continue;
+                }
+                dots.entry(self.dot_color(x, y, dark))
+                    .or_default()
+                    .push(Shape::Rect(RoundedRect::uniform(
+                        x as f64 + inset,
+                        y as f64 + inset,
+                        self.dot_size,
+                        self.dot_size,
+                        self.dot_size / 2.0,
+                    )));
+            }
+        }
+
+        let (r, g, b) = self.light;
+        layers.push(Layer {
+            paint: Paint::Solid(format!("#{:02X}{:02X}{:02X}", r, g, b)),
+            shapes: paper,
+        });
+        layers.extend(dots.into_iter().map(|(color, shapes)| Layer {
+            paint: Paint::Solid(color),
+            shapes,
+        }));
+        layers.extend(symbol_layers(
+            &matrix.function_patterns(),
+            &ModuleStyle::Square,
+            eye_style,
+            colors,
+        ));
+        layers
+    }
+
+    /// Color of the dot for the module at (x, y): the average of the image
+    /// over the module, mixed toward the dark or light target just far
+    /// enough to keep `DOT_CONTRAST` across the luminance midpoint.
+    fn dot_color(&self, x: usize, y: usize, dark: bool) -> String {
+        let average = self.average(x, y);
+        let luminance = |(r, g, b): (u8, u8, u8)| relative_luminance(r, g, b) + 0.05;
+        let midpoint = (luminance(self.dark) * luminance(self.light)).sqrt();
+        let (target, bound) = if dark {
+            (self.dark, midpoint / DOT_CONTRAST.sqrt())
+        } else {
+            (self.light, midpoint * DOT_CONTRAST.sqrt())
+        };
+
+        let color = (0..=10)
+            .map(|step| quantize(mix(average, target, step as f64 / 10.0)))
+            .find(|&color| {
+                if dark {
+                    luminance(color) <= bound
+                } else {
+                    luminance(color) >= bound
+                }
+            })
+            .unwrap_or(target);
+        format!("#{:02X}{:02X}{:02X}", color.0, color.1, color.2)
+    }
+
+    /// Mean color of the image pixels covering the module at (x, y).
+    fn average(&self, x: usize, y: usize) -> (u8, u8, u8) {
+        let image = &self.image.image;
+        let scale = image.width() as f64 / self.image.size;
+        let (x0, x1) = ((x as f64 * scale) as u32, ((x + 1) as f64 * scale) as u32);
+        let (y0, y1) = ((y as f64 * scale) as u32, ((y + 1) as f64 * scale) as u32);
+
+        let mut sum = [0u64; 3];
+        let mut count = 0;
+        for py in y0..y1.max(y0 + 1).min(image.height()) {
+            for px in x0..x1.max(x0 + 1).min(image.width()) {
+                let pixel = image.get_pixel(px, py);
+                for channel in 0..3 {
+                    sum[channel] += pixel[channel] as u64;
+                }
+                count += 1;
+            }
+        }
+        let mean = |channel: usize| (sum[channel] / count.max(1)) as u8;
+        (mean(0), mean(1), mean(2))
+    }
+}
+
+fn mix(from: (u8, u8, u8), to: (u8, u8, u8), t: f64) -> (u8, u8, u8) {
+    let channel = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+    (
+        channel(from.0, to.0),
+        channel(from.1, to.1),
+        channel(from.2, to.2),
+    )
+}
+
+fn quantize(color: (u8, u8, u8)) -> (u8, u8, u8) {
+    let step = 255.0 / COLOR_LEVELS;
+    let channel = |c: u8| ((c as f64 / step).round() * step).round() as u8;
+    (channel(color.0), channel(color.1), channel(color.2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageFormat, Rgba, RgbaImage};
+    use qrcode::QrCode;
+    use std::io::Cursor;
+
+    /// Base64 PNG with a red left half and a pale blue right half.
+    fn two_tone_image() -> String {
+        let image = RgbaImage::from_fn(40, 40, |x, _| {
+            if x < 20 {
+                Rgba([220, 30, 30, 255])
+            } else {
+                Rgba([200, 220, 255, 255])
+            }
+        });
+        let mut bytes = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+            .unwrap();
+        BASE64.encode(bytes)
+    }
+
+    fn halftone(matrix: &QrMatrix) -> Halftone {
+        let layout = QrLayout::compute(matrix, 330).unwrap();
+        let options = HalftoneOptions {
+            image: two_tone_image(),
+            dot_size: None,
+        };
+        Halftone::new(
+            &options,
+            &ErrorCorrectionLevel::H,
+            &layout,
+            &QrColors::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_dots_keep_contrast() {
+        let matrix = QrMatrix::from_qr_code(&QrCode::new(b"https://example.com").unwrap(), 4);
+        let halftone = halftone(&matrix);
+        assert_eq!(halftone.dot_size, 0.3);
+
+        let luminance = |hex: &str| {
+            let (r, g, b, _) = crate::models::hex_to_rgba(hex).unwrap();
+            relative_luminance(r, g, b) + 0.05
+        };
+        for (x, y) in [(2, 12), (20, 12)] {
+            let dark = luminance(&halftone.dot_color(x, y, true));
+            let light = luminance(&halftone.dot_color(x, y, false));
+            assert!(light / dark >= DOT_CONTRAST, "{} / {}", light, dark);
+        }
+        // Red stays red on the left half instead of turning black
+        assert_ne!(halftone.dot_color(2, 12, true), "#000000");
+    }
+
+    #[test]
+    fn test_function_patterns_stay_solid() {
+        let matrix = QrMatrix::from_qr_code(&QrCode::new(b"https://example.com").unwrap(), 4);
+        let layers = halftone(&matrix).layers(&matrix, &EyeStyle::default(), &QrColors::default());
+
+        assert!(matches!(layers[0].paint, Paint::Image(_)));
+        assert_eq!(layers[1].paint, Paint::Solid("#FFFFFF".to_string()));
+        let width = matrix.width();
+        let (functional, light_functional) = (0..width * width)
+            .map(|index| (index % width, index / width))
+            .filter(|&(x, y)| matrix.is_functional(x, y))
+            .fold((0, 0), |(all, light), (x, y)| {
+                (all + 1, light + !matrix.is_dark(x, y) as usize)
+            });
+        assert_eq!(layers[1].shapes.len(), light_functional);
+
+        // Every data module, dark or light, becomes exactly one dot
+        let dots: usize = layers
+            .iter()
+            .skip(2)
+            .take_while(|layer| layer.shapes.iter().all(|shape| shape.bounds().2 == 0.3))
+            .map(|layer| layer.shapes.len())
+            .sum();
+        assert_eq!(dots, width * width - functional);
+    }
+
+    #[test]
+    fn test_every_row_of_a_tall_matrix_gets_dots() {
+        let matrix = QrMatrix::new(3, 5, vec![false; 15], vec![false; 15], 4, Vec::new());
+        let layers = halftone(&matrix).layers(&matrix, &EyeStyle::default(), &QrColors::default());
+
+        let dots: Vec<&Shape> = layers[2..].iter().flat_map(|layer| &layer.shapes).collect();
+        assert_eq!(dots.len(), 15);
+        assert!(dots.iter().any(|shape| shape.bounds().1 > 4.0));
+    }
+}
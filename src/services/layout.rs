@@ -0,0 +1,176 @@
+use super::matrix::QrMatrix;
+use crate::{errors::ApiError, models::ImageDimensions};
+
+/// Pixel geometry of a rendered code.
+///
+/// The code area (symbol, quiet zone and padding) is always exactly the
+/// requested size across. Modules are scaled by the largest whole number of
+/// pixels that fits the symbol plus its quiet zone, and any leftover pixels
+/// are split evenly around the code as extra background padding (the odd
+/// pixel goes to the right and bottom edges). A rectangular symbol keeps
+/// the same padding above and below, so its code area is shorter. The
+/// canvas matches the code area unless the code is placed inside a larger
+/// one, e.g. for a frame, or the canvas is extended, e.g. for a caption.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QrLayout {
+    /// Width of the whole canvas in pixels.
+    pub width: u32,
+    /// Height of the whole canvas in pixels.
+    pub height: u32,
+    /// Distance of the code area from the left edge of the canvas.
+    pub left: u32,
+    /// Distance of the code area from the top edge of the canvas.
+    pub top: u32,
+    /// Width of the code area in pixels.
+    pub code_size: u32,
+    /// Height of the code area in pixels, the same as `code_size` unless the
+    /// symbol is rectangular.
+    pub code_height: u32,
+    /// Edge length of one module in pixels.
+    pub module_size: u32,
+    /// Background padding before the quiet zone on the left and top edges.
+    pub padding: u32,
+    /// Quiet zone width in modules.
+    pub quiet_zone: u32,
+    /// Symbol width in modules, excluding the quiet zone.
+    pub modules: u32,
+    /// Symbol height in modules, excluding the quiet zone.
+    pub rows: u32,
+}
+
+impl QrLayout {
+    pub fn compute(matrix: &QrMatrix, size: u32) -> Result<Self, ApiError> {
+        let total_modules = matrix.total_width() as u32;
+        let total_rows = matrix.total_height() as u32;
+        let module_size = size / total_modules;
+
+        if module_size == 0 {
+            return Err(ApiError::ValidationError(format!(
+                "Size {}px is too small for a {}-module code including its quiet zone; use at least {}px or a smaller border_width",
+                size, total_modules, total_modules
+            )));
+        }
+
+        let remainder = size - module_size * total_modules;
+        let code_height = module_size * total_rows + remainder;
+
+        Ok(Self {
+            width: size,
+            height: code_height,
+            left: 0,
+            top: 0,
+            code_size: size,
+            code_height,
+            module_size,
+            padding: remainder / 2,
+            quiet_zone: matrix.quiet_zone() as u32,
+            modules: matrix.width() as u32,
+            rows: matrix.height() as u32,
+        })
+    }
+
+    /// Pixel position of the first symbol module: `(x, y)`.
+    pub fn origin(&self) -> (u32, u32) {
+        let inset = self.padding + self.quiet_zone * self.module_size;
+        (self.left + inset, self.top + inset)
+    }
+
+    /// Pixel bounds of the symbol without its quiet zone: `(x, y, width)`.
+    pub fn symbol_bounds(&self) -> (u32, u32, u32) {
+        let (x, y) = self.origin();
+        (x, y, self.modules * self.module_size)
+    }
+
+    /// Places the code area at `(left, top)` on a `width` by `height` canvas.
+    pub fn place(&mut self, width: u32, height: u32, left: u32, top: u32) {
+        self.width = width;
+        self.height = height;
+        self.left = left;
+        self.top = top;
+    }
+
+    /// Whether the canvas is larger than the code area in both directions,
+    /// so something other than the quiet zone surrounds the code.
+    pub fn is_framed(&self) -> bool {
+        self.width > self.code_size && self.height > self.code_height
+    }
+
+    /// Adds `above` and `below` pixels of canvas around the code area.
+    pub fn extend(&mut self, above: u32, below: u32) {
+        self.top += above;
+        self.height += above + below;
+    }
+
+    pub fn dimensions(&self) -> ImageDimensions {
+        ImageDimensions {
+            width: self.width,
+            height: self.height,
+            module_size: self.module_size,
+            quiet_zone: self.quiet_zone,
+            modules: self.modules,
+            rows: self.rows,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qrcode::{EcLevel, QrCode, Version};
+
+    fn version_1_matrix(quiet_zone: usize) -> QrMatrix {
+        let qr_code = QrCode::with_version(b"hello", Version::Normal(1), EcLevel::M).unwrap();
+        QrMatrix::from_qr_code(&qr_code, quiet_zone)
+    }
+
+    #[test]
+    fn test_layout_exact_fit() {
+        // 21 modules + 2 * 4 quiet zone = 29 modules
+        let layout = QrLayout::compute(&version_1_matrix(4), 290).unwrap();
+        assert_eq!((layout.width, layout.height), (290, 290));
+        assert_eq!(layout.module_size, 10);
+        assert_eq!(layout.padding, 0);
+        assert_eq!(layout.origin(), (40, 40));
+    }
+
+    #[test]
+    fn test_layout_pads_remainder() {
+        // 300 / 29 = 10 with 10 pixels left over
+        let layout = QrLayout::compute(&version_1_matrix(4), 300).unwrap();
+        assert_eq!(layout.module_size, 10);
+        assert_eq!(layout.padding, 5);
+        assert_eq!(layout.origin(), (45, 45));
+    }
+
+    #[test]
+    fn test_layout_without_quiet_zone() {
+        let layout = QrLayout::compute(&version_1_matrix(0), 210).unwrap();
+        assert_eq!(layout.module_size, 10);
+        assert_eq!(layout.origin(), (0, 0));
+    }
+
+    #[test]
+    fn test_layout_extend() {
+        let mut layout = QrLayout::compute(&version_1_matrix(4), 290).unwrap();
+        layout.extend(30, 10);
+        assert_eq!((layout.width, layout.height), (290, 330));
+        assert_eq!(layout.origin(), (40, 70));
+        assert_eq!(layout.symbol_bounds(), (40, 70, 210));
+    }
+
+    #[test]
+    fn test_layout_place() {
+        let mut layout = QrLayout::compute(&version_1_matrix(4), 290).unwrap();
+        assert!(!layout.is_framed());
+        layout.place(400, 400, 55, 20);
+        assert!(layout.is_framed());
+        assert_eq!(layout.origin(), (95, 60));
+        assert_eq!(layout.symbol_bounds(), (95, 60, 210));
+    }
+
+    #[test]
+    fn test_layout_too_small() {
+        let result = QrLayout::compute(&version_1_matrix(50), 100);
+        assert!(matches!(result, Err(ApiError::ValidationError(_))));
+    }
+}
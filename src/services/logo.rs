@@ -0,0 +1,524 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use image::{imageops, DynamicImage, RgbaImage};
+use resvg::{tiny_skia, usvg};
+use std::{collections::BTreeSet, io::Cursor};
+
+use super::{
+    layout::QrLayout,
+    matrix::QrMatrix,
+    paint::Paint,
+    raster::overlay,
+    shapes::{module_shapes, Layer, RoundedRect, Shape},
+};
+use crate::{
+    errors::ApiError,
+    models::{ErrorCorrectionLevel, LogoOptions, ModuleStyle, QrColors},
+};
+
+/// Largest accepted logo width or height in pixels.
+const MAX_LOGO_DIMENSION: u32 = 4096;
+
+enum LogoSource {
+    Raster(DynamicImage),
+    Svg(Box<usvg::Tree>),
+}
+
+/// A decoded logo image.
+pub struct Logo {
+    source: LogoSource,
+    mime_type: &'static str,
+    bytes: Vec<u8>,
+}
+
+impl Logo {
+    /// Decodes base64 PNG, JPEG or SVG data, with or without a `data:` URI
+    /// prefix. The format is detected from the content itself.
+    pub fn decode(data: &str) -> Result<Self, ApiError> {
+        Self::decode_as(data, "Logo")
+    }
+
+    /// Decodes an image like `decode`, naming it `what` in errors.
+    pub fn decode_as(data: &str, what: &str) -> Result<Self, ApiError> {
+        let encoded = match data.trim().split_once(";base64,") {
+            Some((prefix, encoded)) if prefix.starts_with("data:") => encoded,
+            _ => data.trim(),
+        };
+        let bytes = BASE64
+            .decode(encoded)
+            .map_err(|_| ApiError::ValidationError(format!("{} data is not valid base64", what)))?;
+
+        let (source, mime_type) = if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            (
+                LogoSource::Raster(decode_raster(&bytes, image::ImageFormat::Png, what)?),
+                "image/png",
+            )
+        } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            (
+                LogoSource::Raster(decode_raster(&bytes, image::ImageFormat::Jpeg, what)?),
+                "image/jpeg",
+            )
+        } else if looks_like_svg(&bytes) {
+            let tree = usvg::Tree::from_data(&bytes, &svg_options()).map_err(|e| {
+                ApiError::ValidationError(format!("Invalid SVG {}: {}", what.to_lowercase(), e))
+            })?;
+            (LogoSource::Svg(Box::new(tree)), "image/svg+xml")
+        } else {
+            return Err(ApiError::ValidationError(format!(
+                "{} must be a PNG, JPEG or SVG image",
+                what
+            )));
+        };
+
+        Ok(Self {
+            source,
+            mime_type,
+            bytes,
+        })
+    }
+
+    /// Width divided by height.
+    fn aspect_ratio(&self) -> f64 {
+        match &self.source {
+            LogoSource::Raster(image) => image.width() as f64 / image.height() as f64,
+            LogoSource::Svg(tree) => (tree.size().width() / tree.size().height()) as f64,
+        }
+    }
+
+    /// Renders the largest centered square of the image at `size` x `size`
+    /// pixels.
+    pub fn render_square(&self, size: u32) -> Result<RgbaImage, ApiError> {
+        match &self.source {
+            LogoSource::Raster(image) => {
+                let side = image.width().min(image.height());
+                let (x, y) = ((image.width() - side) / 2, (image.height() - side) / 2);
+                Ok(image
+                    .crop_imm(x, y, side, side)
+                    .resize_exact(size, size, imageops::FilterType::Lanczos3)
+                    .to_rgba8())
+            }
+            LogoSource::Svg(tree) => {
+                let tree_size = tree.size();
+                let scale = (size as f32 / tree_size.width()).max(size as f32 / tree_size.height());
+                let transform = tiny_skia::Transform::from_row(
+                    scale,
+                    0.0,
+                    0.0,
+                    scale,
+                    (size as f32 - tree_size.width() * scale) / 2.0,
+                    (size as f32 - tree_size.height() * scale) / 2.0,
+                );
+                render_svg_tree(tree, size, size, transform)
+            }
+        }
+    }
+
+    /// Renders the logo at exactly `width` x `height` pixels.
+    fn render(&self, width: u32, height: u32) -> Result<RgbaImage, ApiError> {
+        match &self.source {
+            LogoSource::Raster(image) => Ok(image
+                .resize_exact(width, height, imageops::FilterType::Lanczos3)
+                .to_rgba8()),
+            LogoSource::Svg(tree) => {
+                let size = tree.size();
+                let transform = tiny_skia::Transform::from_scale(
+                    width as f32 / size.width(),
+                    height as f32 / size.height(),
+                );
+                render_svg_tree(tree, width, height, transform)
+            }
+        }
+    }
+}
+
+/// A logo positioned over a symbol, in module coordinates.
+pub struct PlacedLogo {
+    logo: Logo,
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+    /// Modules hidden by the logo and its padding: `(x0, y0, x1, y1)`,
+    /// end-exclusive.
+    cleared: (usize, usize, usize, usize),
+    plate: Option<String>,
+    /// Function patterns in the cleared region, drawn over the logo.
+    patterns: Vec<Layer>,
+}
+
+impl PlacedLogo {
+    /// Centers the logo on the symbol and checks that the codewords it hides
+    /// stay within what `error_correction` can recover.
+    pub fn new(
+        logo: Logo,
+        matrix: &QrMatrix,
+        options: &LogoOptions,
+        error_correction: &ErrorCorrectionLevel,
+    ) -> Result<Self, ApiError> {
+        let symbol = matrix.width() as f64;
+        let frame = symbol * options.size;
+        let aspect = logo.aspect_ratio();
+        let (width, height) = if aspect >= 1.0 {
+            (frame, frame / aspect)
+        } else {
+            (frame * aspect, frame)
+        };
+        let (x, y) = ((symbol - width) / 2.0, (symbol - height) / 2.0);
+
+        // Clear whole modules around the logo plus its padding
+        let padding = options.padding as f64;
+        let first = |start: f64| (start - padding).floor().max(0.0) as usize;
+        let last = |end: f64| ((end + padding).ceil() as usize).min(matrix.width());
+        let cleared = (first(x), first(y), last(x + width), last(y + height));
+
+        // A codeword is lost as soon as one of its modules is hidden;
+        // function patterns stay visible on top of the logo
+        let codewords = matrix.codewords();
+        let total = codewords.iter().flatten().max().map_or(0, |&last| last + 1);
+        let hidden: BTreeSet<usize> = (cleared.1..cleared.3)
+            .flat_map(|y| (cleared.0..cleared.2).map(move |x| y * matrix.width() + x))
+            .filter_map(|index| codewords[index])
+            .collect();
+        let coverage = hidden.len() as f64 / total.max(1) as f64;
+        let limit = error_correction.max_logo_coverage();
+        if coverage > limit {
+            return Err(ApiError::ValidationError(format!(
+                "Logo hides {:.1}% of the codewords but error correction level {:?} only allows {:.1}%; use a smaller logo, less padding, a higher error correction level or a larger min_version",
+                coverage * 100.0,
+                error_correction,
+                limit * 100.0
+            )));
+        }
+
+        Ok(Self {
+            logo,
+            x,
+            y,
+            width,
+            height,
+            cleared,
+            plate: options.background.clone(),
+            patterns: Vec::new(),
+        })
+    }
+
+    /// Draws the function modules of `matrix` in the cleared region again
+    /// on top of the logo, so alignment, timing and version patterns under
+    /// it still reach the scanner. Light modules become squares of the
+    /// plate, or the background where there is none.
+    pub fn show_patterns(&mut self, matrix: &QrMatrix, style: &ModuleStyle, colors: &QrColors) {
+        let (x0, y0, x1, y1) = self.cleared;
+        let light: Vec<Shape> = (y0..y1)
+            .flat_map(|y| (x0..x1).map(move |x| (x, y)))
+            .filter(|&(x, y)| matrix.is_functional(x, y) && !matrix.is_dark(x, y))
+            .map(|(x, y)| Shape::Rect(RoundedRect::square(x as f64, y as f64, 1.0, 1.0)))
+            .collect();
+        let dark = module_shapes(&matrix.function_patterns().region(self.cleared), style);
+
+        let background = self.plate.as_ref().unwrap_or(&colors.background);
+        self.patterns = [
+            (Paint::Solid(background.clone()), light),
+            (Paint::foreground(colors), dark),
+        ]
+        .into_iter()
+        .filter(|(_, shapes)| !shapes.is_empty())
+        .map(|(paint, shapes)| Layer { paint, shapes })
+        .collect();
+    }
+
+    /// Layers to draw after the logo.
+    pub fn patterns(&self) -> &[Layer] {
+        &self.patterns
+    }
+
+    /// Module region hidden by the logo: `(x0, y0, x1, y1)`, end-exclusive.
+    pub fn cleared(&self) -> (usize, usize, usize, usize) {
+        self.cleared
+    }
+
+    /// Background plate covering the cleared modules, if one was requested.
+    pub fn plate_layer(&self) -> Option<Layer> {
+        let (x0, y0, x1, y1) = self.cleared;
+        self.plate.as_ref().map(|color| Layer {
+            paint: Paint::Solid(color.clone()),
+            shapes: vec![Shape::Rect(RoundedRect::square(
+                x0 as f64,
+                y0 as f64,
+                (x1 - x0) as f64,
+                (y1 - y0) as f64,
+            ))],
+        })
+    }
+
+    /// Color of the plate behind the logo, if any.
+    pub fn plate_color(&self) -> Option<&str> {
+        self.plate.as_deref()
+    }
+
+    /// Position and size in module coordinates: `(x, y, width, height)`.
+    pub fn bounds(&self) -> (f64, f64, f64, f64) {
+        (self.x, self.y, self.width, self.height)
+    }
+
+    /// Original logo file as a `data:` URI, for embedding in SVG output.
+    pub fn data_uri(&self) -> String {
+        format!(
+            "data:{};base64,{}",
+            self.logo.mime_type,
+            BASE64.encode(&self.logo.bytes)
+        )
+    }
+
+    /// Renders the logo at exactly `width` x `height` pixels.
+    pub fn render(&self, width: u32, height: u32) -> Result<RgbaImage, ApiError> {
+        self.logo.render(width, height)
+    }
+
+    /// Alpha-blends the logo onto a rendered raster image.
+    pub fn draw(&self, image: &mut RgbaImage, layout: &QrLayout) -> Result<(), ApiError> {
+        let (ox, oy) = layout.origin();
+        let (ox, oy) = (ox as f64, oy as f64);
+        let scale = layout.module_size as f64;
+        let left = (ox + self.x * scale).round();
+        let top = (oy + self.y * scale).round();
+        let width = ((ox + (self.x + self.width) * scale).round() - left).max(1.0) as u32;
+        let height = ((oy + (self.y + self.height) * scale).round() - top).max(1.0) as u32;
+
+        let rendered = self.render(width, height)?;
+        overlay(image, &rendered, left as i64, top as i64);
+        Ok(())
+    }
+}
+
+/// Rasterizes an SVG with `transform` onto a transparent canvas.
+fn render_svg_tree(
+    tree: &usvg::Tree,
+    width: u32,
+    height: u32,
+    transform: tiny_skia::Transform,
+) -> Result<RgbaImage, ApiError> {
+    let mut pixmap = tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| ApiError::GenerationError("Failed to allocate logo canvas".to_string()))?;
+    resvg::render(tree, transform, &mut pixmap.as_mut());
+
+    // tiny-skia stores premultiplied alpha
+    let pixels = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    RgbaImage::from_raw(width, height, pixels)
+        .ok_or_else(|| ApiError::GenerationError("Failed to render SVG logo".to_string()))
+}
+
+/// SVG parsing options that embed `<image>` content only from `data:` URLs,
+/// so an uploaded SVG cannot make the server read its own files.
+fn svg_options() -> usvg::Options<'static> {
+    usvg::Options {
+        image_href_resolver: usvg::ImageHrefResolver {
+            resolve_data: usvg::ImageHrefResolver::default_data_resolver(),
+            resolve_string: Box::new(|_, _| None),
+        },
+        ..Default::default()
+    }
+}
+
+fn decode_raster(
+    bytes: &[u8],
+    format: image::ImageFormat,
+    what: &str,
+) -> Result<DynamicImage, ApiError> {
+    let reader = || image::ImageReader::with_format(Cursor::new(bytes), format);
+    let invalid = |e: image::ImageError| {
+        ApiError::ValidationError(format!("Invalid {} image: {}", what.to_lowercase(), e))
+    };
+
+    // Check the size in the header before decoding allocates the pixels
+    let (width, height) = reader().into_dimensions().map_err(invalid)?;
+    if width > MAX_LOGO_DIMENSION || height > MAX_LOGO_DIMENSION {
+        return Err(ApiError::ValidationError(format!(
+            "{} image cannot exceed {}x{} pixels",
+            what, MAX_LOGO_DIMENSION, MAX_LOGO_DIMENSION
+        )));
+    }
+
+    reader().decode().map_err(invalid)
+}
+
+fn looks_like_svg(bytes: &[u8]) -> bool {
+    // The root element usually follows an XML declaration or comments
+    let head = &bytes[..bytes.len().min(1024)];
+    String::from_utf8_lossy(head).contains("<svg")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qrcode::{EcLevel, QrCode, Version};
+
+    const SVG_LOGO: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="10"><rect width="20" height="10" fill="#E53935"/></svg>"##;
+
+    fn png_logo(width: u32, height: u32) -> String {
+        let image = RgbaImage::from_pixel(width, height, image::Rgba([229, 57, 53, 255]));
+        let mut bytes = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut bytes), image::ImageFormat::Png)
+            .unwrap();
+        BASE64.encode(bytes)
+    }
+
+    fn matrix(version: i16) -> QrMatrix {
+        let qr_code = QrCode::with_version(b"hello", Version::Normal(version), EcLevel::H).unwrap();
+        QrMatrix::from_qr_code(&qr_code, 4)
+    }
+
+    #[test]
+    fn test_decode_detects_format() {
+        let png = Logo::decode(&format!("data:image/png;base64,{}", png_logo(8, 4))).unwrap();
+        assert_eq!(png.mime_type, "image/png");
+        assert_eq!(png.aspect_ratio(), 2.0);
+
+        let svg = Logo::decode(&BASE64.encode(SVG_LOGO)).unwrap();
+        assert_eq!(svg.mime_type, "image/svg+xml");
+        assert_eq!(svg.aspect_ratio(), 2.0);
+
+        assert!(Logo::decode("not base64!").is_err());
+        assert!(Logo::decode(&BASE64.encode("plain text")).is_err());
+    }
+
+    #[test]
+    fn test_oversized_header_is_rejected_before_decoding() {
+        // A 1x1 PNG whose header claims 100000x100000 pixels
+        let mut bytes = BASE64.decode(png_logo(1, 1)).unwrap();
+        bytes[16..24].copy_from_slice(&[0, 1, 0x86, 0xA0, 0, 1, 0x86, 0xA0]);
+        let mut crc = flate2::Crc::new();
+        crc.update(&bytes[12..29]);
+        bytes[29..33].copy_from_slice(&crc.sum().to_be_bytes());
+
+        let error = Logo::decode(&BASE64.encode(bytes)).err().unwrap();
+        assert!(
+            error.to_string().contains("cannot exceed 4096x4096"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_svg_images_come_only_from_data_urls() {
+        let path = std::env::temp_dir().join("qr-api-logo-href-test.svg");
+        std::fs::write(&path, SVG_LOGO).unwrap();
+        let logo = |href: &str| {
+            let svg = format!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><image width="10" height="10" href="{}"/></svg>"#,
+                href
+            );
+            Logo::decode(&BASE64.encode(svg))
+                .unwrap()
+                .render_square(10)
+                .unwrap()
+        };
+
+        // Nothing is read from disk
+        let from_file = logo(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        assert!(from_file.pixels().all(|pixel| pixel[3] == 0));
+
+        let embedded = logo(&format!(
+            "data:image/svg+xml;base64,{}",
+            BASE64.encode(SVG_LOGO)
+        ));
+        assert_eq!(embedded.get_pixel(5, 5).0, [229, 57, 53, 255]);
+    }
+
+    #[test]
+    fn test_placement_is_centered_and_padded() {
+        let options = LogoOptions {
+            size: 0.2,
+            padding: 1,
+            ..Default::default()
+        };
+        let logo = Logo::decode(&png_logo(10, 10)).unwrap();
+        // Version 7 is 45 modules wide: a 9-module logo from 18 to 27
+        let placed = PlacedLogo::new(logo, &matrix(7), &options, &ErrorCorrectionLevel::H).unwrap();
+
+        assert_eq!(placed.cleared(), (17, 17, 28, 28));
+        let (x, _, width, _) = placed.bounds();
+        assert!((x + width / 2.0 - 22.5).abs() < 1e-9);
+        assert!(placed.plate_layer().is_none());
+    }
+
+    #[test]
+    fn test_coverage_is_capped_by_error_correction() {
+        let options = LogoOptions {
+            size: 0.25,
+            padding: 1,
+            ..Default::default()
+        };
+        let place = |version: i16, level: ErrorCorrectionLevel| {
+            let logo = Logo::decode(&png_logo(10, 10)).unwrap();
+            PlacedLogo::new(logo, &matrix(version), &options, &level)
+        };
+
+        assert!(place(10, ErrorCorrectionLevel::H).is_ok());
+        match place(10, ErrorCorrectionLevel::L) {
+            Err(ApiError::ValidationError(message)) => assert!(message.contains("level L")),
+            _ => panic!("expected the logo to be rejected at level L"),
+        }
+        // On version 2 the same logo covers 13% of the area but touches
+        // more than a third of the codewords
+        assert!(place(2, ErrorCorrectionLevel::H).is_err());
+    }
+
+    #[test]
+    fn test_function_patterns_are_kept() {
+        // Version 10 is 57 modules wide with an alignment pattern centered
+        // on (28, 28), right under the logo
+        let mut matrix = matrix(10);
+        let options = LogoOptions {
+            size: 0.25,
+            ..Default::default()
+        };
+        let logo = Logo::decode(&png_logo(10, 10)).unwrap();
+        let mut placed =
+            PlacedLogo::new(logo, &matrix, &options, &ErrorCorrectionLevel::H).unwrap();
+        matrix.clear_region(placed.cleared());
+        placed.show_patterns(&matrix, &ModuleStyle::Square, &QrColors::default());
+
+        let (x0, y0, x1, y1) = placed.cleared();
+        assert!(x0 < 26 && y0 < 26 && x1 > 30 && y1 > 30);
+        assert!(matrix.is_dark(26, 26) && !matrix.is_dark(27, 27) && matrix.is_dark(28, 28));
+        assert!(
+            (y0..y1).all(|y| (x0..x1).all(|x| matrix.is_functional(x, y) || !matrix.is_dark(x, y)))
+        );
+
+        // Light squares, then the dark modules, go over the logo
+        let covers = |layer: &Layer, x: f64, y: f64| {
+            layer.shapes.iter().any(|shape| match shape {
+                Shape::Rect(rect) => {
+                    rect.x <= x
+                        && x < rect.x + rect.width
+                        && rect.y <= y
+                        && y < rect.y + rect.height
+                }
+                _ => false,
+            })
+        };
+        let [light, dark] = placed.patterns() else {
+            panic!("expected light and dark pattern layers");
+        };
+        assert!(covers(light, 27.5, 27.5) && !covers(light, 28.5, 28.5));
+        assert!(covers(dark, 28.5, 28.5) && covers(dark, 26.5, 26.5));
+        assert_eq!(dark.paint, Paint::Solid("#000000".to_string()));
+    }
+
+    #[test]
+    fn test_svg_logo_is_rasterized() {
+        let logo = Logo::decode(&BASE64.encode(SVG_LOGO)).unwrap();
+        let rendered = logo.render(40, 20).unwrap();
+
+        assert_eq!(rendered.dimensions(), (40, 20));
+        assert_eq!(*rendered.get_pixel(20, 10), image::Rgba([229, 57, 53, 255]));
+    }
+}
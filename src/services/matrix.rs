@@ -0,0 +1,375 @@
+use qrcode::{canvas, Color, Version};
+
+/// Module grid of an encoded symbol.
+///
+/// Renderers work from this instead of `QrCode` directly so every output
+/// format sees exactly the same modules and quiet zone.
+#[derive(Debug, Clone)]
+pub struct QrMatrix {
+    width: usize,
+    height: usize,
+    modules: Vec<bool>,
+    /// Whether each module belongs to a function pattern rather than data.
+    functional: Vec<bool>,
+    quiet_zone: usize,
+    finder_origins: Vec<(usize, usize)>,
+}
+
+impl QrMatrix {
+    #[cfg(test)]
+    pub fn from_qr_code(qr_code: &qrcode::QrCode, quiet_zone: usize) -> Self {
+        Self::from_colors(qr_code.version(), qr_code.to_colors(), quiet_zone)
+    }
+
+    /// Builds the matrix from the module colors of a symbol, row by row.
+    pub fn from_colors(version: Version, colors: Vec<Color>, quiet_zone: usize) -> Self {
+        let modules = colors
+            .into_iter()
+            .map(|color| color == Color::Dark)
+            .collect();
+
+        let width = version.width() as usize;
+        // The version information blocks next to the top-right and
+        // bottom-left finders count as function patterns too
+        let version_info = matches!(version, Version::Normal(version) if version >= 7);
+        let functional = (0..width * width)
+            .map(|index| {
+                let (x, y) = (index % width, index / width);
+                canvas::is_functional(version, width as i16, x as i16, y as i16)
+                    || (version_info && ((x < 6 && y + 11 >= width) || (y < 6 && x + 11 >= width)))
+            })
+            .collect();
+        // Micro QR has a single finder pattern in the top-left corner
+        let finder_origins = if version.is_micro() {
+            vec![(0, 0)]
+        } else {
+            vec![(0, 0), (width - 7, 0), (0, width - 7)]
+        };
+
+        Self::new(
+            width,
+            width,
+            modules,
+            functional,
+            quiet_zone,
+            finder_origins,
+        )
+    }
+
+    /// Builds the matrix of a `width` by `height` symbol from its modules
+    /// and function pattern flags, row by row.
+    pub fn new(
+        width: usize,
+        height: usize,
+        modules: Vec<bool>,
+        functional: Vec<bool>,
+        quiet_zone: usize,
+        finder_origins: Vec<(usize, usize)>,
+    ) -> Self {
+        Self {
+            width,
+            height,
+            modules,
+            functional,
+            quiet_zone,
+            finder_origins,
+        }
+    }
+
+    /// Places symbols of the same size in a grid, `columns` to a row and
+    /// left to right, with `gap` light modules between them. The grid keeps
+    /// the quiet zone of the symbols around its edge.
+    pub fn tile(symbols: &[QrMatrix], columns: usize, gap: usize) -> Self {
+        let (width, height) = (symbols[0].width, symbols[0].height);
+        let rows = symbols.len().div_ceil(columns);
+        let grid_width = columns * (width + gap) - gap;
+        let grid_height = rows * (height + gap) - gap;
+
+        let mut modules = vec![false; grid_width * grid_height];
+        let mut functional = vec![false; grid_width * grid_height];
+        let mut finder_origins = Vec::new();
+        for (index, symbol) in symbols.iter().enumerate() {
+            let (left, top) = (
+                index % columns * (width + gap),
+                index / columns * (height + gap),
+            );
+            for y in 0..height {
+                let source = y * width;
+                let target = (top + y) * grid_width + left;
+                modules[target..target + width]
+                    .copy_from_slice(&symbol.modules[source..source + width]);
+                functional[target..target + width]
+                    .copy_from_slice(&symbol.functional[source..source + width]);
+            }
+            finder_origins.extend(
+                symbol
+                    .finder_origins
+                    .iter()
+                    .map(|&(x, y)| (left + x, top + y)),
+            );
+        }
+
+        Self::new(
+            grid_width,
+            grid_height,
+            modules,
+            functional,
+            symbols[0].quiet_zone,
+            finder_origins,
+        )
+    }
+
+    /// Number of modules across, excluding the quiet zone.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of modules down, excluding the quiet zone. Only rMQR symbols
+    /// and tiled sets are not square.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Quiet zone width in modules on each side.
+    pub fn quiet_zone(&self) -> usize {
+        self.quiet_zone
+    }
+
+    /// Number of modules across, including the quiet zone on both sides.
+    pub fn total_width(&self) -> usize {
+        self.width + 2 * self.quiet_zone
+    }
+
+    /// Number of modules down, including the quiet zone on both sides.
+    pub fn total_height(&self) -> usize {
+        self.height + 2 * self.quiet_zone
+    }
+
+    /// Whether the module at (x, y) is dark. Coordinates are relative to the
+    /// symbol, not the quiet zone.
+    pub fn is_dark(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height && self.modules[y * self.width + x]
+    }
+
+    /// Whether the module at (x, y) belongs to a finder, separator, timing,
+    /// alignment, format or version pattern.
+    pub fn is_functional(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height && self.functional[y * self.width + x]
+    }
+
+    /// Turns every data module in `x0..x1` by `y0..y1` light, e.g. under a
+    /// logo. Function patterns are left as they are.
+    pub fn clear_region(&mut self, (x0, y0, x1, y1): (usize, usize, usize, usize)) {
+        for y in y0..y1.min(self.height) {
+            for x in x0..x1.min(self.width) {
+                if !self.functional[y * self.width + x] {
+                    self.modules[y * self.width + x] = false;
+                }
+            }
+        }
+    }
+
+    /// Copy of the matrix with every module outside `x0..x1` by `y0..y1`
+    /// turned light.
+    pub fn region(&self, (x0, y0, x1, y1): (usize, usize, usize, usize)) -> Self {
+        let modules = self
+            .modules
+            .iter()
+            .enumerate()
+            .map(|(index, &dark)| {
+                let (x, y) = (index % self.width, index / self.width);
+                dark && (x0..x1).contains(&x) && (y0..y1).contains(&y)
+            })
+            .collect();
+        Self {
+            modules,
+            ..self.clone()
+        }
+    }
+
+    /// Index of the codeword each data module carries, in the order a
+    /// normal QR symbol places them: two-module columns from the right,
+    /// alternately upward and downward, stepping over the vertical timing
+    /// pattern. Function modules and the remainder bits after the last
+    /// codeword have none.
+    pub fn codewords(&self) -> Vec<Option<usize>> {
+        let mut codewords = vec![None; self.modules.len()];
+        let columns: Vec<usize> = (0..self.width).rev().filter(|&x| x != 6).collect();
+        let mut bit = 0;
+        for (pair, columns) in columns.chunks(2).enumerate() {
+            for step in 0..self.height {
+                let y = if pair % 2 == 0 {
+                    self.height - 1 - step
+                } else {
+                    step
+                };
+                for &x in columns {
+                    let index = y * self.width + x;
+                    if !self.functional[index] {
+                        codewords[index] = Some(bit / 8);
+                        bit += 1;
+                    }
+                }
+            }
+        }
+
+        let remainder = Some(bit / 8);
+        for codeword in codewords
+            .iter_mut()
+            .filter(|codeword| **codeword == remainder)
+        {
+            *codeword = None;
+        }
+        codewords
+    }
+
+    /// Copy of the matrix with every data module turned light, leaving only
+    /// the function patterns.
+    pub fn function_patterns(&self) -> Self {
+        let modules = self
+            .modules
+            .iter()
+            .zip(&self.functional)
+            .map(|(&dark, &functional)| dark && functional)
+            .collect();
+        Self {
+            modules,
+            ..self.clone()
+        }
+    }
+
+    /// Top-left corners of the 7x7 finder patterns.
+    pub fn finder_origins(&self) -> &[(usize, usize)] {
+        &self.finder_origins
+    }
+
+    /// Whether (x, y) lies inside one of the finder patterns.
+    pub fn is_finder(&self, x: usize, y: usize) -> bool {
+        self.finder_origins
+            .iter()
+            .any(|&(fx, fy)| x >= fx && x < fx + 7 && y >= fy && y < fy + 7)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qrcode::QrCode;
+
+    #[test]
+    fn test_from_qr_code() {
+        let qr_code = QrCode::new(b"https://example.com").unwrap();
+        let matrix = QrMatrix::from_qr_code(&qr_code, 4);
+
+        assert_eq!(matrix.width(), qr_code.width());
+        assert_eq!(matrix.quiet_zone(), 4);
+        assert_eq!(matrix.total_width(), qr_code.width() + 8);
+        // Top-left finder pattern corner is always dark
+        assert!(matrix.is_dark(0, 0));
+        assert!(!matrix.is_dark(qr_code.width(), 0));
+    }
+
+    #[test]
+    fn test_finder_patterns() {
+        let qr_code = QrCode::new(b"https://example.com").unwrap();
+        let matrix = QrMatrix::from_qr_code(&qr_code, 4);
+        let width = qr_code.width();
+
+        assert_eq!(matrix.finder_origins().len(), 3);
+        assert!(matrix.is_finder(0, 0));
+        assert!(matrix.is_finder(width - 1, 6));
+        assert!(matrix.is_finder(6, width - 1));
+        assert!(!matrix.is_finder(7, 7));
+        assert!(!matrix.is_finder(width - 1, width - 1));
+    }
+
+    #[test]
+    fn test_function_patterns() {
+        let qr_code =
+            QrCode::with_version(b"hello", Version::Normal(7), qrcode::EcLevel::M).unwrap();
+        let matrix = QrMatrix::from_qr_code(&qr_code, 4);
+        let width = matrix.width();
+
+        // Finder and separator, timing pattern, alignment pattern
+        assert!(matrix.is_functional(7, 7));
+        assert!(matrix.is_functional(20, 6));
+        assert!(matrix.is_functional(22, 22));
+        // Version information
+        assert!(matrix.is_functional(0, width - 11) && matrix.is_functional(width - 9, 5));
+        assert!(!matrix.is_functional(12, 12));
+
+        let patterns = matrix.function_patterns();
+        assert!(patterns.is_dark(8, 6));
+        assert!((0..width)
+            .all(|y| (0..width).all(|x| matrix.is_functional(x, y) || !patterns.is_dark(x, y))));
+    }
+
+    #[test]
+    fn test_clear_region() {
+        let qr_code = QrCode::new(b"https://example.com").unwrap();
+        let mut matrix = QrMatrix::from_qr_code(&qr_code, 4);
+
+        assert!((9..14).any(|y| (9..14).any(|x| matrix.is_dark(x, y))));
+        matrix.clear_region((0, 0, 14, 14));
+        assert!((9..14).all(|y| (9..14).all(|x| !matrix.is_dark(x, y))));
+        // Function patterns inside the region are kept
+        assert!(matrix.is_dark(0, 0));
+        assert!(matrix.is_dark(8, 6));
+        // Outside the region data modules are untouched
+        assert!((14..20).any(|y| (14..20).any(|x| matrix.is_dark(x, y))));
+    }
+
+    #[test]
+    fn test_region() {
+        let qr_code = QrCode::new(b"https://example.com").unwrap();
+        let matrix = QrMatrix::from_qr_code(&qr_code, 4);
+
+        let region = matrix.region((0, 0, 3, 2));
+        assert!(region.is_dark(0, 0) && region.is_dark(2, 0));
+        assert!(!region.is_dark(0, 2));
+        assert_eq!(region.width(), matrix.width());
+    }
+
+    #[test]
+    fn test_codewords() {
+        // Version 2 has 44 codewords followed by 7 remainder bits
+        let qr_code =
+            QrCode::with_version(b"hello", Version::Normal(2), qrcode::EcLevel::M).unwrap();
+        let matrix = QrMatrix::from_qr_code(&qr_code, 4);
+        let codewords = matrix.codewords();
+
+        assert_eq!(codewords.iter().flatten().count(), 44 * 8);
+        assert_eq!(codewords.iter().flatten().max(), Some(&43));
+        // The first codeword fills the bottom-right corner upward
+        let width = matrix.width();
+        let at = |x: usize, y: usize| codewords[y * width + x];
+        assert_eq!(at(width - 1, width - 1), Some(0));
+        assert_eq!(at(width - 2, width - 4), Some(0));
+        assert_eq!(at(width - 1, width - 5), Some(1));
+        // Function modules and the remainder bits carry none
+        assert_eq!(at(8, 6), None);
+        assert_eq!(at(0, 8), None);
+        assert_eq!(at(0, 16), None);
+        assert_eq!(at(1, 13), Some(43));
+    }
+
+    #[test]
+    fn test_tile() {
+        let qr_code = QrCode::new(b"https://example.com").unwrap();
+        let matrix = QrMatrix::from_qr_code(&qr_code, 4);
+        let width = matrix.width();
+        let grid = QrMatrix::tile(&[matrix.clone(), matrix.clone(), matrix.clone()], 2, 4);
+
+        assert_eq!(
+            (grid.width(), grid.height(), grid.quiet_zone()),
+            (2 * width + 4, 2 * width + 4, 4)
+        );
+        assert_eq!(grid.finder_origins().len(), 9);
+        assert!(grid.is_finder(width + 4, 0) && grid.is_finder(0, width + 4));
+        // The gap between symbols and the empty last cell stay light
+        assert!((0..grid.height()).all(|y| (width..width + 4).all(|x| !grid.is_dark(x, y))));
+        assert!(!grid.is_dark(width + 4, width + 4) && !grid.is_functional(width + 4, width + 4));
+        assert!((0..width)
+            .all(|y| (0..width).all(|x| grid.is_dark(x, width + 4 + y) == matrix.is_dark(x, y))));
+    }
+}
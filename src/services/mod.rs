@@ -1,3 +1,20 @@
+pub mod caption;
+pub mod ccitt;
+pub mod encoder;
+pub mod eps;
+pub mod frame;
+pub mod halftone;
+pub mod layout;
+pub mod logo;
+pub mod matrix;
+pub mod paint;
+pub mod pdf;
+pub mod png;
 pub mod qr_service;
+pub mod raster;
+pub mod rmqr;
+pub mod shapes;
+pub mod svg;
+pub mod text;
 
-pub use qr_service::QrService;
\ No newline at end of file
+pub use qr_service::QrService;
@@ -0,0 +1,195 @@
+use std::sync::Arc;
+
+use image::RgbaImage;
+
+use crate::models::{hex_to_rgba, Gradient, GradientKind, QrColors};
+
+/// How the shapes of a layer are filled.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Paint {
+    Solid(String),
+    Gradient(Gradient),
+    Image(ImagePaint),
+}
+
+/// An opaque image stretched over a square in module coordinates, showing
+/// wherever the shapes of its layer cover it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImagePaint {
+    pub image: Arc<RgbaImage>,
+    /// The image encoded as a PNG `data:` URI, for embedding in SVG output.
+    pub data_uri: Arc<str>,
+    pub x: f64,
+    pub y: f64,
+    pub size: f64,
+}
+
+impl ImagePaint {
+    /// Color of the image pixel under the point (x, y) in module
+    /// coordinates, clamped to the image edges.
+    pub fn color_at(&self, x: f64, y: f64) -> (u8, u8, u8, u8) {
+        let (width, height) = self.image.dimensions();
+        let column = ((x - self.x) / self.size * width as f64)
+            .floor()
+            .clamp(0.0, (width - 1) as f64);
+        let row = ((y - self.y) / self.size * height as f64)
+            .floor()
+            .clamp(0.0, (height - 1) as f64);
+        let pixel = self.image.get_pixel(column as u32, row as u32);
+        (pixel[0], pixel[1], pixel[2], pixel[3])
+    }
+}
+
+impl Paint {
+    /// Paint of the data modules: the gradient if one is set, otherwise the
+    /// solid foreground color.
+    pub fn foreground(colors: &QrColors) -> Self {
+        match &colors.gradient {
+            Some(gradient) => Paint::Gradient(gradient.clone()),
+            None => Paint::Solid(colors.foreground.clone()),
+        }
+    }
+}
+
+/// Placement of a gradient over a symbol of `width` by `height` modules, in
+/// module coordinates.
+///
+/// Linear gradients run through the symbol center along `angle` and span
+/// the full projection of the symbol onto that direction, so the first and
+/// last stops land exactly on the outermost corners. Radial gradients are
+/// centered on the symbol and reach its corners.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GradientGeometry {
+    Linear { start: (f64, f64), end: (f64, f64) },
+    Radial { center: (f64, f64), radius: f64 },
+}
+
+impl GradientGeometry {
+    pub fn new(gradient: &Gradient, (width, height): (f64, f64)) -> Self {
+        let (cx, cy) = (width / 2.0, height / 2.0);
+        match gradient.kind {
+            GradientKind::Linear => {
+                let (sin, cos) = gradient.angle.to_radians().sin_cos();
+                let half_length = (width * cos.abs() + height * sin.abs()) / 2.0;
+                Self::Linear {
+                    start: (cx - cos * half_length, cy - sin * half_length),
+                    end: (cx + cos * half_length, cy + sin * half_length),
+                }
+            }
+            GradientKind::Radial => Self::Radial {
+                center: (cx, cy),
+                radius: cx.hypot(cy),
+            },
+        }
+    }
+
+    /// Gradient position of the point (x, y), clamped to 0.0..=1.0.
+    pub fn position(&self, x: f64, y: f64) -> f64 {
+        let t = match *self {
+            Self::Linear { start, end } => {
+                let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+                ((x - start.0) * dx + (y - start.1) * dy) / (dx * dx + dy * dy)
+            }
+            Self::Radial { center, radius } => (x - center.0).hypot(y - center.1) / radius,
+        };
+        t.clamp(0.0, 1.0)
+    }
+}
+
+/// Resolves colors along a gradient, interpolating between stops in sRGB
+/// the same way SVG viewers do.
+pub struct GradientSampler {
+    geometry: GradientGeometry,
+    stops: Vec<(f64, [f64; 4])>,
+}
+
+impl GradientSampler {
+    pub fn new(gradient: &Gradient, size: (f64, f64)) -> Result<Self, String> {
+        let stops = gradient
+            .offsets()
+            .into_iter()
+            .zip(&gradient.stops)
+            .map(|(offset, stop)| {
+                let (r, g, b, a) = hex_to_rgba(&stop.color)?;
+                Ok((offset, [r as f64, g as f64, b as f64, a as f64]))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(Self {
+            geometry: GradientGeometry::new(gradient, size),
+            stops,
+        })
+    }
+
+    /// Color at the point (x, y) in module coordinates.
+    pub fn color_at(&self, x: f64, y: f64) -> (u8, u8, u8, u8) {
+        let t = self.geometry.position(x, y);
+        let upper = self
+            .stops
+            .iter()
+            .position(|&(offset, _)| offset >= t)
+            .unwrap_or(self.stops.len() - 1);
+        let lower = upper.saturating_sub(1);
+        let ((from, low), (to, high)) = (self.stops[lower], self.stops[upper]);
+
+        let mix = if to > from {
+            ((t - from) / (to - from)).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        let channel = |i: usize| (low[i] + (high[i] - low[i]) * mix).round() as u8;
+        (channel(0), channel(1), channel(2), channel(3))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::GradientStop;
+
+    fn gradient(kind: GradientKind, angle: f64) -> Gradient {
+        Gradient {
+            kind,
+            angle,
+            stops: vec![
+                GradientStop {
+                    offset: None,
+                    color: "#000000".to_string(),
+                },
+                GradientStop {
+                    offset: None,
+                    color: "#0000FF".to_string(),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_linear_geometry_spans_corners() {
+        let horizontal = GradientGeometry::new(&gradient(GradientKind::Linear, 0.0), (20.0, 20.0));
+        assert_eq!(horizontal.position(0.0, 7.0), 0.0);
+        assert_eq!(horizontal.position(10.0, 3.0), 0.5);
+        assert_eq!(horizontal.position(20.0, 0.0), 1.0);
+
+        let diagonal = GradientGeometry::new(&gradient(GradientKind::Linear, 45.0), (20.0, 20.0));
+        assert!(diagonal.position(0.0, 0.0) < 1e-9);
+        assert!((diagonal.position(20.0, 20.0) - 1.0).abs() < 1e-9);
+        assert!((diagonal.position(20.0, 0.0) - 0.5).abs() < 1e-9);
+
+        // A wide symbol keeps the end stops on its own corners
+        let wide = GradientGeometry::new(&gradient(GradientKind::Linear, 45.0), (40.0, 10.0));
+        assert!(wide.position(0.0, 0.0) < 1e-9);
+        assert!((wide.position(40.0, 10.0) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_radial_sampler_interpolates_stops() {
+        let sampler =
+            GradientSampler::new(&gradient(GradientKind::Radial, 0.0), (20.0, 20.0)).unwrap();
+
+        assert_eq!(sampler.color_at(10.0, 10.0), (0, 0, 0, 255));
+        assert_eq!(sampler.color_at(0.0, 0.0), (0, 0, 255, 255));
+        let (_, _, blue, _) = sampler.color_at(5.0, 5.0);
+        assert!(blue > 100 && blue < 155);
+    }
+}
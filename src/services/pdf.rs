@@ -0,0 +1,551 @@
+use std::fmt::Write as _;
+use std::io::Write as _;
+
+use flate2::{write::ZlibEncoder, Compression};
+
+use super::{
+    layout::QrLayout,
+    logo::PlacedLogo,
+    paint::{GradientGeometry, Paint},
+    shapes::{Layer, PathOp},
+    svg::{fixed, num},
+};
+use crate::{
+    errors::ApiError,
+    models::{hex_to_rgba, Gradient, PrintSize},
+};
+
+/// Pixels of logo image embedded per point of page space.
+const LOGO_RESOLUTION: f64 = 4.0;
+const MAX_LOGO_PIXELS: f64 = 2048.0;
+
+/// Renders the layers as a single-page vector PDF.
+///
+/// The page measures one point per canvas pixel, or the physical size when
+/// one was requested, and the quiet zone and padding form the page margin.
+/// Shapes are filled as vector paths in module coordinates, gradients
+/// become native axial or radial shadings, image paints are drawn through a
+/// clip of their shapes, and a logo is embedded as an image with a soft
+/// mask for its alpha.
+pub fn render_pdf(
+    layers: &[Layer],
+    layout: &QrLayout,
+    print: Option<&PrintSize>,
+    background: &str,
+    logo: Option<&PlacedLogo>,
+) -> Result<Vec<u8>, ApiError> {
+    let mut pdf = PdfWriter::new();
+    let catalog = pdf.reserve();
+    let pages = pdf.reserve();
+    let page = pdf.reserve();
+
+    let (width, height) = (layout.width as f64, layout.height as f64);
+    let scale = layout.module_size as f64;
+    let (ox, oy) = (layout.origin().0 as f64, layout.origin().1 as f64);
+    let points = points_per_pixel(layout, print);
+    let (page_width, page_height) = (width * points, height * points);
+    let mut content = String::new();
+    let mut resources = Resources::default();
+
+    // Page space has its origin at the bottom left; flip it so both the
+    // background and the symbol use the top-left origin of the other
+    // renderers, and scale canvas pixels to the page
+    let _ = writeln!(
+        content,
+        "{} 0 0 {} 0 {} cm",
+        fixed(points, 6),
+        fixed(-points, 6),
+        num(page_height)
+    );
+    let (r, g, b, a) = rgba(background)?;
+    if a > 0 {
+        let _ = writeln!(content, "q");
+        set_alpha(&mut content, &mut resources, &mut pdf, a);
+        let _ = writeln!(
+            content,
+            "{} rg 0 0 {} {} re f Q",
+            rgb(r, g, b),
+            num(width),
+            num(height)
+        );
+    }
+
+    let _ = writeln!(
+        content,
+        "{} 0 0 {} {} {} cm",
+        num(scale),
+        num(scale),
+        num(ox),
+        num(oy)
+    );
+    for layer in layers {
+        fill_layer(
+            &mut pdf,
+            &mut content,
+            &mut resources,
+            layer,
+            layout,
+            points,
+        )?;
+    }
+
+    if let Some(logo) = logo {
+        let (x, y, width, height) = logo.bounds();
+        let pixels = |modules: f64| {
+            (modules * scale * LOGO_RESOLUTION)
+                .clamp(1.0, MAX_LOGO_PIXELS)
+                .round() as u32
+        };
+        let image = logo.render(pixels(width), pixels(height))?;
+        let xobject = image_xobject(&mut pdf, &image)?;
+        let name = resources.add("XObject", "Im", xobject);
+        // Image rows run top to bottom, so undo the flip for the unit square
+        let _ = writeln!(
+            content,
+            "q {} 0 0 {} {} {} cm /{} Do Q",
+            num(width),
+            num(-height),
+            num(x),
+            num(y + height),
+            name
+        );
+        for layer in logo.patterns() {
+            fill_layer(
+                &mut pdf,
+                &mut content,
+                &mut resources,
+                layer,
+                layout,
+                points,
+            )?;
+        }
+    }
+
+    let content = pdf.add_stream("", content.as_bytes())?;
+    pdf.set(
+        page,
+        format!(
+            "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] /Resources {} /Contents {} 0 R >>",
+            pages,
+            num(page_width),
+            num(page_height),
+            resources.dictionary(),
+            content
+        ),
+    );
+    pdf.set(
+        pages,
+        format!("<< /Type /Pages /Kids [{} 0 R] /Count 1 >>", page),
+    );
+    pdf.set(
+        catalog,
+        format!("<< /Type /Catalog /Pages {} 0 R >>", pages),
+    );
+
+    Ok(pdf.finish(catalog))
+}
+
+/// Fills one layer's shapes with its paint, in module coordinates.
+fn fill_layer(
+    pdf: &mut PdfWriter,
+    content: &mut String,
+    resources: &mut Resources,
+    layer: &Layer,
+    layout: &QrLayout,
+    points: f64,
+) -> Result<(), ApiError> {
+    let scale = layout.module_size as f64;
+    let (ox, oy) = (layout.origin().0 as f64, layout.origin().1 as f64);
+    let page_height = layout.height as f64 * points;
+
+    let _ = writeln!(content, "q");
+    match &layer.paint {
+        Paint::Solid(color) => {
+            let (r, g, b, a) = rgba(color)?;
+            set_alpha(content, resources, pdf, a);
+            let _ = writeln!(content, "{} rg", rgb(r, g, b));
+        }
+        Paint::Gradient(gradient) => {
+            // Patterns live in default page space, not the current CTM
+            let matrix = [
+                scale * points,
+                0.0,
+                0.0,
+                -scale * points,
+                ox * points,
+                page_height - oy * points,
+            ];
+            let shading = pdf.add(shading_dictionary(
+                gradient,
+                (layout.modules as f64, layout.rows as f64),
+            )?);
+            let pattern = pdf.add(format!(
+                "<< /Type /Pattern /PatternType 2 /Shading {} 0 R /Matrix [{}] >>",
+                shading,
+                matrix.map(|value| fixed(value, 6)).join(" ")
+            ));
+            let name = resources.add("Pattern", "P", pattern);
+            let _ = writeln!(content, "/Pattern cs /{} scn", name);
+        }
+        Paint::Image(_) => {}
+    }
+    for shape in &layer.shapes {
+        path_ops(content, &shape.path());
+    }
+    match &layer.paint {
+        // Clip to the shapes and draw the image through them
+        Paint::Image(fill) => {
+            let xobject = image_xobject(pdf, &fill.image)?;
+            let name = resources.add("XObject", "Im", xobject);
+            let _ = writeln!(
+                content,
+                "W n {} 0 0 {} {} {} cm /{} Do Q",
+                num(fill.size),
+                num(-fill.size),
+                num(fill.x),
+                num(fill.y + fill.size),
+                name
+            );
+        }
+        _ => {
+            let _ = writeln!(content, "f Q");
+        }
+    }
+    Ok(())
+}
+
+/// Points of page space per canvas pixel: one, unless a physical size was
+/// requested.
+pub fn points_per_pixel(layout: &QrLayout, print: Option<&PrintSize>) -> f64 {
+    match print {
+        Some(print) => print.points() / layout.width as f64,
+        None => 1.0,
+    }
+}
+
+/// Writes a shape's outline as PDF path construction operators.
+pub fn path_ops(content: &mut String, ops: &[PathOp]) {
+    for op in ops {
+        let _ = match *op {
+            PathOp::MoveTo(x, y) => writeln!(content, "{} {} m", num(x), num(y)),
+            PathOp::LineTo(x, y) => writeln!(content, "{} {} l", num(x), num(y)),
+            PathOp::CubicTo(x1, y1, x2, y2, x, y) => writeln!(
+                content,
+                "{} {} {} {} {} {} c",
+                num(x1),
+                num(y1),
+                num(x2),
+                num(y2),
+                num(x),
+                num(y)
+            ),
+            PathOp::Close => writeln!(content, "h"),
+        };
+    }
+}
+
+/// Selects a constant fill opacity through a graphics state, when needed.
+fn set_alpha(content: &mut String, resources: &mut Resources, pdf: &mut PdfWriter, alpha: u8) {
+    if alpha < 255 {
+        let state = pdf.add(format!(
+            "<< /Type /ExtGState /ca {} >>",
+            num(alpha as f64 / 255.0)
+        ));
+        let name = resources.add("ExtGState", "GS", state);
+        let _ = writeln!(content, "/{} gs", name);
+    }
+}
+
+/// Axial or radial shading with one linear segment per pair of stops, in
+/// module coordinates. The dictionary is valid in both PDF and PostScript.
+/// Shadings are opaque, so the alpha of gradient stops is ignored.
+pub fn shading_dictionary(gradient: &Gradient, size: (f64, f64)) -> Result<String, ApiError> {
+    let mut stops = Vec::new();
+    for (offset, stop) in gradient.offsets().into_iter().zip(&gradient.stops) {
+        let (r, g, b, _) = rgba(&stop.color)?;
+        stops.push((offset, rgb(r, g, b)));
+    }
+
+    // Stitching functions must cover 0..1 without empty segments
+    if let Some(&(offset, ref color)) = stops.first() {
+        if offset > 0.0 {
+            stops.insert(0, (0.0, color.clone()));
+        }
+    }
+    if let Some(&(offset, ref color)) = stops.last() {
+        if offset < 1.0 {
+            stops.push((1.0, color.clone()));
+        }
+    }
+    let segments: Vec<_> = stops
+        .windows(2)
+        .filter(|pair| pair[1].0 > pair[0].0)
+        .map(|pair| (pair[0].0, pair[1].0, &pair[0].1, &pair[1].1))
+        .collect();
+
+    let functions: Vec<String> = segments
+        .iter()
+        .map(|(_, _, from, to)| {
+            format!(
+                "<< /FunctionType 2 /Domain [0 1] /C0 [{}] /C1 [{}] /N 1 >>",
+                from, to
+            )
+        })
+        .collect();
+    let bounds: Vec<String> = segments
+        .iter()
+        .skip(1)
+        .map(|(start, ..)| num(*start))
+        .collect();
+    let encode = vec!["0 1"; segments.len()].join(" ");
+    let function = format!(
+        "<< /FunctionType 3 /Domain [0 1] /Functions [{}] /Bounds [{}] /Encode [{}] >>",
+        functions.join(" "),
+        bounds.join(" "),
+        encode
+    );
+
+    let (shading_type, coords) = match GradientGeometry::new(gradient, size) {
+        GradientGeometry::Linear { start, end } => {
+            (2, [start.0, start.1, end.0, end.1].map(num).join(" "))
+        }
+        GradientGeometry::Radial { center, radius } => (
+            3,
+            [center.0, center.1, 0.0, center.0, center.1, radius]
+                .map(num)
+                .join(" "),
+        ),
+    };
+
+    Ok(format!(
+        "<< /ShadingType {} /ColorSpace /DeviceRGB /Coords [{}] /Function {} /Extend [true true] >>",
+        shading_type, coords, function
+    ))
+}
+
+/// Embeds an RGBA image as an RGB image with a grayscale soft mask.
+fn image_xobject(pdf: &mut PdfWriter, image: &image::RgbaImage) -> Result<usize, ApiError> {
+    let (width, height) = image.dimensions();
+    let mut color = Vec::with_capacity((width * height * 3) as usize);
+    let mut alpha = Vec::with_capacity((width * height) as usize);
+    for pixel in image.pixels() {
+        color.extend_from_slice(&pixel.0[..3]);
+        alpha.push(pixel[3]);
+    }
+
+    let image_dict = |color_space: &str| {
+        format!(
+            "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /{} /BitsPerComponent 8",
+            width, height, color_space
+        )
+    };
+    let mask = pdf.add_stream(&image_dict("DeviceGray"), &alpha)?;
+    pdf.add_stream(
+        &format!("{} /SMask {} 0 R", image_dict("DeviceRGB"), mask),
+        &color,
+    )
+}
+
+fn rgba(color: &str) -> Result<(u8, u8, u8, u8), ApiError> {
+    hex_to_rgba(color).map_err(ApiError::ValidationError)
+}
+
+/// PDF color operands, each channel between 0 and 1.
+pub fn rgb(r: u8, g: u8, b: u8) -> String {
+    [r, g, b].map(|c| num(c as f64 / 255.0)).join(" ")
+}
+
+/// Named entries of the page resource dictionary, by category.
+#[derive(Default)]
+struct Resources {
+    entries: Vec<(&'static str, String, usize)>,
+}
+
+impl Resources {
+    fn add(&mut self, category: &'static str, prefix: &str, object: usize) -> String {
+        let name = format!("{}{}", prefix, self.entries.len());
+        self.entries.push((category, name.clone(), object));
+        name
+    }
+
+    fn dictionary(&self) -> String {
+        let mut dictionary = String::from("<<");
+        for category in ["ExtGState", "Pattern", "XObject"] {
+            let entries: Vec<String> = self
+                .entries
+                .iter()
+                .filter(|(c, ..)| *c == category)
+                .map(|(_, name, object)| format!("/{} {} 0 R", name, object))
+                .collect();
+            if !entries.is_empty() {
+                let _ = write!(dictionary, " /{} << {} >>", category, entries.join(" "));
+            }
+        }
+        dictionary.push_str(" >>");
+        dictionary
+    }
+}
+
+/// Minimal PDF file builder: numbered objects plus a cross-reference table.
+struct PdfWriter {
+    objects: Vec<Vec<u8>>,
+}
+
+impl PdfWriter {
+    fn new() -> Self {
+        Self {
+            objects: Vec::new(),
+        }
+    }
+
+    /// Allocates an object number to be filled in later with `set`.
+    fn reserve(&mut self) -> usize {
+        self.objects.push(Vec::new());
+        self.objects.len()
+    }
+
+    fn set(&mut self, id: usize, body: String) {
+        self.objects[id - 1] = body.into_bytes();
+    }
+
+    fn add(&mut self, body: String) -> usize {
+        self.objects.push(body.into_bytes());
+        self.objects.len()
+    }
+
+    /// Adds a Flate-compressed stream; `dictionary` holds extra entries.
+    fn add_stream(&mut self, dictionary: &str, data: &[u8]) -> Result<usize, ApiError> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(data)
+            .and_then(|_| encoder.finish())
+            .map(|compressed| {
+                let extra = if dictionary.is_empty() {
+                    String::new()
+                } else {
+                    format!("{} ", dictionary)
+                };
+                let mut body = format!(
+                    "<< {}/Filter /FlateDecode /Length {} >>\nstream\n",
+                    extra,
+                    compressed.len()
+                )
+                .into_bytes();
+                body.extend_from_slice(&compressed);
+                body.extend_from_slice(b"\nendstream");
+                self.objects.push(body);
+                self.objects.len()
+            })
+            .map_err(|e| ApiError::GenerationError(format!("Failed to compress PDF stream: {}", e)))
+    }
+
+    fn finish(self, root: usize) -> Vec<u8> {
+        // The binary comment marks the file as binary for transfer tools
+        let mut out = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut offsets = Vec::with_capacity(self.objects.len());
+        for (index, body) in self.objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+            out.extend_from_slice(body);
+            out.extend_from_slice(b"\nendobj\n");
+        }
+
+        let xref = out.len();
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", self.objects.len() + 1);
+        for offset in offsets {
+            let _ = writeln!(table, "{:010} 00000 n ", offset);
+        }
+        let _ = write!(
+            table,
+            "trailer\n<< /Size {} /Root {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            self.objects.len() + 1,
+            root,
+            xref
+        );
+        out.extend_from_slice(table.as_bytes());
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{EyeStyle, GradientKind, GradientStop, ModuleStyle, QrColors};
+    use crate::services::{matrix::QrMatrix, shapes::symbol_layers};
+    use qrcode::QrCode;
+
+    fn pdf_for(colors: &QrColors, style: ModuleStyle) -> String {
+        let qr_code = QrCode::new(b"hello").unwrap();
+        let matrix = QrMatrix::from_qr_code(&qr_code, 4);
+        let layout = QrLayout::compute(&matrix, 290).unwrap();
+        let layers = symbol_layers(&matrix, &style, &EyeStyle::default(), colors);
+        let bytes = render_pdf(&layers, &layout, None, &colors.background, None).unwrap();
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+
+    #[test]
+    fn test_render_pdf_structure() {
+        let pdf = pdf_for(&QrColors::default(), ModuleStyle::Dots);
+
+        assert!(pdf.starts_with("%PDF-1.4"));
+        assert!(pdf.contains("/MediaBox [0 0 290 290]"));
+        assert!(pdf.contains("/Filter /FlateDecode"));
+        assert!(pdf.trim_end().ends_with("%%EOF"));
+
+        // Every xref offset points at its object header
+        let xref_start: usize = pdf.lines().rev().nth(1).unwrap().parse().unwrap();
+        let table = &pdf[xref_start..];
+        for (index, line) in table
+            .lines()
+            .skip(3)
+            .take_while(|l| l.ends_with(" n "))
+            .enumerate()
+        {
+            let offset: usize = line[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(&format!("{} 0 obj", index + 1)));
+        }
+    }
+
+    #[test]
+    fn test_path_ops_use_module_coordinates() {
+        let mut content = String::new();
+        path_ops(
+            &mut content,
+            &[
+                PathOp::MoveTo(0.0, 0.0),
+                PathOp::LineTo(7.0, 0.0),
+                PathOp::CubicTo(1.0, 2.0, 3.0, 4.0, 5.0, 6.5),
+                PathOp::Close,
+            ],
+        );
+        assert_eq!(content, "0 0 m\n7 0 l\n1 2 3 4 5 6.5 c\nh\n");
+    }
+
+    #[test]
+    fn test_gradient_becomes_shading() {
+        let colors = QrColors {
+            gradient: Some(Gradient {
+                kind: GradientKind::Linear,
+                angle: 0.0,
+                stops: vec![
+                    GradientStop {
+                        offset: Some(0.25),
+                        color: "#000000".to_string(),
+                    },
+                    GradientStop {
+                        offset: None,
+                        color: "#0000FF".to_string(),
+                    },
+                ],
+            }),
+            ..Default::default()
+        };
+        let pdf = pdf_for(&colors, ModuleStyle::Square);
+
+        assert!(pdf.contains("/ShadingType 2"));
+        assert!(pdf.contains("/Coords [0 10.5 21 10.5]"));
+        // The flat start before the first stop is its own segment
+        assert!(pdf.contains("/Bounds [0.25]"));
+        assert!(pdf.contains("/Pattern << /P0"));
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::{HashMap, HashSet};
+
+use image::RgbaImage;
+use png::{
+    AdaptiveFilterType, BitDepth, ColorType, Compression, FilterType, PixelDimensions, Unit,
+};
+
+use crate::{
+    errors::ApiError,
+    models::{PngCompression, PrintSize},
+};
+
+const BLACK: [u8; 4] = [0, 0, 0, 255];
+const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+/// Encodes the image in the smallest PNG color type that holds it exactly.
+///
+/// Plain black on white becomes 1-bit grayscale, and anything with at most
+/// 256 colors (including anti-aliased edges and translucent colors) an
+/// indexed palette at 1, 2, 4 or 8 bits per pixel. Only gradients and
+/// detailed logos fall back to 8-bit RGB or RGBA. A print size is recorded
+/// as the resolution in a pHYs chunk.
+pub fn encode_png(
+    image: &RgbaImage,
+    compression: &PngCompression,
+    print: Option<&PrintSize>,
+) -> Result<Vec<u8>, ApiError> {
+    let (width, height) = image.dimensions();
+    let mut png_bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png_bytes, width, height);
+    encoder.set_compression(match compression {
+        PngCompression::Fast => Compression::Fast,
+        PngCompression::Balanced => Compression::Default,
+        PngCompression::Smallest => Compression::Best,
+    });
+    encoder.set_pixel_dims(print.map(|print| PixelDimensions {
+        xppu: print.pixels_per_meter(),
+        yppu: print.pixels_per_meter(),
+        unit: Unit::Meter,
+    }));
+
+    let data = match palette(image) {
+        Some(colors)
+            if colors
+                .iter()
+                .all(|color| *color == BLACK || *color == WHITE) =>
+        {
+            encoder.set_color(ColorType::Grayscale);
+            encoder.set_depth(BitDepth::One);
+            // Filters rarely pay off below 8 bits per pixel
+            encoder.set_filter(FilterType::NoFilter);
+            pack(image, 1, |pixel| (pixel == WHITE) as u8)
+        }
+        Some(colors) => {
+            let bits = match colors.len() {
+                0..=2 => 1,
+                3..=4 => 2,
+                5..=16 => 4,
+                _ => 8,
+            };
+            let index: HashMap<[u8; 4], u8> = colors
+                .iter()
+                .enumerate()
+                .map(|(i, &color)| (color, i as u8))
+                .collect();
+
+            encoder.set_color(ColorType::Indexed);
+            encoder.set_depth(match bits {
+                1 => BitDepth::One,
+                2 => BitDepth::Two,
+                4 => BitDepth::Four,
+                _ => BitDepth::Eight,
+            });
+            encoder.set_palette(
+                colors
+                    .iter()
+                    .flat_map(|c| [c[0], c[1], c[2]])
+                    .collect::<Vec<_>>(),
+            );
+            // Translucent entries come first, so tRNS stops at the last one
+            let translucent = colors.iter().take_while(|c| c[3] < 255).count();
+            if translucent > 0 {
+                encoder.set_trns(
+                    colors[..translucent]
+                        .iter()
+                        .map(|c| c[3])
+                        .collect::<Vec<_>>(),
+                );
+            }
+            encoder.set_filter(FilterType::NoFilter);
+            pack(image, bits, |pixel| index[&pixel])
+        }
+        None => {
+            if matches!(compression, PngCompression::Smallest) {
+                encoder.set_adaptive_filter(AdaptiveFilterType::Adaptive);
+            }
+            if image.pixels().all(|pixel| pixel[3] == 255) {
+                encoder.set_color(ColorType::Rgb);
+                image
+                    .pixels()
+                    .flat_map(|pixel| [pixel[0], pixel[1], pixel[2]])
+                    .collect()
+            } else {
+                encoder.set_color(ColorType::Rgba);
+                image.as_raw().clone()
+            }
+        }
+    };
+
+    let png_error =
+        |e: png::EncodingError| ApiError::GenerationError(format!("Failed to encode PNG: {}", e));
+    let mut writer = encoder.write_header().map_err(png_error)?;
+    writer.write_image_data(&data).map_err(png_error)?;
+    writer.finish().map_err(png_error)?;
+
+    Ok(png_bytes)
+}
+
+/// Distinct colors of the image with translucent ones first, or `None`
+/// when there are more than a palette can hold.
+fn palette(image: &RgbaImage) -> Option<Vec<[u8; 4]>> {
+    let mut colors: Vec<[u8; 4]> = Vec::new();
+    let mut seen = HashSet::new();
+    for pixel in image.pixels() {
+        if seen.insert(pixel.0) {
+            if colors.len() == 256 {
+                return None;
+            }
+            colors.push(pixel.0);
+        }
+    }
+
+    colors.sort_by_key(|color| color[3] == 255);
+    Some(colors)
+}
+
+/// Packs one sample of `bits` bits per pixel, most significant first, with
+/// every row starting on a byte boundary.
+fn pack(image: &RgbaImage, bits: u8, sample: impl Fn([u8; 4]) -> u8) -> Vec<u8> {
+    let per_byte = (8 / bits) as usize;
+    let row_bytes = (image.width() as usize).div_ceil(per_byte);
+    let mut data = vec![0u8; row_bytes * image.height() as usize];
+
+    for (y, row) in image.rows().enumerate() {
+        for (x, pixel) in row.enumerate() {
+            let shift = 8 - bits as usize * (x % per_byte + 1);
+            data[y * row_bytes + x / per_byte] |= sample(pixel.0) << shift;
+        }
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    /// Color type and bit depth from the IHDR chunk.
+    fn header(png_bytes: &[u8]) -> (u8, u8) {
+        (png_bytes[25], png_bytes[24])
+    }
+
+    fn round_trip(image: &RgbaImage) -> (u8, u8) {
+        let png_bytes = encode_png(image, &PngCompression::default(), None).unwrap();
+        let decoded = image::load_from_memory(&png_bytes).unwrap().to_rgba8();
+        assert_eq!(&decoded, image);
+        header(&png_bytes)
+    }
+
+    fn checkerboard(width: u32, colors: &[Rgba<u8>]) -> RgbaImage {
+        RgbaImage::from_fn(width, 7, |x, y| colors[((x + y) as usize) % colors.len()])
+    }
+
+    #[test]
+    fn test_black_and_white_is_one_bit_grayscale() {
+        let image = checkerboard(13, &[Rgba(BLACK), Rgba(WHITE)]);
+        // Color type 0 (grayscale) at 1 bit
+        assert_eq!(round_trip(&image), (0, 1));
+    }
+
+    #[test]
+    fn test_few_colors_use_a_palette() {
+        let two = checkerboard(9, &[Rgba([26, 35, 126, 255]), Rgba([255, 248, 225, 255])]);
+        assert_eq!(round_trip(&two), (3, 1));
+
+        let translucent =
+            checkerboard(11, &[Rgba([0, 0, 0, 128]), Rgba([0, 0, 0, 0]), Rgba(BLACK)]);
+        assert_eq!(round_trip(&translucent), (3, 2));
+
+        let shades: Vec<Rgba<u8>> = (0..40).map(|i| Rgba([i * 6, 0, 0, 255])).collect();
+        assert_eq!(round_trip(&checkerboard(50, &shades)), (3, 8));
+    }
+
+    #[test]
+    fn test_many_colors_use_truecolor() {
+        let opaque = RgbaImage::from_fn(40, 40, |x, y| Rgba([x as u8 * 6, y as u8 * 6, 0, 255]));
+        assert_eq!(round_trip(&opaque), (2, 8));
+
+        let translucent =
+            RgbaImage::from_fn(40, 40, |x, y| Rgba([x as u8 * 6, y as u8 * 6, 0, 200]));
+        assert_eq!(round_trip(&translucent), (6, 8));
+    }
+
+    #[test]
+    fn test_print_size_is_recorded_in_phys() {
+        let image = checkerboard(8, &[Rgba(BLACK), Rgba(WHITE)]);
+        let print = PrintSize {
+            width: 10.0,
+            unit: crate::models::LengthUnit::Mm,
+            dpi: 300,
+        };
+        let png_bytes = encode_png(&image, &PngCompression::default(), Some(&print)).unwrap();
+
+        let decoder = png::Decoder::new(&png_bytes[..]);
+        let reader = decoder.read_info().unwrap();
+        let dims = reader.info().pixel_dims.unwrap();
+        assert_eq!((dims.xppu, dims.yppu), (11811, 11811));
+        assert_eq!(dims.unit, Unit::Meter);
+    }
+}
@@ -1,17 +1,52 @@
+use super::{
+    caption::PlacedCaption,
+    ccitt::encode_group4,
+    encoder::{encode, encode_set, EncodedSet, EncodedSymbol},
+    eps::render_eps,
+    frame::PlacedFrame,
+    halftone::Halftone,
+    layout::QrLayout,
+    logo::{Logo, PlacedLogo},
+    matrix::QrMatrix,
+    pdf::render_pdf,
+    png::encode_png,
+    raster::{draw_layers, overlay, render_bilevel, render_raster},
+    shapes::{symbol_layers, Layer},
+    svg::render_svg,
+    text::{render_text, text_dimensions},
+};
 use crate::{
     config::Config,
     errors::ApiError,
-    models::{QrCustomization, OutputFormat},
+    models::{
+        composite, hex_to_rgba, AppendLayout, AppendedSymbol, ChromaSubsampling, GenerationDetails,
+        ModuleStyle, OutputFormat, QrCustomization, StructuredAppendDetails, Symbology,
+        TiffCompression,
+    },
 };
-use qrcode::QrCode;
-use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
-use image::Rgba;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use image::{DynamicImage, RgbImage, RgbaImage};
 use std::io::Cursor;
+use tiff::{
+    encoder::{colortype, compression::Lzw, Rational, TiffEncoder},
+    tags::{ResolutionUnit, Tag},
+};
+
+/// Smallest module size in pixels that JPEG compression reliably keeps
+/// sharp; the 8x8 DCT blocks smear anything finer.
+const MIN_JPEG_MODULE_PIXELS: u32 = 4;
 
 pub struct QrService {
     config: Config,
 }
 
+/// Encoded image bytes (or UTF-8 text) together with the geometry they
+/// were rendered with.
+pub struct GeneratedQr {
+    pub data: Vec<u8>,
+    pub details: GenerationDetails,
+}
+
 impl QrService {
     pub fn new(config: Config) -> Self {
         Self { config }
@@ -21,33 +56,259 @@ impl QrService {
         &self,
         url: &str,
         customization: &QrCustomization,
-    ) -> Result<Vec<u8>, ApiError> {
+    ) -> Result<GeneratedQr, ApiError> {
+        let mut generated = self.generate_qr_codes(url, customization)?;
+        if generated.len() > 1 {
+            return Err(ApiError::ValidationError(format!(
+                "The content takes {} structured append symbols; get them as separate images from POST /generate, or set structured_append.layout to \"tiled\" for one image",
+                generated.len()
+            )));
+        }
+        Ok(generated.swap_remove(0))
+    }
+
+    /// Generates every image for the content: a single code, or the symbols
+    /// of a structured append set in order when the content is split and
+    /// the symbols are not tiled.
+    pub fn generate_qr_codes(
+        &self,
+        url: &str,
+        customization: &QrCustomization,
+    ) -> Result<Vec<GeneratedQr>, ApiError> {
         // Validate inputs
         self.validate_input(url)?;
         customization.validate()?;
 
-        // Create QR code with specified error correction
-        let qr_code = QrCode::with_error_correction_level(
-            url,
-            customization.error_correction.to_qrcode_ecc(),
-        )
-        .map_err(|e| ApiError::GenerationError(format!("Failed to generate QR code: {}", e)))?;
+        // Encode with the specified error correction, raised to H for logos,
+        // in the requested segments, version and mask if any
+        let Some(options) = &customization.structured_append else {
+            return Ok(vec![
+                self.render(encode(url, customization)?, customization)?
+            ]);
+        };
+        let EncodedSet {
+            mut symbols,
+            parity,
+        } = encode_set(url, customization)?;
+        if symbols.len() == 1 {
+            return Ok(vec![self.render(symbols.swap_remove(0), customization)?]);
+        }
 
-        // Generate the image based on format
-        match customization.format {
-            OutputFormat::Png => self.generate_png(&qr_code, customization),
-            OutputFormat::Svg => self.generate_svg(&qr_code, customization),
-            OutputFormat::Jpeg => self.generate_jpeg(&qr_code, customization),
+        let details = StructuredAppendDetails {
+            total: symbols.len() as u8,
+            parity,
+            symbols: symbols
+                .iter()
+                .enumerate()
+                .map(|(index, symbol)| AppendedSymbol {
+                    index: index as u8,
+                    qr_code: None,
+                    segments: symbol.segments.clone(),
+                })
+                .collect(),
+        };
+        let mut generated = match options.layout {
+            AppendLayout::Tiled => {
+                // Neighbouring symbols keep the full quiet zone of QR codes
+                // between them, whatever the border width
+                let matrices: Vec<QrMatrix> =
+                    symbols.iter().map(|symbol| symbol.matrix.clone()).collect();
+                let gap = Symbology::Qr.default_quiet_zone() as usize;
+                let matrix = QrMatrix::tile(&matrices, options.columns(symbols.len()), gap);
+                let segments = symbols
+                    .into_iter()
+                    .flat_map(|symbol| symbol.segments)
+                    .collect();
+                vec![self.render(EncodedSymbol { matrix, segments }, customization)?]
+            }
+            AppendLayout::Separate => symbols
+                .into_iter()
+                .map(|symbol| self.render(symbol, customization))
+                .collect::<Result<_, _>>()?,
+        };
+        for generated in &mut generated {
+            generated.details.structured_append = Some(details.clone());
+        }
+        Ok(generated)
+    }
+
+    /// Lays out and renders an encoded symbol, or a tiled set of them, in
+    /// the requested format.
+    fn render(
+        &self,
+        symbol: EncodedSymbol,
+        customization: &QrCustomization,
+    ) -> Result<GeneratedQr, ApiError> {
+        let error_correction = customization.effective_error_correction();
+        let EncodedSymbol {
+            mut matrix,
+            segments,
+        } = symbol;
+
+        // Text output is drawn straight from the modules, whatever the size
+        if customization.format.is_text() {
+            let text = render_text(&matrix, &customization.format, &customization.colors)?;
+            return Ok(GeneratedQr {
+                data: text.into_bytes(),
+                details: GenerationDetails {
+                    dimensions: text_dimensions(&matrix, &customization.format),
+                    warnings: Vec::new(),
+                    segments,
+                    structured_append: None,
+                },
+            });
+        }
+
+        // A frame keeps the requested size and shrinks the code to fit inside
+        let frame = match &customization.frame {
+            Some(options) => Some(PlacedFrame::new(
+                options,
+                &customization.colors,
+                customization.size.to_pixels(),
+            )?),
+            None => None,
+        };
+        let mut layout = match &frame {
+            Some(frame) => frame.layout(&matrix)?,
+            None => QrLayout::compute(&matrix, customization.size.to_pixels())?,
+        };
+        let caption = match &customization.caption {
+            Some(options) => Some(PlacedCaption::new(
+                options,
+                &mut layout,
+                &customization.colors.foreground,
+            )?),
+            None => None,
+        };
+        let mut warnings = Vec::new();
+        if matches!(customization.format, OutputFormat::Jpeg)
+            && layout.module_size < MIN_JPEG_MODULE_PIXELS
+        {
+            warnings.push(format!(
+                "Modules are only {}px wide and may blur under JPEG compression; use at least {}px per module (a larger size or smaller border_width), PNG, or a higher quality",
+                layout.module_size, MIN_JPEG_MODULE_PIXELS
+            ));
+        }
+
+        // Clear the modules under the logo before building shapes
+        let logo = match &customization.logo {
+            Some(options) => {
+                let logo = Logo::decode(&options.data)?;
+                let placed = PlacedLogo::new(logo, &matrix, options, &error_correction)?;
+                matrix.clear_region(placed.cleared());
+                Some(placed)
+            }
+            None => None,
+        };
+
+        let mut layers = frame.map(|frame| frame.layers(&layout)).unwrap_or_default();
+        match &customization.halftone {
+            Some(options) => {
+                let halftone =
+                    Halftone::new(options, &error_correction, &layout, &customization.colors)?;
+                layers.extend(halftone.layers(
+                    &matrix,
+                    &customization.eye_style,
+                    &customization.colors,
+                ));
+            }
+            None => layers.extend(symbol_layers(
+                &matrix,
+                &customization.module_style,
+                &customization.eye_style,
+                &customization.colors,
+            )),
         }
+        layers.extend(logo.as_ref().and_then(PlacedLogo::plate_layer));
+        // Function patterns under the logo are drawn again on top of it,
+        // as squares like the rest of a halftone's patterns
+        let logo = logo.map(|mut logo| {
+            let style = match customization.halftone {
+                Some(_) => &ModuleStyle::Square,
+                None => &customization.module_style,
+            };
+            logo.show_patterns(&matrix, style, &customization.colors);
+            logo
+        });
+        // SVG sets the caption as text; everything else draws its glyphs
+        if !matches!(customization.format, OutputFormat::Svg) {
+            layers.extend(caption.as_ref().map(|caption| caption.layer(&layout)));
+        }
+
+        // Generate the image based on format
+        let logo = logo.as_ref();
+        let print = customization.size.print_size();
+        let data = match customization.format {
+            OutputFormat::Png => self.generate_png(&layers, &layout, logo, customization),
+            OutputFormat::Svg => {
+                self.generate_svg(&layers, &layout, logo, caption.as_ref(), customization)
+            }
+            OutputFormat::Jpeg => self.generate_jpeg(&layers, &layout, logo, customization),
+            OutputFormat::Webp => self.generate_webp(&layers, &layout, logo, customization),
+            OutputFormat::Pdf => render_pdf(
+                &layers,
+                &layout,
+                print,
+                &customization.colors.background,
+                logo,
+            ),
+            OutputFormat::Eps => render_eps(&layers, &layout, print, &customization.colors, logo),
+            OutputFormat::Gif => self.generate_gif(&layers, &layout, logo, customization),
+            OutputFormat::Bmp => self.generate_bmp(&layers, &layout, logo, customization),
+            OutputFormat::Tiff => self.generate_tiff(&layers, &layout, logo, customization),
+            OutputFormat::Ico => self.generate_ico(&layers, &layout, logo, customization),
+            OutputFormat::Text | OutputFormat::Ansi | OutputFormat::Ascii => {
+                unreachable!("text formats return early")
+            }
+        }?;
+
+        Ok(GeneratedQr {
+            data,
+            details: GenerationDetails {
+                dimensions: layout.dimensions(),
+                warnings,
+                segments,
+                structured_append: None,
+            },
+        })
     }
 
+    /// Generates the code as a base64 `data:` URL, or as the raw string for
+    /// text formats. Separate structured append symbols come back as the
+    /// first one, with every symbol's own code in the details.
     pub fn generate_qr_base64(
         &self,
         url: &str,
         customization: &QrCustomization,
+    ) -> Result<(String, GenerationDetails), ApiError> {
+        let mut generated = self.generate_qr_codes(url, customization)?;
+        let mut codes = generated
+            .iter()
+            .map(|generated| self.encode_output(&generated.data, customization))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut details = generated.swap_remove(0).details;
+        if let (true, Some(structured_append)) = (codes.len() > 1, &mut details.structured_append) {
+            for (symbol, code) in structured_append.symbols.iter_mut().zip(&codes) {
+                symbol.qr_code = Some(code.clone());
+            }
+        }
+        Ok((codes.swap_remove(0), details))
+    }
+
+    /// Output bytes as a base64 `data:` URL, or as the string itself for
+    /// text formats.
+    fn encode_output(
+        &self,
+        data: &[u8],
+        customization: &QrCustomization,
     ) -> Result<String, ApiError> {
-        let image_data = self.generate_qr_code(url, customization)?;
-        let base64_data = BASE64.encode(&image_data);
+        if customization.format.is_text() {
+            return String::from_utf8(data.to_vec())
+                .map_err(|e| ApiError::GenerationError(format!("Invalid text output: {}", e)));
+        }
+
+        let base64_data = BASE64.encode(data);
         
         let data_url = format!(
             "data:{};base64,{}",
@@ -73,92 +334,348 @@ impl QrService {
         Ok(())
     }
 
+    fn render_rgba(
+        &self,
+        layers: &[Layer],
+        layout: &QrLayout,
+        logo: Option<&PlacedLogo>,
+        customization: &QrCustomization,
+    ) -> Result<RgbaImage, ApiError> {
+        let mut image = render_raster(layers, layout, &customization.colors.background)
+            .map_err(ApiError::ValidationError)?;
+        if let Some(logo) = logo {
+            logo.draw(&mut image, layout)?;
+            draw_layers(&mut image, logo.patterns(), layout).map_err(ApiError::ValidationError)?;
+        }
+        Ok(image)
+    }
+
     fn generate_png(
         &self,
-        qr_code: &QrCode,
+        layers: &[Layer],
+        layout: &QrLayout,
+        logo: Option<&PlacedLogo>,
         customization: &QrCustomization,
     ) -> Result<Vec<u8>, ApiError> {
-        let size = customization.size.to_pixels();
-        
-        // Get colors as RGB
-        let (fg_r, fg_g, fg_b) = customization.colors.foreground_rgb()
-            .map_err(|e| ApiError::ValidationError(e))?;
-        let (bg_r, bg_g, bg_b) = customization.colors.background_rgb()
-            .map_err(|e| ApiError::ValidationError(e))?;
-
-        // Create RGBA image for better color control
-        let image = qr_code.render::<Rgba<u8>>()
-            .min_dimensions(size, size)
-            .dark_color(Rgba([fg_r, fg_g, fg_b, 255]))
-            .light_color(Rgba([bg_r, bg_g, bg_b, 255]))
-            .quiet_zone(true) // This adds border
-            .build();
+        let image = self.render_rgba(layers, layout, logo, customization)?;
 
-        // Convert to PNG bytes
-        let mut png_bytes = Vec::new();
-        image.write_to(&mut Cursor::new(&mut png_bytes), image::ImageFormat::Png)
-            .map_err(|e| ApiError::GenerationError(format!("Failed to encode PNG: {}", e)))?;
-
-        Ok(png_bytes)
+        encode_png(
+            &image,
+            &customization.png_compression.clone().unwrap_or_default(),
+            customization.size.print_size(),
+        )
     }
 
     fn generate_svg(
         &self,
-        qr_code: &QrCode,
+        layers: &[Layer],
+        layout: &QrLayout,
+        logo: Option<&PlacedLogo>,
+        caption: Option<&PlacedCaption>,
         customization: &QrCustomization,
     ) -> Result<Vec<u8>, ApiError> {
-        // For now, generate PNG and convert to bytes
-        // TODO: Implement proper SVG generation
-        let png_data = self.generate_png(qr_code, customization)?;
-        
-        // Create a simple SVG wrapper for the PNG (temporary solution)
-        let size = customization.size.to_pixels();
-        let base64_png = BASE64.encode(&png_data);
-        let svg_content = format!(
-            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}">
-  <image href="data:image/png;base64,{}" width="{}" height="{}"/>
-</svg>"#,
-            size, size, base64_png, size, size
+        let svg_content = render_svg(
+            layers,
+            layout,
+            customization.size.print_size(),
+            &customization.colors.background,
+            logo,
+            caption,
         );
 
         Ok(svg_content.into_bytes())
     }
 
+    /// Renders the code and flattens any transparency onto the declared
+    /// backdrop, for formats without an alpha channel. Refuses rather than
+    /// silently picking a matte color when no backdrop is set.
+    fn render_opaque(
+        &self,
+        layers: &[Layer],
+        layout: &QrLayout,
+        logo: Option<&PlacedLogo>,
+        customization: &QrCustomization,
+        format_name: &str,
+    ) -> Result<RgbImage, ApiError> {
+        let mut image = self.render_rgba(layers, layout, logo, customization)?;
+        if image.pixels().any(|pixel| pixel[3] < 255) {
+            let backdrop = customization.colors.backdrop.as_deref().ok_or_else(|| {
+                ApiError::ValidationError(format!(
+                    "{} does not support transparency; set colors.backdrop to flatten onto a matte color, or use PNG or SVG",
+                    format_name
+                ))
+            })?;
+            let (r, g, b, _) = hex_to_rgba(backdrop).map_err(ApiError::ValidationError)?;
+            let mut matte =
+                RgbaImage::from_pixel(image.width(), image.height(), image::Rgba([r, g, b, 255]));
+            overlay(&mut matte, &image, 0, 0);
+            image = matte;
+        }
+        Ok(DynamicImage::ImageRgba8(image).to_rgb8())
+    }
+
     fn generate_jpeg(
         &self,
-        qr_code: &QrCode,
+        layers: &[Layer],
+        layout: &QrLayout,
+        logo: Option<&PlacedLogo>,
         customization: &QrCustomization,
     ) -> Result<Vec<u8>, ApiError> {
-        let size = customization.size.to_pixels();
-        
-        // Get colors as RGB
-        let (fg_r, fg_g, fg_b) = customization.colors.foreground_rgb()
-            .map_err(|e| ApiError::ValidationError(e))?;
-        let (bg_r, bg_g, bg_b) = customization.colors.background_rgb()
-            .map_err(|e| ApiError::ValidationError(e))?;
-
-        // Create RGB image (JPEG doesn't support transparency)
-        let image = qr_code.render::<image::Rgb<u8>>()
-            .min_dimensions(size, size)
-            .dark_color(image::Rgb([fg_r, fg_g, fg_b]))
-            .light_color(image::Rgb([bg_r, bg_g, bg_b]))
-            .quiet_zone(true)
-            .build();
+        let image = self.render_opaque(layers, layout, logo, customization, "JPEG")?;
 
         // Convert to JPEG bytes
         let mut jpeg_bytes = Vec::new();
-        image.write_to(&mut Cursor::new(&mut jpeg_bytes), image::ImageFormat::Jpeg)
+        let mut encoder =
+            jpeg_encoder::Encoder::new(&mut jpeg_bytes, customization.quality.unwrap_or(75));
+        encoder.set_sampling_factor(
+            match customization.chroma_subsampling.clone().unwrap_or_default() {
+                ChromaSubsampling::Ratio444 => jpeg_encoder::SamplingFactor::R_4_4_4,
+                ChromaSubsampling::Ratio420 => jpeg_encoder::SamplingFactor::R_4_2_0,
+            },
+        );
+        if let Some(print) = customization.size.print_size() {
+            encoder.set_density(jpeg_encoder::PixelDensity::dpi(print.dpi as u16));
+        }
+        encoder
+            .encode(
+                &image,
+                layout.width as u16,
+                layout.height as u16,
+                jpeg_encoder::ColorType::Rgb,
+            )
             .map_err(|e| ApiError::GenerationError(format!("Failed to encode JPEG: {}", e)))?;
 
         Ok(jpeg_bytes)
     }
+
+    fn generate_gif(
+        &self,
+        layers: &[Layer],
+        layout: &QrLayout,
+        logo: Option<&PlacedLogo>,
+        customization: &QrCustomization,
+    ) -> Result<Vec<u8>, ApiError> {
+        let colors = &customization.colors;
+        let base = colors
+            .effective_background()
+            .map_err(ApiError::ValidationError)?;
+        let foreground = composite(
+            colors
+                .foreground_rgba()
+                .map_err(ApiError::ValidationError)?,
+            base,
+        );
+        let background = colors
+            .background_rgba()
+            .map_err(ApiError::ValidationError)?;
+
+        // Palette index 0 is the background, 1 the foreground. A fully
+        // transparent background becomes the transparent index.
+        let (transparent, background) = if background.3 == 0 {
+            (Some(0), base)
+        } else {
+            (None, composite(background, base))
+        };
+        let palette = [
+            background.0,
+            background.1,
+            background.2,
+            foreground.0,
+            foreground.1,
+            foreground.2,
+        ];
+        // Two colors leave no room for the logo, only the patterns over it
+        let layers = [layers, logo.map_or(&[][..], PlacedLogo::patterns)].concat();
+        let pixels: Vec<u8> = render_bilevel(&layers, layout, &colors.background)
+            .map_err(ApiError::ValidationError)?
+            .into_iter()
+            .map(u8::from)
+            .collect();
+
+        let (width, height) = (layout.width as u16, layout.height as u16);
+        let gif_error = |e: gif::EncodingError| {
+            ApiError::GenerationError(format!("Failed to encode GIF: {}", e))
+        };
+        let mut encoder =
+            gif::Encoder::new(Vec::new(), width, height, &palette).map_err(gif_error)?;
+        encoder
+            .write_frame(&gif::Frame::from_indexed_pixels(
+                width,
+                height,
+                pixels,
+                transparent,
+            ))
+            .map_err(gif_error)?;
+        encoder
+            .into_inner()
+            .map_err(|e| ApiError::GenerationError(format!("Failed to encode GIF: {}", e)))
+    }
+
+    fn generate_bmp(
+        &self,
+        layers: &[Layer],
+        layout: &QrLayout,
+        logo: Option<&PlacedLogo>,
+        customization: &QrCustomization,
+    ) -> Result<Vec<u8>, ApiError> {
+        let image = self.render_opaque(layers, layout, logo, customization, "BMP")?;
+
+        let mut bmp_bytes = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut bmp_bytes), image::ImageFormat::Bmp)
+            .map_err(|e| ApiError::GenerationError(format!("Failed to encode BMP: {}", e)))?;
+
+        Ok(bmp_bytes)
+    }
+
+    fn generate_tiff(
+        &self,
+        layers: &[Layer],
+        layout: &QrLayout,
+        logo: Option<&PlacedLogo>,
+        customization: &QrCustomization,
+    ) -> Result<Vec<u8>, ApiError> {
+        let tiff_error =
+            |e: tiff::TiffError| ApiError::GenerationError(format!("Failed to encode TIFF: {}", e));
+        let (width, height) = (layout.width, layout.height);
+        let mut tiff_bytes = Vec::new();
+        let mut encoder = TiffEncoder::new(Cursor::new(&mut tiff_bytes)).map_err(tiff_error)?;
+        // Without a print size, record the customary 72 DPI
+        let dpi = customization
+            .size
+            .print_size()
+            .map_or(72, |print| print.dpi);
+
+        match customization.tiff_compression.clone().unwrap_or_default() {
+            TiffCompression::Lzw => {
+                // Keep the alpha channel only when something is see-through
+                let image = self.render_rgba(layers, layout, logo, customization)?;
+                let resolution = Rational { n: dpi, d: 1 };
+                if image.pixels().all(|pixel| pixel[3] == 255) {
+                    let rgb = DynamicImage::ImageRgba8(image).to_rgb8();
+                    let mut tiff = encoder
+                        .new_image_with_compression::<colortype::RGB8, _>(width, height, Lzw)
+                        .map_err(tiff_error)?;
+                    tiff.resolution(ResolutionUnit::Inch, resolution);
+                    tiff.write_data(&rgb)
+                } else {
+                    let mut tiff = encoder
+                        .new_image_with_compression::<colortype::RGBA8, _>(width, height, Lzw)
+                        .map_err(tiff_error)?;
+                    tiff.resolution(ResolutionUnit::Inch, resolution);
+                    tiff.write_data(&image)
+                }
+                .map_err(tiff_error)?;
+            }
+            TiffCompression::Group4 => {
+                // Bilevel fax image: modules are black on white whatever
+                // the requested colors
+                let layers = [layers, logo.map_or(&[][..], PlacedLogo::patterns)].concat();
+                let ink = render_bilevel(&layers, layout, &customization.colors.background)
+                    .map_err(ApiError::ValidationError)?;
+                let data = encode_group4(&ink, width as usize, height as usize);
+
+                let mut directory = encoder.new_directory().map_err(tiff_error)?;
+                let offset = directory.write_data(&data[..]).map_err(tiff_error)?;
+                directory
+                    .write_tag(Tag::ImageWidth, width)
+                    .map_err(tiff_error)?;
+                directory
+                    .write_tag(Tag::ImageLength, height)
+                    .map_err(tiff_error)?;
+                directory
+                    .write_tag(Tag::BitsPerSample, 1u16)
+                    .map_err(tiff_error)?;
+                // CCITT Group 4, WhiteIsZero
+                directory
+                    .write_tag(Tag::Compression, 4u16)
+                    .map_err(tiff_error)?;
+                directory
+                    .write_tag(Tag::PhotometricInterpretation, 0u16)
+                    .map_err(tiff_error)?;
+                directory
+                    .write_tag(Tag::StripOffsets, offset as u32)
+                    .map_err(tiff_error)?;
+                directory
+                    .write_tag(Tag::SamplesPerPixel, 1u16)
+                    .map_err(tiff_error)?;
+                directory
+                    .write_tag(Tag::RowsPerStrip, height)
+                    .map_err(tiff_error)?;
+                directory
+                    .write_tag(Tag::StripByteCounts, data.len() as u32)
+                    .map_err(tiff_error)?;
+                directory
+                    .write_tag(Tag::XResolution, Rational { n: dpi, d: 1 })
+                    .map_err(tiff_error)?;
+                directory
+                    .write_tag(Tag::YResolution, Rational { n: dpi, d: 1 })
+                    .map_err(tiff_error)?;
+                directory
+                    .write_tag(Tag::ResolutionUnit, ResolutionUnit::Inch.to_u16())
+                    .map_err(tiff_error)?;
+                // T6Options: no uncompressed mode
+                directory
+                    .write_tag(Tag::Unknown(293), 0u32)
+                    .map_err(tiff_error)?;
+                directory.finish().map_err(tiff_error)?;
+            }
+        }
+
+        Ok(tiff_bytes)
+    }
+
+    fn generate_ico(
+        &self,
+        layers: &[Layer],
+        layout: &QrLayout,
+        logo: Option<&PlacedLogo>,
+        customization: &QrCustomization,
+    ) -> Result<Vec<u8>, ApiError> {
+        if layout.height > 256 {
+            return Err(ApiError::ValidationError(format!(
+                "ICO images cannot exceed 256x256 pixels, but the caption makes this one {}x{}",
+                layout.width, layout.height
+            )));
+        }
+        let image = self.render_rgba(layers, layout, logo, customization)?;
+
+        // Stored as a PNG inside the icon, which keeps the alpha channel
+        let mut ico_bytes = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut ico_bytes), image::ImageFormat::Ico)
+            .map_err(|e| ApiError::GenerationError(format!("Failed to encode ICO: {}", e)))?;
+
+        Ok(ico_bytes)
+    }
+
+    fn generate_webp(
+        &self,
+        layers: &[Layer],
+        layout: &QrLayout,
+        logo: Option<&PlacedLogo>,
+        customization: &QrCustomization,
+    ) -> Result<Vec<u8>, ApiError> {
+        let image = self.render_rgba(layers, layout, logo, customization)?;
+        let encoder = webp::Encoder::from_rgba(image.as_raw(), image.width(), image.height());
+
+        // Lossless unless a quality is requested; for lossless encoding the
+        // quality value only trades speed for size
+        let webp_bytes = match customization.quality {
+            Some(quality) => encoder.encode_simple(false, quality as f32),
+            None => encoder.encode_simple(true, 75.0),
+        }
+        .map_err(|e| ApiError::GenerationError(format!("Failed to encode WebP: {:?}", e)))?;
+
+        Ok(webp_bytes.to_vec())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::config::Config;
-    use crate::models::v2::{QrSize, ErrorCorrectionLevel, QrColors};
+    use crate::models::{ErrorCorrectionLevel, QrColors, QrSize};
 
     fn create_test_config() -> Config {
         Config {
@@ -169,89 +686,1194 @@ mod tests {
         }
     }
 
+    /// The PDF with its Flate streams inflated where they hold text.
+    fn inflated_pdf(data: &[u8]) -> String {
+        use flate2::read::ZlibDecoder;
+        use std::io::Read;
+
+        let mut text = String::from_utf8_lossy(data).into_owned();
+        let mut rest = data;
+        while let Some(start) = rest.windows(7).position(|window| window == b"stream\n") {
+            let body = &rest[start + 7..];
+            let end = body
+                .windows(10)
+                .position(|window| window == b"\nendstream")
+                .unwrap();
+            let mut stream = String::new();
+            if ZlibDecoder::new(&body[..end])
+                .read_to_string(&mut stream)
+                .is_ok()
+            {
+                text.push_str(&stream);
+            }
+            rest = &body[end + 10..];
+        }
+        text
+    }
+
     fn create_test_customization() -> QrCustomization {
         QrCustomization {
             size: QrSize::Medium,
             error_correction: ErrorCorrectionLevel::M,
             colors: QrColors::default(),
-            border_width: 4,
+            border_width: Some(4),
             format: OutputFormat::Png,
+            ..Default::default()
+        }
+    }
+
+    /// Every image format, two-color GIF included.
+    const IMAGE_FORMATS: [OutputFormat; 9] = [
+        OutputFormat::Png,
+        OutputFormat::Svg,
+        OutputFormat::Jpeg,
+        OutputFormat::Webp,
+        OutputFormat::Pdf,
+        OutputFormat::Eps,
+        OutputFormat::Gif,
+        OutputFormat::Bmp,
+        OutputFormat::Tiff,
+    ];
+
+    /// Image formats in full color, which can show images and eye colors.
+    const COLOR_FORMATS: [OutputFormat; 8] = [
+        OutputFormat::Png,
+        OutputFormat::Svg,
+        OutputFormat::Jpeg,
+        OutputFormat::Webp,
+        OutputFormat::Pdf,
+        OutputFormat::Eps,
+        OutputFormat::Bmp,
+        OutputFormat::Tiff,
+    ];
+
+    const TEXT_FORMATS: [OutputFormat; 3] =
+        [OutputFormat::Text, OutputFormat::Ansi, OutputFormat::Ascii];
+
+    /// Generates `content` as `customization` asks, once in each format.
+    fn generate_in(
+        formats: &[OutputFormat],
+        content: &str,
+        customization: &QrCustomization,
+    ) -> Vec<(OutputFormat, GeneratedQr)> {
+        let service = QrService::new(create_test_config());
+        formats
+            .iter()
+            .map(|format| {
+                let customization = QrCustomization {
+                    format: format.clone(),
+                    ..customization.clone()
+                };
+                let generated = service
+                    .generate_qr_code(content, &customization)
+                    .unwrap_or_else(|e| panic!("{}: {:?}", format, e));
+                (format.clone(), generated)
+            })
+            .collect()
+    }
+
+    /// Raster output decoded to read its colors at points of the symbol.
+    struct Sampler {
+        image: RgbImage,
+        layout: QrLayout,
+    }
+
+    impl Sampler {
+        fn new(data: &[u8], content: &str, customization: &QrCustomization) -> Self {
+            let matrix = encode(content, customization).unwrap().matrix;
+            Self {
+                image: image::load_from_memory(data).unwrap().to_rgb8(),
+                layout: QrLayout::compute(&matrix, customization.size.to_pixels()).unwrap(),
+            }
         }
+
+        /// Color at (x, y) in module coordinates.
+        fn point(&self, x: f64, y: f64) -> [u8; 3] {
+            let (left, top) = self.layout.origin();
+            let scale = self.layout.module_size as f64;
+            self.image
+                .get_pixel(left + (x * scale) as u32, top + (y * scale) as u32)
+                .0
+        }
+
+        /// Color at the center of module (x, y).
+        fn module(&self, x: u32, y: u32) -> [u8; 3] {
+            self.point(x as f64 + 0.5, y as f64 + 0.5)
+        }
+    }
+
+    /// Whether every channel is within `tolerance` of the expected color,
+    /// as JPEG shifts colors slightly.
+    fn close(actual: [u8; 3], expected: [u8; 3], tolerance: u8) -> bool {
+        actual
+            .iter()
+            .zip(expected)
+            .all(|(&a, e)| a.abs_diff(e) <= tolerance)
     }
 
     #[test]
-    fn test_generate_qr_code_v2_success() {
+    fn test_generate_qr_code_success() {
         let config = create_test_config();
-        let service = QrServiceV2::new(config);
+        let service = QrService::new(config);
         let customization = create_test_customization();
         
-        let result = service.generate_qr_code_v2("https://example.com", &customization);
+        let result = service.generate_qr_code("https://example.com", &customization);
         assert!(result.is_ok());
         
-        let png_data = result.unwrap();
+        let png_data = result.unwrap().data;
         assert!(!png_data.is_empty());
         // PNG files start with specific magic bytes
         assert_eq!(&png_data[0..8], &[137, 80, 78, 71, 13, 10, 26, 10]);
     }
 
     #[test]
-    fn test_generate_qr_base64_v2_success() {
+    fn test_generate_qr_base64_success() {
         let config = create_test_config();
-        let service = QrServiceV2::new(config);
+        let service = QrService::new(config);
         let customization = create_test_customization();
         
-        let result = service.generate_qr_base64_v2("https://example.com", &customization);
+        let result = service.generate_qr_base64("https://example.com", &customization);
         assert!(result.is_ok());
         
-        let data_url = result.unwrap();
+        let (data_url, _) = result.unwrap();
         assert!(data_url.starts_with("data:image/png;base64,"));
     }
 
     #[test]
     fn test_generate_svg_format() {
         let config = create_test_config();
-        let service = QrServiceV2::new(config);
+        let service = QrService::new(config);
         let mut customization = create_test_customization();
         customization.format = OutputFormat::Svg;
         
-        let result = service.generate_qr_code_v2("https://example.com", &customization);
+        let result = service.generate_qr_code("https://example.com", &customization);
         assert!(result.is_ok());
         
-        let svg_data = result.unwrap();
+        let svg_data = result.unwrap().data;
         let svg_string = String::from_utf8(svg_data).unwrap();
         assert!(svg_string.contains("<svg"));
         assert!(svg_string.contains("</svg>"));
+        assert!(svg_string.contains("<path"));
+        assert!(!svg_string.contains("data:image/png"));
     }
 
     #[test]
     fn test_custom_colors() {
         let config = create_test_config();
-        let service = QrServiceV2::new(config);
+        let service = QrService::new(config);
         let mut customization = create_test_customization();
-        customization.colors = QrColors::new("#FF0000".to_string(), "#00FF00".to_string()).unwrap();
+        customization.colors = QrColors::new("#1A237E".to_string(), "#FFF59D".to_string()).unwrap();
         
-        let result = service.generate_qr_code_v2("https://example.com", &customization);
+        let result = service.generate_qr_code("https://example.com", &customization);
         assert!(result.is_ok());
     }
 
     #[test]
     fn test_different_sizes() {
         let config = create_test_config();
-        let service = QrServiceV2::new(config);
+        let service = QrService::new(config);
         
         for size in [QrSize::Small, QrSize::Medium, QrSize::Large] {
             let mut customization = create_test_customization();
             customization.size = size;
             
-            let result = service.generate_qr_code_v2("https://example.com", &customization);
+            let result = service.generate_qr_code("https://example.com", &customization);
             assert!(result.is_ok());
         }
     }
 
+    #[test]
+    fn test_output_matches_requested_size() {
+        let config = create_test_config();
+        let service = QrService::new(config);
+
+        for pixels in [50, 123, 300, 777] {
+            let mut customization = create_test_customization();
+            customization.size = QrSize::Custom(pixels);
+
+            let generated = service
+                .generate_qr_code("https://example.com", &customization)
+                .unwrap();
+            let image = image::load_from_memory(&generated.data).unwrap();
+            assert_eq!((image.width(), image.height()), (pixels, pixels));
+            assert_eq!(generated.details.dimensions.width, pixels);
+        }
+    }
+
+    #[test]
+    fn test_border_width_sets_quiet_zone() {
+        let config = create_test_config();
+        let service = QrService::new(config);
+
+        let mut customization = create_test_customization();
+        customization.border_width = Some(0);
+        let without = service
+            .generate_qr_code("https://example.com", &customization)
+            .unwrap();
+
+        customization.border_width = Some(10);
+        let with = service
+            .generate_qr_code("https://example.com", &customization)
+            .unwrap();
+
+        assert_eq!(without.details.dimensions.quiet_zone, 0);
+        assert_eq!(with.details.dimensions.quiet_zone, 10);
+        assert!(without.details.dimensions.module_size > with.details.dimensions.module_size);
+    }
+
+    #[test]
+    fn test_size_too_small_for_quiet_zone() {
+        let config = create_test_config();
+        let service = QrService::new(config);
+
+        let mut customization = create_test_customization();
+        customization.size = QrSize::Custom(50);
+        customization.border_width = Some(50);
+
+        let result = service.generate_qr_code("https://example.com", &customization);
+        assert!(matches!(result, Err(ApiError::ValidationError(_))));
+    }
+
+    #[test]
+    fn test_module_styles_in_every_format() {
+        use crate::models::ModuleStyle;
+
+        let url = "https://example.com";
+        let square = generate_in(&IMAGE_FORMATS, url, &create_test_customization());
+
+        // A dark data module with no dark neighbours shows its own shape
+        let matrix = encode(url, &create_test_customization()).unwrap().matrix;
+        let (x, y) = (0..matrix.height())
+            .flat_map(|y| (0..matrix.width()).map(move |x| (x, y)))
+            .find(|&(x, y)| {
+                matrix.is_dark(x, y)
+                    && !matrix.is_finder(x, y)
+                    && (x == 0 || !matrix.is_dark(x - 1, y))
+                    && (y == 0 || !matrix.is_dark(x, y - 1))
+                    && !matrix.is_dark(x + 1, y)
+                    && !matrix.is_dark(x, y + 1)
+            })
+            .unwrap();
+
+        for style in [
+            ModuleStyle::Square,
+            ModuleStyle::Dots,
+            ModuleStyle::Rounded,
+            ModuleStyle::ConnectedRounded,
+            ModuleStyle::Diamond,
+            ModuleStyle::VerticalBars,
+            ModuleStyle::HorizontalBars,
+        ] {
+            let mut customization = create_test_customization();
+            customization.module_style = style.clone();
+            let generated = generate_in(&IMAGE_FORMATS, url, &customization);
+            for ((format, generated), (_, square)) in generated.iter().zip(&square) {
+                if style != ModuleStyle::Square {
+                    assert_ne!(generated.data, square.data, "{:?} {:?}", style, format);
+                }
+            }
+
+            let png = Sampler::new(&generated[0].1.data, url, &customization);
+            assert_eq!(png.module(x as u32, y as u32)[0], 0, "{:?}", style);
+            // Only squares fill the corners of an isolated module;
+            // anti-aliasing may tint the background a little
+            let corner = png.point(x as f64, y as f64)[0];
+            match style {
+                ModuleStyle::Square => assert_eq!(corner, 0),
+                ModuleStyle::Dots
+                | ModuleStyle::Rounded
+                | ModuleStyle::ConnectedRounded
+                | ModuleStyle::Diamond => assert!(corner > 200, "{:?}: {}", style, corner),
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_eye_styles_in_every_format() {
+        use crate::models::{EyeColors, EyeShape, EyeStyle};
+
+        let url = "https://example.com";
+        let mut customization = create_test_customization();
+        customization.eye_style = EyeStyle {
+            outer: EyeShape::Leaf,
+            inner: EyeShape::Circle,
+        };
+        customization.colors.eyes = vec![
+            EyeColors {
+                outer: Some("#8B0000".to_string()),
+                inner: None,
+            },
+            EyeColors {
+                outer: None,
+                inner: Some("#00008B".to_string()),
+            },
+            EyeColors::default(),
+        ];
+        let width = encode(url, &customization).unwrap().matrix.width() as u32;
+
+        for (format, generated) in generate_in(&COLOR_FORMATS, url, &customization) {
+            match format {
+                OutputFormat::Svg => {
+                    let svg = String::from_utf8(generated.data).unwrap();
+                    assert!(
+                        svg.contains(r##"fill="#8B0000""##) && svg.contains(r##"fill="#00008B""##)
+                    );
+                }
+                OutputFormat::Pdf => {
+                    let pdf = inflated_pdf(&generated.data);
+                    assert!(pdf.contains("0.545 0 0 rg") && pdf.contains("0 0 0.545 rg"));
+                }
+                OutputFormat::Eps => {
+                    let eps = String::from_utf8(generated.data).unwrap();
+                    assert!(
+                        eps.contains("0.545 0 0 setrgbcolor")
+                            && eps.contains("0 0 0.545 setrgbcolor")
+                    );
+                }
+                _ => {
+                    // Ring of the top-left eye, pupil of the top-right one;
+                    // the other parts keep the foreground color
+                    let sampler = Sampler::new(&generated.data, url, &customization);
+                    for ((x, y), expected) in [
+                        ((3, 0), [139, 0, 0]),
+                        ((3, 3), [0, 0, 0]),
+                        ((width - 4, 0), [0, 0, 0]),
+                        ((width - 4, 3), [0, 0, 139]),
+                        ((3, width - 7), [0, 0, 0]),
+                        ((3, width - 4), [0, 0, 0]),
+                    ] {
+                        let actual = sampler.module(x, y);
+                        assert!(
+                            close(actual, expected, 16),
+                            "{:?} at {:?}: {:?}",
+                            format,
+                            (x, y),
+                            actual
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_gradients_in_every_format() {
+        use crate::models::{Gradient, GradientKind, GradientStop};
+
+        let url = "https://example.com";
+        let matrix = encode(url, &create_test_customization()).unwrap().matrix;
+        let width = matrix.width() as u32;
+        let center = width as i32 / 2;
+        let middle = (0..width)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter(|&(x, y)| matrix.is_dark(x as usize, y as usize))
+            .min_by_key(|&(x, y)| (x as i32 - center).pow(2) + (y as i32 - center).pow(2))
+            .unwrap();
+
+        for kind in [GradientKind::Linear, GradientKind::Radial] {
+            let mut customization = create_test_customization();
+            customization.colors.gradient = Some(Gradient {
+                kind,
+                angle: 135.0,
+                stops: vec![
+                    GradientStop {
+                        offset: None,
+                        color: "#1A237E".to_string(),
+                    },
+                    GradientStop {
+                        offset: None,
+                        color: "#880E4F".to_string(),
+                    },
+                ],
+            });
+            let linear = kind == GradientKind::Linear;
+
+            let formats = [
+                OutputFormat::Png,
+                OutputFormat::Svg,
+                OutputFormat::Jpeg,
+                OutputFormat::Tiff,
+                OutputFormat::Pdf,
+            ];
+            for (format, generated) in generate_in(&formats, url, &customization) {
+                match format {
+                    OutputFormat::Svg => {
+                        let svg = String::from_utf8(generated.data).unwrap();
+                        assert!(svg.contains(if linear {
+                            "<linearGradient"
+                        } else {
+                            "<radialGradient"
+                        }));
+                        assert!(svg.contains(r#"fill="url(#qr-gradient-"#));
+                    }
+                    OutputFormat::Pdf => {
+                        let pdf = String::from_utf8_lossy(&generated.data);
+                        assert!(pdf.contains(if linear {
+                            "/ShadingType 2"
+                        } else {
+                            "/ShadingType 3"
+                        }));
+                    }
+                    _ => {
+                        // The finder centers and the dark module nearest the
+                        // middle span the gradient
+                        let sampler = Sampler::new(&generated.data, url, &customization);
+                        let reds: Vec<u8> = [(3, 3), (width - 4, 3), (3, width - 4), middle]
+                            .into_iter()
+                            .map(|(x, y)| sampler.module(x, y)[0])
+                            .collect();
+                        let spread = reds.iter().max().unwrap() - reds.iter().min().unwrap();
+                        assert!(spread > 40, "{:?} {:?}: {:?}", format, kind, reds);
+                    }
+                }
+            }
+        }
+
+        // A pale stop fails the contrast check
+        let mut customization = create_test_customization();
+        customization.colors.gradient = Some(Gradient {
+            kind: GradientKind::Linear,
+            angle: 0.0,
+            stops: vec![
+                GradientStop {
+                    offset: None,
+                    color: "#000000".to_string(),
+                },
+                GradientStop {
+                    offset: None,
+                    color: "#EEEEEE".to_string(),
+                },
+            ],
+        });
+        let service = QrService::new(create_test_config());
+        let result = service.generate_qr_code(url, &customization);
+        assert!(matches!(result, Err(ApiError::ValidationError(_))));
+    }
+
+    #[test]
+    fn test_logo_in_every_format() {
+        use crate::models::LogoOptions;
+
+        let url = "https://example.com";
+        let svg_logo = BASE64.encode(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><circle cx="5" cy="5" r="5" fill="#E53935"/></svg>"##,
+        );
+        let mut customization = create_test_customization();
+        // Smaller symbols lose too many codewords under the logo
+        customization.min_version = Some(6);
+        customization.logo = Some(LogoOptions {
+            data: svg_logo.clone(),
+            background: Some("#FFFFFF".to_string()),
+            ..Default::default()
+        });
+        let matrix = encode(url, &customization).unwrap().matrix;
+        let cleared = |customization: &QrCustomization| {
+            let options = customization.logo.as_ref().unwrap();
+            let level = customization.effective_error_correction();
+            PlacedLogo::new(Logo::decode(&svg_logo).unwrap(), &matrix, options, &level)
+                .unwrap()
+                .cleared()
+        };
+        let (x0, y0, _, _) = cleared(&customization);
+        let middle = matrix.width() as f64 / 2.0;
+
+        for (format, generated) in generate_in(&COLOR_FORMATS, url, &customization) {
+            match format {
+                OutputFormat::Svg => {
+                    let svg = String::from_utf8(generated.data).unwrap();
+                    assert!(svg.contains(r#"xlink:href="data:image/svg+xml;base64,"#));
+                    // The xlink prefix is declared, so the output parses
+                    resvg::usvg::Tree::from_str(&svg, &Default::default()).unwrap();
+                }
+                OutputFormat::Pdf | OutputFormat::Eps => {}
+                _ => {
+                    let sampler = Sampler::new(&generated.data, url, &customization);
+                    let center = sampler.point(middle, middle);
+                    assert!(
+                        close(center, [229, 57, 53], 24),
+                        "{:?}: {:?}",
+                        format,
+                        center
+                    );
+
+                    // The plate shows in the corners of the cleared region,
+                    // outside the round logo
+                    let corner = sampler.point(x0 as f64 + 0.2, y0 as f64 + 0.2);
+                    assert!(
+                        close(corner, [255, 255, 255], 24),
+                        "{:?}: {:?}",
+                        format,
+                        corner
+                    );
+                }
+            }
+        }
+
+        // Without a plate, the modules under the logo are cleared rather
+        // than covered
+        customization.logo = Some(LogoOptions {
+            data: svg_logo.clone(),
+            ..Default::default()
+        });
+        let (x0, y0, x1, y1) = cleared(&customization);
+        assert!((y0..y1).any(|y| (x0..x1).any(|x| matrix.is_dark(x, y))));
+        let generated = &generate_in(&[OutputFormat::Png], url, &customization)[0].1;
+        let sampler = Sampler::new(&generated.data, url, &customization);
+        let (left, top) = sampler.layout.origin();
+        let module = sampler.layout.module_size;
+        for y in top + y0 as u32 * module..top + y1 as u32 * module {
+            for x in left + x0 as u32 * module..left + x1 as u32 * module {
+                let pixel = sampler.image.get_pixel(x, y).0;
+                assert!(pixel.iter().any(|&c| c > 128), "dark pixel at {:?}", (x, y));
+            }
+        }
+
+        // Without the automatic switch to H, level L cannot hide a large logo
+        let mut customization = create_test_customization();
+        customization.error_correction = ErrorCorrectionLevel::L;
+        customization.logo = Some(LogoOptions {
+            data: svg_logo,
+            size: 0.3,
+            auto_error_correction: false,
+            ..Default::default()
+        });
+        let service = QrService::new(create_test_config());
+        let result = service.generate_qr_code(url, &customization);
+        assert!(matches!(result, Err(ApiError::ValidationError(_))));
+    }
+
+    #[test]
+    fn test_logo_keeps_alignment_pattern() {
+        use crate::models::LogoOptions;
+
+        let url = "https://example.com";
+        let mut customization = create_test_customization();
+        // Version 10 has an alignment pattern centered on (28, 28)
+        customization.min_version = Some(10);
+        customization.logo = Some(LogoOptions {
+            data: BASE64.encode(
+                r##"<svg xmlns="http://www.w3.org/2000/svg" width="4" height="4"><rect width="4" height="4" fill="#E53935"/></svg>"##,
+            ),
+            size: 0.25,
+            background: Some("#FFFFFF".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(encode(url, &customization).unwrap().matrix.width(), 57);
+
+        let generated = generate_in(&[OutputFormat::Png, OutputFormat::Svg], url, &customization);
+        let sampler = Sampler::new(&generated[0].1.data, url, &customization);
+        // The pattern sits on top of the logo, dark center and ring
+        // around a light one
+        assert_eq!(sampler.module(28, 28), [0, 0, 0]);
+        assert_eq!(sampler.module(27, 27), [255, 255, 255]);
+        assert_eq!(sampler.module(26, 28), [0, 0, 0]);
+        // Data modules next to it stay under the logo
+        assert_eq!(sampler.module(24, 28), [229, 57, 53]);
+
+        // Vector output draws the patterns after the logo as well
+        let svg = String::from_utf8_lossy(&generated[1].1.data);
+        let logo_at = svg.find("<image").unwrap();
+        assert!(svg[logo_at..].contains("<path"));
+    }
+
+    #[test]
+    fn test_halftone_in_every_format() {
+        use crate::models::HalftoneOptions;
+
+        let url = "https://example.com";
+        let svg_image = BASE64.encode(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="10"><rect width="20" height="10" fill="#1E88E5"/><circle cx="10" cy="5" r="4" fill="#FDD835"/></svg>"##,
+        );
+        let mut customization = create_test_customization();
+        customization.error_correction = ErrorCorrectionLevel::H;
+        customization.halftone = Some(HalftoneOptions {
+            image: svg_image.clone(),
+            dot_size: None,
+        });
+
+        for (format, generated) in generate_in(&COLOR_FORMATS, url, &customization) {
+            if matches!(format, OutputFormat::Svg) {
+                let svg = String::from_utf8(generated.data).unwrap();
+                assert!(svg.contains(r#"fill="url(#qr-image-"#));
+            }
+        }
+
+        // The top-left finder stays solid over the image
+        let mut customization = create_test_customization();
+        customization.halftone = Some(HalftoneOptions {
+            image: svg_image.clone(),
+            dot_size: Some(0.6),
+        });
+        let generated = &generate_in(&[OutputFormat::Png], url, &customization)[0].1;
+        let sampler = Sampler::new(&generated.data, url, &customization);
+        assert_eq!(sampler.module(3, 3)[0], 0);
+
+        // Small dots need a higher error correction level
+        customization.error_correction = ErrorCorrectionLevel::L;
+        customization.halftone = Some(HalftoneOptions {
+            image: svg_image,
+            dot_size: Some(0.3),
+        });
+        let service = QrService::new(create_test_config());
+        let result = service.generate_qr_code(url, &customization);
+        assert!(matches!(result, Err(ApiError::ValidationError(_))));
+    }
+
+    #[test]
+    fn test_micro_qr_in_every_format() {
+        use crate::models::Symbology;
+
+        let mut customization = create_test_customization();
+        customization.symbology = Symbology::MicroQr;
+        customization.border_width = None;
+
+        let formats = [IMAGE_FORMATS.as_slice(), &TEXT_FORMATS].concat();
+        let generated = generate_in(&formats, "12345", &customization);
+        for (format, generated) in &generated {
+            let dimensions = &generated.details.dimensions;
+            assert_eq!(
+                (dimensions.modules, dimensions.quiet_zone),
+                (13, 2),
+                "{}",
+                format
+            );
+        }
+
+        // A single finder pattern in the top-left corner, and none elsewhere
+        let png = Sampler::new(&generated[0].1.data, "12345", &customization);
+        assert_eq!(png.module(3, 3)[0], 0);
+        assert_eq!(png.module(1, 1)[0], 255);
+    }
+
+    #[test]
+    fn test_rmqr_in_every_format() {
+        use crate::models::Symbology;
+
+        let mut customization = create_test_customization();
+        customization.symbology = Symbology::Rmqr;
+        customization.border_width = None;
+        customization.error_correction = ErrorCorrectionLevel::M;
+        customization.max_rows = Some(7);
+
+        let formats = [IMAGE_FORMATS.as_slice(), &TEXT_FORMATS].concat();
+        let generated = generate_in(&formats, "CBL-0042-A", &customization);
+        for (format, generated) in &generated {
+            let dimensions = &generated.details.dimensions;
+            assert_eq!(
+                (dimensions.modules, dimensions.rows, dimensions.quiet_zone),
+                (59, 7, 2),
+                "{}",
+                format
+            );
+            assert!(dimensions.height < dimensions.width, "{}", format);
+        }
+
+        // The finder sits on the left and the sub-finder in the bottom-right
+        let (_, png) = &generated[0];
+        let sampler = Sampler::new(&png.data, "CBL-0042-A", &customization);
+        assert_eq!(sampler.image.height(), png.details.dimensions.height);
+        assert_eq!(sampler.module(3, 3)[0], 0);
+        assert_eq!(sampler.module(1, 1)[0], 255);
+        assert_eq!(sampler.module(56, 4)[0], 0);
+        assert_eq!(sampler.module(55, 3)[0], 255);
+    }
+
+    #[test]
+    fn test_structured_append_layouts() {
+        use crate::models::{AppendLayout, StructuredAppendOptions};
+
+        let config = create_test_config();
+        let service = QrService::new(config);
+        let mut customization = create_test_customization();
+        customization.structured_append = Some(StructuredAppendOptions {
+            max_version: Some(2),
+            ..Default::default()
+        });
+        let content = "x".repeat(60);
+
+        // Separate symbols: the first one is the code, each has its own
+        let (code, details) = service
+            .generate_qr_base64(&content, &customization)
+            .unwrap();
+        let structured_append = details.structured_append.unwrap();
+        assert_eq!(
+            (structured_append.total, structured_append.symbols.len()),
+            (3, 3)
+        );
+        assert_eq!(
+            structured_append.symbols[0].qr_code.as_deref(),
+            Some(code.as_str())
+        );
+        assert!(structured_append
+            .symbols
+            .iter()
+            .all(|symbol| symbol.qr_code.is_some()));
+        assert_eq!(details.dimensions.modules, 25);
+        let error = service
+            .generate_qr_code(&content, &customization)
+            .err()
+            .unwrap();
+        assert!(
+            error
+                .to_string()
+                .contains("takes 3 structured append symbols"),
+            "{}",
+            error
+        );
+
+        // Tiled: two by two with a 4-module gap, in one image
+        customization.structured_append = Some(StructuredAppendOptions {
+            max_version: Some(2),
+            layout: AppendLayout::Tiled,
+            ..Default::default()
+        });
+        let generated = service.generate_qr_code(&content, &customization).unwrap();
+        let dimensions = &generated.details.dimensions;
+        assert_eq!((dimensions.modules, dimensions.rows), (54, 54));
+        assert_eq!(
+            generated
+                .details
+                .structured_append
+                .as_ref()
+                .unwrap()
+                .symbols[2]
+                .qr_code,
+            None
+        );
+        let image = image::load_from_memory(&generated.data).unwrap();
+        assert_eq!(
+            (image.width(), image.height()),
+            (dimensions.width, dimensions.height)
+        );
+    }
+
+    #[test]
+    fn test_transparency_per_format() {
+        let config = create_test_config();
+        let service = QrService::new(config);
+        let mut customization = create_test_customization();
+        customization.colors.background = "transparent".to_string();
+
+        // PNG keeps the alpha channel
+        customization.format = OutputFormat::Png;
+        let generated = service
+            .generate_qr_code("https://example.com", &customization)
+            .unwrap();
+        let image = image::load_from_memory(&generated.data).unwrap().to_rgba8();
+        assert_eq!(image.get_pixel(0, 0)[3], 0);
+
+        // JPEG needs a matte color
+        customization.format = OutputFormat::Jpeg;
+        let result = service.generate_qr_code("https://example.com", &customization);
+        assert!(matches!(result, Err(ApiError::ValidationError(_))));
+
+        customization.colors.backdrop = Some("#FFF59D".to_string());
+        let generated = service
+            .generate_qr_code("https://example.com", &customization)
+            .unwrap();
+        let image = image::load_from_memory(&generated.data).unwrap().to_rgb8();
+        let corner = image.get_pixel(0, 0);
+        assert!(corner[0] > 240 && corner[1] > 230 && corner[2] < 175);
+    }
+
+    #[test]
+    fn test_generate_webp_format() {
+        let config = create_test_config();
+        let service = QrService::new(config);
+        let mut customization = create_test_customization();
+        customization.format = OutputFormat::Webp;
+
+        // Lossless output decodes back to exactly two colors
+        let lossless = service
+            .generate_qr_code("https://example.com", &customization)
+            .unwrap();
+        assert_eq!(&lossless.data[..4], b"RIFF");
+        assert_eq!(&lossless.data[8..12], b"WEBP");
+        let image = image::load_from_memory(&lossless.data).unwrap().to_rgba8();
+        assert_eq!(image.dimensions(), (300, 300));
+        assert!(image
+            .pixels()
+            .all(|p| p.0 == [0, 0, 0, 255] || p.0 == [255, 255, 255, 255]));
+
+        customization.quality = Some(50);
+        let lossy = service
+            .generate_qr_code("https://example.com", &customization)
+            .unwrap();
+        assert_eq!(&lossy.data[8..12], b"WEBP");
+        assert_ne!(lossy.data, lossless.data);
+
+        let (data_url, _) = service
+            .generate_qr_base64("https://example.com", &customization)
+            .unwrap();
+        assert!(data_url.starts_with("data:image/webp;base64,"));
+    }
+
+    #[test]
+    fn test_generate_pdf_format() {
+        let config = create_test_config();
+        let service = QrService::new(config);
+        let mut customization = create_test_customization();
+        customization.format = OutputFormat::Pdf;
+
+        let generated = service
+            .generate_qr_code("https://example.com", &customization)
+            .unwrap();
+        assert!(generated.data.starts_with(b"%PDF-"));
+        assert!(generated.data.ends_with(b"%%EOF\n"));
+
+        let (data_url, _) = service
+            .generate_qr_base64("https://example.com", &customization)
+            .unwrap();
+        assert!(data_url.starts_with("data:application/pdf;base64,"));
+    }
+
+    #[test]
+    fn test_generate_eps_format() {
+        use crate::models::LogoOptions;
+
+        let config = create_test_config();
+        let service = QrService::new(config);
+        let mut customization = create_test_customization();
+        customization.format = OutputFormat::Eps;
+        customization.min_version = Some(6);
+        customization.logo = Some(LogoOptions {
+            data: BASE64.encode(r##"<svg xmlns="http://www.w3.org/2000/svg" width="4" height="4"><rect width="4" height="4" fill="#E53935"/></svg>"##),
+            ..Default::default()
+        });
+
+        let generated = service
+            .generate_qr_code("https://example.com", &customization)
+            .unwrap();
+        let eps = String::from_utf8(generated.data).unwrap();
+        assert!(eps.starts_with("%!PS-Adobe-3.0 EPSF-3.0"));
+        assert!(eps.contains("%%BoundingBox: 0 0 300 300"));
+        assert!(eps.contains("/ASCII85Decode filter /FlateDecode filter >> image"));
+    }
+
+    #[test]
+    fn test_png_uses_smallest_color_type() {
+        use crate::models::PngCompression;
+
+        let config = create_test_config();
+        let service = QrService::new(config);
+        let mut customization = create_test_customization();
+
+        // Black on white: 1-bit grayscale
+        let generated = service
+            .generate_qr_code("https://example.com", &customization)
+            .unwrap();
+        assert_eq!((generated.data[24], generated.data[25]), (1, 0));
+
+        // Two colors: 1-bit palette, at any compression
+        customization.colors = QrColors::new("#1A237E".to_string(), "#FFF8E1".to_string()).unwrap();
+        for compression in [PngCompression::Fast, PngCompression::Smallest] {
+            customization.png_compression = Some(compression);
+            let generated = service
+                .generate_qr_code("https://example.com", &customization)
+                .unwrap();
+            assert_eq!((generated.data[24], generated.data[25]), (1, 3));
+        }
+    }
+
+    #[test]
+    fn test_jpeg_quality_and_subsampling() {
+        use crate::models::ChromaSubsampling;
+
+        let config = create_test_config();
+        let service = QrService::new(config);
+        let mut customization = create_test_customization();
+        customization.format = OutputFormat::Jpeg;
+        customization.colors = QrColors::new("#1A237E".to_string(), "#FFF8E1".to_string()).unwrap();
+
+        let mut sizes = Vec::new();
+        for quality in [30, 95] {
+            customization.quality = Some(quality);
+            let generated = service
+                .generate_qr_code("https://example.com", &customization)
+                .unwrap();
+            assert!(generated.details.warnings.is_empty());
+            sizes.push(generated.data.len());
+        }
+        assert!(sizes[0] < sizes[1]);
+
+        // The first component's sampling factors in the SOF0 segment
+        let sampling = |data: &[u8]| {
+            let sof = data.windows(2).position(|w| w == [0xFF, 0xC0]).unwrap();
+            data[sof + 11]
+        };
+        let generated = service
+            .generate_qr_code("https://example.com", &customization)
+            .unwrap();
+        assert_eq!(sampling(&generated.data), 0x11);
+        customization.chroma_subsampling = Some(ChromaSubsampling::Ratio420);
+        let generated = service
+            .generate_qr_code("https://example.com", &customization)
+            .unwrap();
+        assert_eq!(sampling(&generated.data), 0x22);
+    }
+
+    #[test]
+    fn test_jpeg_warns_about_small_modules() {
+        let config = create_test_config();
+        let service = QrService::new(config);
+        let mut customization = create_test_customization();
+        customization.format = OutputFormat::Jpeg;
+        customization.size = QrSize::Custom(100);
+
+        let (_, details) = service
+            .generate_qr_base64("https://example.com", &customization)
+            .unwrap();
+        assert_eq!(details.warnings.len(), 1);
+        assert!(details.warnings[0].contains("3px"));
+
+        customization.format = OutputFormat::Png;
+        let (_, details) = service
+            .generate_qr_base64("https://example.com", &customization)
+            .unwrap();
+        assert!(details.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_generate_legacy_raster_formats() {
+        use crate::models::TiffCompression;
+
+        let config = create_test_config();
+        let service = QrService::new(config);
+        let mut customization = create_test_customization();
+        customization.size = QrSize::Custom(256);
+
+        for format in [
+            OutputFormat::Gif,
+            OutputFormat::Bmp,
+            OutputFormat::Tiff,
+            OutputFormat::Ico,
+        ] {
+            customization.format = format;
+            let generated = service
+                .generate_qr_code("https://example.com", &customization)
+                .unwrap();
+            let image = image::load_from_memory(&generated.data).unwrap().to_rgba8();
+            assert_eq!(image.dimensions(), (256, 256));
+            assert!(
+                image.pixels().all(|p| p[0] == 0 || p[0] == 255),
+                "{:?}",
+                customization.format
+            );
+        }
+
+        customization.format = OutputFormat::Tiff;
+        customization.tiff_compression = Some(TiffCompression::Group4);
+        let generated = service
+            .generate_qr_code("https://example.com", &customization)
+            .unwrap();
+        let mut decoder = tiff::decoder::Decoder::new(Cursor::new(&generated.data)).unwrap();
+        assert_eq!(decoder.get_tag_u32(Tag::Compression).unwrap(), 4);
+        assert_eq!(decoder.get_tag_u32(Tag::BitsPerSample).unwrap(), 1);
+        assert_eq!(decoder.dimensions().unwrap(), (256, 256));
+        // Runs of identical rows compress to a few bits each
+        assert!(generated.data.len() < 4096);
+    }
+
+    #[test]
+    fn test_print_size_sets_resolution() {
+        use crate::models::{LengthUnit, PrintSize};
+
+        let config = create_test_config();
+        let service = QrService::new(config);
+        let mut customization = create_test_customization();
+        // 30mm at 600 DPI is 708.66 pixels
+        customization.size = QrSize::Print(PrintSize {
+            width: 30.0,
+            unit: LengthUnit::Mm,
+            dpi: 600,
+        });
+        let mut generate = |format: OutputFormat| {
+            customization.format = format;
+            service
+                .generate_qr_code("https://example.com", &customization)
+                .unwrap()
+        };
+
+        let png = generate(OutputFormat::Png);
+        assert_eq!(png.details.dimensions.width, 709);
+
+        let svg = String::from_utf8(generate(OutputFormat::Svg).data).unwrap();
+        assert!(svg.contains(r#"width="30mm" height="30mm" viewBox="0 0 709 709""#));
+
+        let pdf = String::from_utf8_lossy(&generate(OutputFormat::Pdf).data).into_owned();
+        assert!(pdf.contains("/MediaBox [0 0 85.039 85.039]"));
+
+        let eps = String::from_utf8(generate(OutputFormat::Eps).data).unwrap();
+        assert!(eps.contains("%%BoundingBox: 0 0 86 86\n"));
+        assert!(eps.contains("%%HiResBoundingBox: 0 0 85.039 85.039\n"));
+
+        // JFIF APP0: units 1 (dots per inch), then the X and Y density
+        let jpeg = generate(OutputFormat::Jpeg).data;
+        let jfif = jpeg.windows(5).position(|w| w == b"JFIF\0").unwrap();
+        assert_eq!(&jpeg[jfif + 7..jfif + 12], &[1, 0x02, 0x58, 0x02, 0x58]);
+
+        let tiff = generate(OutputFormat::Tiff).data;
+        let mut decoder = tiff::decoder::Decoder::new(Cursor::new(tiff)).unwrap();
+        assert_eq!(
+            decoder.get_tag(Tag::XResolution).unwrap(),
+            tiff::decoder::ifd::Value::Rational(600, 1)
+        );
+    }
+
+    #[test]
+    fn test_caption_in_every_format() {
+        use crate::models::CaptionOptions;
+
+        let url = "https://example.com";
+        let mut customization = create_test_customization();
+        customization.caption = Some(CaptionOptions {
+            text: "example.com & more".to_string(),
+            ..Default::default()
+        });
+
+        let generated = generate_in(&IMAGE_FORMATS, url, &customization);
+        for (format, generated) in &generated {
+            let dimensions = &generated.details.dimensions;
+            assert_eq!(dimensions.width, 300, "{}", format);
+            assert!(dimensions.height > 300, "{}", format);
+        }
+
+        // The caption is drawn below the code in the foreground color
+        let image = image::load_from_memory(&generated[0].1.data)
+            .unwrap()
+            .to_rgba8();
+        assert!((300..image.height()).any(|y| (0..300).any(|x| image.get_pixel(x, y)[0] < 128)));
+
+        let svg = String::from_utf8_lossy(&generated[1].1.data);
+        assert!(svg.contains(">example.com &amp; more</text>"));
+        assert!(svg.contains(r#"text-anchor="middle""#));
+
+        let service = QrService::new(create_test_config());
+        customization.format = OutputFormat::Text;
+        assert!(service.generate_qr_code(url, &customization).is_err());
+
+        customization.format = OutputFormat::Ico;
+        customization.size = QrSize::Custom(256);
+        assert!(service.generate_qr_code(url, &customization).is_err());
+    }
+
+    #[test]
+    fn test_frame_in_every_format() {
+        use crate::models::{CaptionOptions, FrameOptions, FrameStyle};
+
+        let url = "https://example.com";
+        let mut customization = create_test_customization();
+
+        for style in [
+            FrameStyle::Banner,
+            FrameStyle::Bubble,
+            FrameStyle::Ticket,
+            FrameStyle::Phone,
+        ] {
+            customization.frame = Some(FrameOptions {
+                style,
+                ..Default::default()
+            });
+            for (format, generated) in generate_in(&IMAGE_FORMATS, url, &customization) {
+                let dimensions = &generated.details.dimensions;
+                assert_eq!(
+                    (dimensions.width, dimensions.height),
+                    (300, 300),
+                    "{:?} {}",
+                    style,
+                    format
+                );
+            }
+        }
+
+        // The banner is drawn in the foreground with the text knocked out in
+        // the background color, in full color and two-color output alike
+        customization.frame = Some(FrameOptions::default());
+        let formats = [OutputFormat::Png, OutputFormat::Gif];
+        for (format, generated) in generate_in(&formats, url, &customization) {
+            let image = image::load_from_memory(&generated.data).unwrap().to_rgba8();
+            let banner: Vec<_> = (250..295)
+                .flat_map(|y| (20..280).map(move |x| (x, y)))
+                .collect();
+            assert!(
+                banner.iter().any(|&(x, y)| image.get_pixel(x, y)[0] < 128),
+                "{}",
+                format
+            );
+            assert!(
+                banner.iter().any(|&(x, y)| image.get_pixel(x, y)[0] > 128),
+                "{}",
+                format
+            );
+        }
+
+        // A caption still extends the framed canvas
+        customization.caption = Some(CaptionOptions {
+            text: "example.com".to_string(),
+            ..Default::default()
+        });
+        let generated = &generate_in(&[OutputFormat::Gif], url, &customization)[0].1;
+        assert!(generated.details.dimensions.height > 300);
+
+        customization.format = OutputFormat::Ascii;
+        let service = QrService::new(create_test_config());
+        assert!(service.generate_qr_code(url, &customization).is_err());
+    }
+
+    #[test]
+    fn test_gif_transparent_background() {
+        let config = create_test_config();
+        let service = QrService::new(config);
+        let mut customization = create_test_customization();
+        customization.format = OutputFormat::Gif;
+        customization.colors =
+            QrColors::new("#1A237E".to_string(), "transparent".to_string()).unwrap();
+
+        let generated = service
+            .generate_qr_code("https://example.com", &customization)
+            .unwrap();
+        let image = image::load_from_memory(&generated.data).unwrap().to_rgba8();
+        assert_eq!(image.get_pixel(0, 0)[3], 0);
+        assert!(image.pixels().any(|p| p.0 == [0x1A, 0x23, 0x7E, 255]));
+    }
+
+    #[test]
+    fn test_generate_text_formats() {
+        let config = create_test_config();
+        let service = QrService::new(config);
+        let mut customization = create_test_customization();
+        customization.format = OutputFormat::Text;
+
+        // The raw string is returned rather than a data URL
+        let (text, details) = service
+            .generate_qr_base64("https://example.com", &customization)
+            .unwrap();
+        let dimensions = details.dimensions;
+        assert!(text.contains('█'));
+        assert_eq!(text.lines().count() as u32, dimensions.height);
+        assert_eq!(
+            dimensions.width,
+            dimensions.modules + 2 * dimensions.quiet_zone
+        );
+
+        customization.format = OutputFormat::Ascii;
+        let (text, _) = service
+            .generate_qr_base64("https://example.com", &customization)
+            .unwrap();
+        assert!(text.is_ascii() && text.contains("##"));
+
+        customization.format = OutputFormat::Ansi;
+        let (text, _) = service
+            .generate_qr_base64("https://example.com", &customization)
+            .unwrap();
+        assert!(text.starts_with("\x1b[38;2;"));
+    }
+
     #[test]
     fn test_error_correction_levels() {
         let config = create_test_config();
-        let service = QrServiceV2::new(config);
+        let service = QrService::new(config);
         
         for ec_level in [
             ErrorCorrectionLevel::L,
@@ -262,7 +1884,7 @@ mod tests {
             let mut customization = create_test_customization();
             customization.error_correction = ec_level;
             
-            let result = service.generate_qr_code_v2("https://example.com", &customization);
+            let result = service.generate_qr_code("https://example.com", &customization);
             assert!(result.is_ok());
         }
     }
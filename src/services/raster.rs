@@ -0,0 +1,368 @@
+use image::{Rgba, RgbaImage};
+
+use super::{
+    layout::QrLayout,
+    paint::{GradientSampler, Paint},
+    shapes::{Layer, Shape},
+};
+use crate::models::hex_to_rgba;
+
+/// Samples per pixel edge used to anti-alias curved shapes.
+const SUBSAMPLES: u32 = 4;
+const FULL_COVERAGE: u16 = u16::MAX;
+
+/// Per-pixel coverage of a set of shapes.
+///
+/// Each pixel keeps one bit per subsample, and shapes are OR-ed in, so
+/// shapes that touch or overlap merge without seams along shared edges.
+pub struct CoverageMask {
+    width: u32,
+    height: u32,
+    samples: Vec<u16>,
+    /// Indices of the pixels with any coverage, so compositing sparse
+    /// layers such as halftone dots skips the rest of the canvas.
+    touched: Vec<usize>,
+}
+
+impl CoverageMask {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            samples: vec![0; (width * height) as usize],
+            touched: Vec::new(),
+        }
+    }
+
+    /// Adds a shape given in module coordinates, placed with `layout`.
+    pub fn fill_shape(&mut self, shape: &Shape, layout: &QrLayout) {
+        let (ox, oy) = layout.origin();
+        let (ox, oy) = (ox as f64, oy as f64);
+        let scale = layout.module_size as f64;
+        let (x, y, width, height) = shape.bounds();
+
+        let left = (ox + x * scale).floor().max(0.0) as u32;
+        let top = (oy + y * scale).floor().max(0.0) as u32;
+        let right = ((ox + (x + width) * scale).ceil().max(0.0) as u32).min(self.width);
+        let bottom = ((oy + (y + height) * scale).ceil().max(0.0) as u32).min(self.height);
+        let exact = shape.is_axis_aligned_rect();
+
+        for py in top..bottom {
+            for px in left..right {
+                let index = (py * self.width + px) as usize;
+                if self.samples[index] == FULL_COVERAGE {
+                    continue;
+                }
+
+                // Plain rectangles covering the whole pixel need no sampling
+                if exact {
+                    let (mx0, my0) = ((px as f64 - ox) / scale, (py as f64 - oy) / scale);
+                    let (mx1, my1) = (
+                        (px as f64 + 1.0 - ox) / scale,
+                        (py as f64 + 1.0 - oy) / scale,
+                    );
+                    if mx0 >= x && my0 >= y && mx1 <= x + width && my1 <= y + height {
+                        self.add(index, FULL_COVERAGE);
+                        continue;
+                    }
+                }
+
+                let mut bits = 0u16;
+                for sy in 0..SUBSAMPLES {
+                    for sx in 0..SUBSAMPLES {
+                        let sample_x = px as f64 + (sx as f64 + 0.5) / SUBSAMPLES as f64;
+                        let sample_y = py as f64 + (sy as f64 + 0.5) / SUBSAMPLES as f64;
+                        if shape.contains((sample_x - ox) / scale, (sample_y - oy) / scale) {
+                            bits |= 1 << (sy * SUBSAMPLES + sx);
+                        }
+                    }
+                }
+                self.add(index, bits);
+            }
+        }
+    }
+
+    fn add(&mut self, index: usize, bits: u16) {
+        if bits == 0 {
+            return;
+        }
+        if self.samples[index] == 0 {
+            self.touched.push(index);
+        }
+        self.samples[index] |= bits;
+    }
+
+    /// Coverage of the pixel at (x, y) between 0.0 and 1.0.
+    pub fn coverage(&self, x: u32, y: u32) -> f64 {
+        let bits = self.samples[(y * self.width + x) as usize];
+        bits.count_ones() as f64 / (SUBSAMPLES * SUBSAMPLES) as f64
+    }
+
+    /// Blends `color` over the image wherever the mask has coverage.
+    pub fn composite(&self, image: &mut RgbaImage, color: Rgba<u8>) {
+        self.composite_with(image, |_, _| color);
+    }
+
+    /// Blends the color returned for each covered pixel over the image.
+    pub fn composite_with(&self, image: &mut RgbaImage, color_at: impl Fn(u32, u32) -> Rgba<u8>) {
+        for &index in &self.touched {
+            let (x, y) = (index as u32 % self.width, index as u32 / self.width);
+            let pixel = image.get_pixel_mut(x, y);
+            *pixel = blend(*pixel, color_at(x, y), self.coverage(x, y));
+        }
+    }
+}
+
+/// Composites `top` over `image` with its top-left corner at (x, y),
+/// clipping anything outside the image.
+pub fn overlay(image: &mut RgbaImage, top: &RgbaImage, x: i64, y: i64) {
+    for (tx, ty, pixel) in top.enumerate_pixels() {
+        let (px, py) = (x + tx as i64, y + ty as i64);
+        if px >= 0 && py >= 0 && px < image.width() as i64 && py < image.height() as i64 {
+            let base = image.get_pixel_mut(px as u32, py as u32);
+            *base = blend(*base, *pixel, 1.0);
+        }
+    }
+}
+
+/// Composites `top`, scaled by `coverage`, over `base` ("source over"),
+/// so translucent colors and transparent backgrounds blend correctly.
+fn blend(base: Rgba<u8>, top: Rgba<u8>, coverage: f64) -> Rgba<u8> {
+    let top_alpha = top[3] as f64 / 255.0 * coverage;
+    let base_alpha = base[3] as f64 / 255.0;
+    let alpha = top_alpha + base_alpha * (1.0 - top_alpha);
+    if alpha <= 0.0 {
+        return Rgba([0, 0, 0, 0]);
+    }
+
+    let mix = |b: u8, t: u8| {
+        ((t as f64 * top_alpha + b as f64 * base_alpha * (1.0 - top_alpha)) / alpha).round() as u8
+    };
+    Rgba([
+        mix(base[0], top[0]),
+        mix(base[1], top[1]),
+        mix(base[2], top[2]),
+        (alpha * 255.0).round() as u8,
+    ])
+}
+
+/// Renders the layers in order into an RGBA image of exactly
+/// `layout.width` by `layout.height` pixels.
+pub fn render_raster(
+    layers: &[Layer],
+    layout: &QrLayout,
+    background: &str,
+) -> Result<RgbaImage, String> {
+    let mut image = RgbaImage::from_pixel(layout.width, layout.height, rgba(background)?);
+    draw_layers(&mut image, layers, layout)?;
+    Ok(image)
+}
+
+/// Draws the layers in order onto an image laid out by `layout`.
+pub fn draw_layers(
+    image: &mut RgbaImage,
+    layers: &[Layer],
+    layout: &QrLayout,
+) -> Result<(), String> {
+    for layer in layers {
+        let mut mask = CoverageMask::new(layout.width, layout.height);
+        for shape in &layer.shapes {
+            mask.fill_shape(shape, layout);
+        }
+        match &layer.paint {
+            Paint::Solid(color) => mask.composite(image, rgba(color)?),
+            Paint::Gradient(gradient) => {
+                let sampler =
+                    GradientSampler::new(gradient, (layout.modules as f64, layout.rows as f64))?;
+                let (ox, oy) = layout.origin();
+                let scale = layout.module_size as f64;
+                mask.composite_with(image, |x, y| {
+                    // Sample at the pixel center, in module coordinates
+                    let (r, g, b, a) = sampler.color_at(
+                        (x as f64 + 0.5 - ox as f64) / scale,
+                        (y as f64 + 0.5 - oy as f64) / scale,
+                    );
+                    Rgba([r, g, b, a])
+                });
+            }
+            Paint::Image(fill) => {
+                let (ox, oy) = layout.origin();
+                let scale = layout.module_size as f64;
+                mask.composite_with(image, |x, y| {
+                    let (r, g, b, a) = fill.color_at(
+                        (x as f64 + 0.5 - ox as f64) / scale,
+                        (y as f64 + 0.5 - oy as f64) / scale,
+                    );
+                    Rgba([r, g, b, a])
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Renders which pixels are covered by the symbol's shapes, ignoring their
+/// colors, for two-color outputs. Layers painted in the `background` color,
+/// such as text on a frame, clear the pixels they cover instead. Anti-aliased
+/// edges count as covered from half coverage up.
+pub fn render_bilevel(
+    layers: &[Layer],
+    layout: &QrLayout,
+    background: &str,
+) -> Result<Vec<bool>, String> {
+    let ink: Vec<Layer> = layers
+        .iter()
+        .map(|layer| {
+            let paper = matches!(&layer.paint, Paint::Solid(color) if color.eq_ignore_ascii_case(background));
+            Layer {
+                paint: Paint::Solid(if paper { "#FFFFFF" } else { "#000000" }.to_string()),
+                shapes: layer.shapes.clone(),
+            }
+        })
+        .collect();
+    let image = render_raster(&ink, layout, "#FFFFFF")?;
+
+    Ok(image.pixels().map(|pixel| pixel[0] < 128).collect())
+}
+
+fn rgba(hex: &str) -> Result<Rgba<u8>, String> {
+    let (r, g, b, a) = hex_to_rgba(hex)?;
+    Ok(Rgba([r, g, b, a]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{EyeStyle, ModuleStyle, QrColors};
+    use crate::services::{matrix::QrMatrix, shapes::symbol_layers};
+    use qrcode::{EcLevel, QrCode, Version};
+
+    const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+    const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+    fn matrix() -> QrMatrix {
+        let qr_code = QrCode::with_version(b"hello", Version::Normal(1), EcLevel::M).unwrap();
+        QrMatrix::from_qr_code(&qr_code, 4)
+    }
+
+    fn render(style: ModuleStyle, colors: &QrColors, size: u32) -> RgbaImage {
+        let matrix = matrix();
+        let layout = QrLayout::compute(&matrix, size).unwrap();
+        let layers = symbol_layers(&matrix, &style, &EyeStyle::default(), colors);
+        render_raster(&layers, &layout, &colors.background).unwrap()
+    }
+
+    #[test]
+    fn test_render_raster_exact_size_and_padding() {
+        let image = render(ModuleStyle::Square, &QrColors::default(), 300);
+
+        assert_eq!(image.dimensions(), (300, 300));
+        // Padding and quiet zone are background, the finder corner is dark
+        assert_eq!(*image.get_pixel(44, 44), WHITE);
+        assert_eq!(*image.get_pixel(45, 45), BLACK);
+        assert_eq!(*image.get_pixel(299, 299), WHITE);
+    }
+
+    #[test]
+    fn test_square_style_has_no_intermediate_colors() {
+        let image = render(ModuleStyle::Square, &QrColors::default(), 290);
+
+        assert!(image.pixels().all(|p| *p == BLACK || *p == WHITE));
+    }
+
+    #[test]
+    fn test_bilevel_ignores_colors() {
+        let colors = QrColors {
+            foreground: "#1A237E".to_string(),
+            background: "transparent".to_string(),
+            ..Default::default()
+        };
+        let matrix = matrix();
+        let layout = QrLayout::compute(&matrix, 290).unwrap();
+        let layers = symbol_layers(&matrix, &ModuleStyle::Square, &EyeStyle::default(), &colors);
+        let bilevel = render_bilevel(&layers, &layout, &colors.background).unwrap();
+
+        let reference = render(ModuleStyle::Square, &QrColors::default(), 290);
+        assert!(reference
+            .pixels()
+            .zip(&bilevel)
+            .all(|(pixel, &ink)| ink == (*pixel == BLACK)));
+    }
+
+    #[test]
+    fn test_dots_are_anti_aliased() {
+        let image = render(ModuleStyle::Dots, &QrColors::default(), 290);
+
+        assert!(image.pixels().any(|p| *p != BLACK && *p != WHITE));
+    }
+
+    #[test]
+    fn test_eye_colors_are_painted() {
+        use crate::models::EyeColors;
+
+        let colors = QrColors {
+            eyes: vec![EyeColors {
+                outer: Some("#FF0000".to_string()),
+                inner: Some("#0000FF".to_string()),
+            }],
+            ..Default::default()
+        };
+        // 290px for 29 modules: 10px per module, no padding, 40px quiet zone
+        let image = render(ModuleStyle::Square, &colors, 290);
+
+        assert_eq!(*image.get_pixel(45, 45), Rgba([255, 0, 0, 255]));
+        assert_eq!(*image.get_pixel(75, 75), Rgba([0, 0, 255, 255]));
+    }
+
+    #[test]
+    fn test_gradient_is_painted_across_symbol() {
+        use crate::models::{Gradient, GradientKind, GradientStop};
+
+        let colors = QrColors {
+            gradient: Some(Gradient {
+                kind: GradientKind::Linear,
+                angle: 0.0,
+                stops: vec![
+                    GradientStop {
+                        offset: None,
+                        color: "#000000".to_string(),
+                    },
+                    GradientStop {
+                        offset: None,
+                        color: "#0000FF".to_string(),
+                    },
+                ],
+            }),
+            ..Default::default()
+        };
+        let image = render(ModuleStyle::Square, &colors, 290);
+
+        // Left finder edge starts near the first stop, right finder edge
+        // ends near the last one
+        assert!(image.get_pixel(45, 45)[2] < 20);
+        assert!(image.get_pixel(244, 45)[2] > 235);
+        assert_eq!(image.get_pixel(244, 45)[0], 0);
+    }
+
+    #[test]
+    fn test_transparent_background_keeps_alpha() {
+        let colors = QrColors {
+            background: "transparent".to_string(),
+            ..Default::default()
+        };
+        let image = render(ModuleStyle::Dots, &colors, 290);
+
+        assert_eq!(*image.get_pixel(0, 0), Rgba([0, 0, 0, 0]));
+        assert_eq!(*image.get_pixel(45, 45), BLACK);
+        // Anti-aliased edges fade out through alpha, not towards another color
+        assert!(image.pixels().any(|p| p[3] > 0 && p[3] < 255 && p[0] == 0));
+    }
+
+    #[test]
+    fn test_blend_translucent_color() {
+        let blended = blend(WHITE, Rgba([0, 0, 0, 128]), 1.0);
+        assert_eq!(blended, Rgba([127, 127, 127, 255]));
+        assert_eq!(blend(WHITE, BLACK, 0.5), Rgba([128, 128, 128, 255]));
+    }
+}
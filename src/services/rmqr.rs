@@ -0,0 +1,798 @@
+use qrcode::ec::create_error_correction_code;
+
+use super::{
+    encoder::{EncodedSymbol, Preamble},
+    matrix::QrMatrix,
+};
+use crate::{
+    errors::ApiError,
+    models::{shift_jis_kanji, EncodingMode, ErrorCorrectionLevel, QrCustomization, Segment},
+};
+
+/// Characters of the alphanumeric mode in the order of their values.
+const ALPHANUMERIC: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// Generator polynomial of the BCH(18, 6) code protecting the format
+/// information.
+const FORMAT_GENERATOR: u32 = 0b1_1111_0010_0101;
+
+/// Masks of the format information next to the finder pattern and next to
+/// the finder sub-pattern.
+const FORMAT_MASKS: [u32; 2] = [0b01_1111_1010_1011_0010, 0b10_0000_1010_0111_1011];
+
+/// Error correction blocks as `(blocks, codewords, data codewords)` groups.
+type Blocks = &'static [(usize, usize, usize)];
+
+/// An rMQR size, R{rows}x{columns}.
+struct RmqrSize {
+    rows: usize,
+    columns: usize,
+    /// Length of the character count of numeric, alphanumeric, byte and
+    /// Kanji segments, in bits.
+    count_bits: [usize; 4],
+    /// Error correction blocks at levels M and H.
+    blocks: [Blocks; 2],
+}
+
+/// Every rMQR size in the order of its version indicator.
+#[rustfmt::skip]
+const SIZES: [RmqrSize; 32] = [
+    RmqrSize { rows: 7, columns: 43, count_bits: [4, 3, 3, 2], blocks: [&[(1, 13, 6)], &[(1, 13, 3)]] },
+    RmqrSize { rows: 7, columns: 59, count_bits: [5, 5, 4, 3], blocks: [&[(1, 21, 12)], &[(1, 21, 7)]] },
+    RmqrSize { rows: 7, columns: 77, count_bits: [6, 5, 5, 4], blocks: [&[(1, 32, 20)], &[(1, 32, 10)]] },
+    RmqrSize { rows: 7, columns: 99, count_bits: [7, 6, 5, 5], blocks: [&[(1, 44, 28)], &[(1, 44, 14)]] },
+    RmqrSize { rows: 7, columns: 139, count_bits: [7, 6, 6, 5], blocks: [&[(1, 68, 44)], &[(2, 34, 12)]] },
+    RmqrSize { rows: 9, columns: 43, count_bits: [5, 5, 4, 3], blocks: [&[(1, 21, 12)], &[(1, 21, 7)]] },
+    RmqrSize { rows: 9, columns: 59, count_bits: [6, 5, 5, 4], blocks: [&[(1, 33, 21)], &[(1, 33, 11)]] },
+    RmqrSize { rows: 9, columns: 77, count_bits: [7, 6, 5, 5], blocks: [&[(1, 49, 31)], &[(1, 24, 8), (1, 25, 9)]] },
+    RmqrSize { rows: 9, columns: 99, count_bits: [7, 6, 6, 5], blocks: [&[(1, 66, 42)], &[(2, 33, 11)]] },
+    RmqrSize { rows: 9, columns: 139, count_bits: [8, 7, 6, 6], blocks: [&[(1, 49, 31), (1, 50, 32)], &[(3, 33, 11)]] },
+    RmqrSize { rows: 11, columns: 27, count_bits: [4, 4, 3, 2], blocks: [&[(1, 15, 7)], &[(1, 15, 5)]] },
+    RmqrSize { rows: 11, columns: 43, count_bits: [6, 5, 5, 4], blocks: [&[(1, 31, 19)], &[(1, 31, 11)]] },
+    RmqrSize { rows: 11, columns: 59, count_bits: [7, 6, 5, 5], blocks: [&[(1, 47, 31)], &[(1, 23, 7), (1, 24, 8)]] },
+    RmqrSize { rows: 11, columns: 77, count_bits: [7, 6, 6, 5], blocks: [&[(1, 67, 43)], &[(1, 33, 11), (1, 34, 12)]] },
+    RmqrSize { rows: 11, columns: 99, count_bits: [8, 7, 6, 6], blocks: [&[(1, 44, 28), (1, 45, 29)], &[(1, 44, 14), (1, 45, 15)]] },
+    RmqrSize { rows: 11, columns: 139, count_bits: [8, 7, 7, 6], blocks: [&[(2, 66, 42)], &[(3, 44, 14)]] },
+    RmqrSize { rows: 13, columns: 27, count_bits: [5, 5, 4, 3], blocks: [&[(1, 21, 12)], &[(1, 21, 7)]] },
+    RmqrSize { rows: 13, columns: 43, count_bits: [6, 6, 5, 5], blocks: [&[(1, 41, 27)], &[(1, 41, 13)]] },
+    RmqrSize { rows: 13, columns: 59, count_bits: [7, 6, 6, 5], blocks: [&[(1, 60, 38)], &[(2, 30, 10)]] },
+    RmqrSize { rows: 13, columns: 77, count_bits: [7, 7, 6, 5], blocks: [&[(1, 42, 26), (1, 43, 27)], &[(1, 42, 14), (1, 43, 15)]] },
+    RmqrSize { rows: 13, columns: 99, count_bits: [8, 7, 7, 6], blocks: [&[(1, 56, 36), (1, 57, 37)], &[(1, 37, 11), (2, 38, 12)]] },
+    RmqrSize { rows: 13, columns: 139, count_bits: [8, 8, 7, 7], blocks: [&[(2, 55, 35), (1, 56, 36)], &[(2, 41, 13), (2, 42, 14)]] },
+    RmqrSize { rows: 15, columns: 43, count_bits: [7, 6, 6, 5], blocks: [&[(1, 51, 33)], &[(1, 25, 7), (1, 26, 8)]] },
+    RmqrSize { rows: 15, columns: 59, count_bits: [7, 7, 6, 5], blocks: [&[(1, 74, 48)], &[(2, 37, 13)]] },
+    RmqrSize { rows: 15, columns: 77, count_bits: [8, 7, 7, 6], blocks: [&[(1, 51, 33), (1, 52, 34)], &[(2, 34, 10), (1, 35, 11)]] },
+    RmqrSize { rows: 15, columns: 99, count_bits: [8, 7, 7, 6], blocks: [&[(2, 68, 44)], &[(4, 34, 12)]] },
+    RmqrSize { rows: 15, columns: 139, count_bits: [9, 8, 7, 7], blocks: [&[(2, 66, 42), (1, 67, 43)], &[(1, 39, 13), (4, 40, 14)]] },
+    RmqrSize { rows: 17, columns: 43, count_bits: [7, 6, 6, 5], blocks: [&[(1, 61, 39)], &[(1, 30, 10), (1, 31, 11)]] },
+    RmqrSize { rows: 17, columns: 59, count_bits: [8, 7, 6, 6], blocks: [&[(2, 44, 28)], &[(2, 44, 14)]] },
+    RmqrSize { rows: 17, columns: 77, count_bits: [8, 7, 7, 6], blocks: [&[(2, 61, 39)], &[(1, 40, 12), (2, 41, 13)]] },
+    RmqrSize { rows: 17, columns: 99, count_bits: [8, 8, 7, 6], blocks: [&[(2, 53, 33), (1, 54, 34)], &[(4, 40, 14)]] },
+    RmqrSize { rows: 17, columns: 139, count_bits: [9, 8, 8, 7], blocks: [&[(4, 58, 38)], &[(2, 38, 12), (4, 39, 13)]] },
+];
+
+impl RmqrSize {
+    fn name(&self) -> String {
+        format!("R{}x{}", self.rows, self.columns)
+    }
+
+    /// Columns of the alignment patterns, each joined to its partner on the
+    /// opposite edge by a vertical timing pattern.
+    fn alignment_columns(&self) -> &'static [usize] {
+        match self.columns {
+            43 => &[21],
+            59 => &[19, 39],
+            77 => &[25, 51],
+            99 => &[23, 49, 75],
+            139 => &[27, 55, 83, 111],
+            _ => &[],
+        }
+    }
+
+    /// Encodes the segments into the final codeword sequence at level H
+    /// if `high` is set, otherwise M: data, terminator and padding split
+    /// into blocks, each block's error correction appended, and both
+    /// interleaved. The data starts with the charset's ECI designator or
+    /// the GS1 FNC1 indicator if asked for. `None` if the segments do not
+    /// fit.
+    fn codewords(&self, segments: &[Segment], high: bool, preamble: Preamble) -> Option<Vec<u8>> {
+        let blocks = self.blocks[high as usize];
+        let capacity: usize = blocks.iter().map(|&(count, _, data)| count * data).sum();
+
+        let mut bits = Bits::default();
+        if let Some(charset) = preamble.charset {
+            // Every supported assignment number fits the one-byte form
+            bits.push(0b111, 3);
+            bits.push(charset.eci(), 8);
+        }
+        if preamble.gs1 {
+            bits.push(0b101, 3);
+        }
+        for segment in segments {
+            bits.push_segment(segment, &self.count_bits, preamble)?;
+        }
+        if bits.0.len() > capacity * 8 {
+            return None;
+        }
+        // The terminator is cut short when the symbol is full
+        let terminator = (capacity * 8 - bits.0.len()).min(3);
+        bits.push(0, terminator);
+        bits.push(0, (8 - bits.0.len() % 8) % 8);
+        let mut data: Vec<u8> = bits
+            .0
+            .chunks(8)
+            .map(|byte| byte.iter().fold(0, |acc, &bit| acc << 1 | bit as u8))
+            .collect();
+        let padding = capacity - data.len();
+        data.extend([0xEC, 0x11].into_iter().cycle().take(padding));
+
+        let mut rest = &data[..];
+        let mut split = Vec::new();
+        for &(count, codewords, data) in blocks {
+            for _ in 0..count {
+                let (block, tail) = rest.split_at(data);
+                split.push((block, create_error_correction_code(block, codewords - data)));
+                rest = tail;
+            }
+        }
+
+        let longest = split
+            .iter()
+            .map(|(block, _)| block.len())
+            .max()
+            .unwrap_or(0);
+        let mut interleaved = Vec::new();
+        for index in 0..longest {
+            interleaved.extend(split.iter().filter_map(|(block, _)| block.get(index)));
+        }
+        for index in 0..split[0].1.len() {
+            interleaved.extend(split.iter().map(|(_, ec)| ec[index]));
+        }
+        Some(interleaved)
+    }
+}
+
+/// Data bit stream, most significant bit first.
+#[derive(Default)]
+struct Bits(Vec<bool>);
+
+impl Bits {
+    fn push(&mut self, value: u32, length: usize) {
+        self.0
+            .extend((0..length).rev().map(|bit| value >> bit & 1 == 1));
+    }
+
+    /// Appends the mode indicator, character count and data of a segment,
+    /// with byte segments in the charset or else UTF-8. `None` if the count
+    /// does not fit its field or a character does not fit the mode.
+    fn push_segment(
+        &mut self,
+        segment: &Segment,
+        count_bits: &[usize; 4],
+        preamble: Preamble,
+    ) -> Option<()> {
+        let text = &preamble.escape(segment);
+        let bytes = match (segment.mode, preamble.charset) {
+            (EncodingMode::Auto | EncodingMode::Byte, Some(charset)) => {
+                charset.encode(text).ok()?
+            }
+            _ => text.as_bytes().to_vec(),
+        };
+        let (indicator, count_bits, count) = match segment.mode {
+            EncodingMode::Numeric => (0b001, count_bits[0], text.len()),
+            EncodingMode::Alphanumeric => (0b010, count_bits[1], text.len()),
+            EncodingMode::Auto | EncodingMode::Byte => (0b011, count_bits[2], bytes.len()),
+            EncodingMode::Kanji => (0b100, count_bits[3], text.chars().count()),
+        };
+        if count >> count_bits != 0 {
+            return None;
+        }
+        self.push(indicator, 3);
+        self.push(count as u32, count_bits);
+
+        match segment.mode {
+            EncodingMode::Numeric => {
+                for group in text.as_bytes().chunks(3) {
+                    let value = std::str::from_utf8(group).ok()?.parse().ok()?;
+                    self.push(value, [0, 4, 7, 10][group.len()]);
+                }
+            }
+            EncodingMode::Alphanumeric => {
+                for pair in text.as_bytes().chunks(2) {
+                    let mut value = 0;
+                    for &byte in pair {
+                        value = value * 45 + ALPHANUMERIC.find(byte as char)? as u32;
+                    }
+                    self.push(value, if pair.len() == 2 { 11 } else { 6 });
+                }
+            }
+            EncodingMode::Auto | EncodingMode::Byte => {
+                for &byte in &bytes {
+                    self.push(byte as u32, 8);
+                }
+            }
+            EncodingMode::Kanji => {
+                for c in text.chars() {
+                    let code = shift_jis_kanji(c)? as u32;
+                    let offset = code - if code <= 0x9FFC { 0x8140 } else { 0xC140 };
+                    self.push((offset >> 8) * 0xC0 + (offset & 0xFF), 13);
+                }
+            }
+        }
+        Some(())
+    }
+}
+
+/// Modules of a symbol being drawn, row by row.
+struct Grid {
+    width: usize,
+    height: usize,
+    modules: Vec<bool>,
+    functional: Vec<bool>,
+}
+
+impl Grid {
+    fn new(size: &RmqrSize) -> Self {
+        let count = size.columns * size.rows;
+        Self {
+            width: size.columns,
+            height: size.rows,
+            modules: vec![false; count],
+            functional: vec![false; count],
+        }
+    }
+
+    /// Draws a function pattern module.
+    fn set(&mut self, x: usize, y: usize, dark: bool) {
+        let index = y * self.width + x;
+        self.modules[index] = dark;
+        self.functional[index] = true;
+    }
+
+    fn draw_function_patterns(&mut self, size: &RmqrSize, format: u32) {
+        let (width, height) = (self.width, self.height);
+
+        // Timing patterns run around the whole edge
+        for x in 0..width {
+            self.set(x, 0, x % 2 == 0);
+            self.set(x, height - 1, x % 2 == 0);
+        }
+        for y in 0..height {
+            self.set(0, y, y % 2 == 0);
+            self.set(width - 1, y, y % 2 == 0);
+        }
+
+        // Alignment patterns on the top and bottom edges, joined by
+        // vertical timing patterns
+        for &column in size.alignment_columns() {
+            for y in 3..height - 3 {
+                self.set(column, y, y % 2 == 0);
+            }
+            for (dx, dy) in (0..3).flat_map(|dy| (0..3).map(move |dx| (dx, dy))) {
+                let ring = (dx, dy) != (1, 1);
+                self.set(column + dx - 1, dy, ring);
+                self.set(column + dx - 1, height - 3 + dy, ring);
+            }
+        }
+
+        // Finder pattern with its separator, which the edge of R7 cuts off
+        for y in 0..height.min(8) {
+            for x in 0..8usize {
+                let ring = x.abs_diff(3).max(y.abs_diff(3));
+                self.set(x, y, ring != 2 && ring < 4);
+            }
+        }
+
+        // Finder sub-pattern in the bottom-right corner
+        for y in 0..5usize {
+            for x in 0..5usize {
+                let ring = x.abs_diff(2).max(y.abs_diff(2));
+                self.set(width - 5 + x, height - 5 + y, ring != 1);
+            }
+        }
+
+        // Corner finder patterns in the other two corners; below the finder
+        // of R9 only the bottom edge is left for one
+        self.set(width - 2, 0, true);
+        self.set(width - 1, 1, true);
+        self.set(width - 2, 1, false);
+        self.set(1, height - 1, true);
+        if height > 9 {
+            self.set(0, height - 2, true);
+            self.set(1, height - 2, false);
+        }
+
+        // Format information beside the finder and beside the sub-pattern,
+        // least significant bit first
+        let [finder_side, sub_side] = FORMAT_MASKS.map(|mask| format ^ mask);
+        for bit in 0..18 {
+            let (x, y) = if bit < 15 {
+                (8 + bit / 5, 1 + bit % 5)
+            } else {
+                (11, bit - 14)
+            };
+            self.set(x, y, finder_side >> bit & 1 == 1);
+            let (x, y) = if bit < 15 {
+                (width - 8 + bit / 5, height - 6 + bit % 5)
+            } else {
+                (width - 20 + bit, height - 6)
+            };
+            self.set(x, y, sub_side >> bit & 1 == 1);
+        }
+    }
+
+    /// Places the codewords in two-module columns zigzagging up and down
+    /// from the bottom-right, skipping function patterns, and applies the
+    /// data mask. Modules left over after the last codeword are remainder
+    /// bits and start light.
+    fn place_data(&mut self, codewords: &[u8]) {
+        let mut bits = codewords
+            .iter()
+            .flat_map(|&codeword| (0..8).rev().map(move |bit| codeword >> bit & 1 == 1))
+            .chain(std::iter::repeat(false));
+
+        let mut upward = true;
+        for right in (1..self.width - 1).rev().step_by(2) {
+            for step in 0..self.height {
+                let y = if upward { self.height - 1 - step } else { step };
+                for x in [right, right - 1] {
+                    let index = y * self.width + x;
+                    if !self.functional[index] {
+                        let masked = (y / 2 + x / 3) % 2 == 0;
+                        self.modules[index] = bits.next().unwrap_or_default() != masked;
+                    }
+                }
+            }
+            upward = !upward;
+        }
+    }
+}
+
+/// Format information of the size at `index` and the error correction
+/// level, with its BCH check bits but before masking.
+fn format_information(index: usize, high: bool) -> u32 {
+    let data = (high as u32) << 5 | index as u32;
+    let mut remainder = data << 12;
+    for bit in (12..18).rev() {
+        if remainder >> bit & 1 == 1 {
+            remainder ^= FORMAT_GENERATOR << (bit - 12);
+        }
+    }
+    data << 12 | remainder
+}
+
+/// Encodes `content` into an rMQR symbol, in the fixed segments if given.
+///
+/// The size is the one set by `rows` and `columns`, or else the smallest
+/// rectangle by area that fits within them and `max_rows`, the shorter one
+/// on a tie.
+pub fn encode_rmqr(
+    content: &str,
+    fixed: Option<&[Segment]>,
+    customization: &QrCustomization,
+) -> Result<EncodedSymbol, ApiError> {
+    let level = customization.effective_error_correction();
+    let high = matches!(level, ErrorCorrectionLevel::H);
+
+    let allowed = |size: &RmqrSize| {
+        customization
+            .rows
+            .is_none_or(|rows| size.rows == rows as usize)
+            && customization
+                .max_rows
+                .is_none_or(|rows| size.rows <= rows as usize)
+            && customization
+                .columns
+                .is_none_or(|columns| size.columns == columns as usize)
+    };
+    let mut sizes: Vec<(usize, &RmqrSize)> = SIZES.iter().enumerate().collect();
+    sizes.sort_by_key(|(_, size)| (size.rows * size.columns, size.rows));
+
+    let mut conditions = Vec::new();
+    if let Some(rows) = customization.rows {
+        conditions.push(format!("{} rows", rows));
+    }
+    if let Some(rows) = customization.max_rows {
+        conditions.push(format!("at most {} rows", rows));
+    }
+    if let Some(columns) = customization.columns {
+        conditions.push(format!("{} columns", columns));
+    }
+    if !sizes.iter().any(|(_, size)| allowed(size)) {
+        return Err(ApiError::ValidationError(format!(
+            "No rMQR size has {}",
+            conditions.join(" and ")
+        )));
+    }
+
+    let attempt = |size: &RmqrSize| {
+        let segments = fixed.map_or_else(|| optimal_segments(content, size), <[Segment]>::to_vec);
+        size.codewords(&segments, high, Preamble::of(customization))
+            .map(|codewords| (codewords, segments))
+    };
+    let Some((index, size, (codewords, segments))) = sizes
+        .iter()
+        .filter(|(_, size)| allowed(size))
+        .find_map(|&(index, size)| attempt(size).map(|encoded| (index, size, encoded)))
+    else {
+        let target = match (customization.rows, customization.columns) {
+            (Some(rows), Some(columns)) => format!("R{}x{}", rows, columns),
+            _ if conditions.is_empty() => "any rMQR size".to_string(),
+            _ => format!("an rMQR size with {}", conditions.join(" and ")),
+        };
+        let message = match sizes.iter().find(|(_, size)| attempt(size).is_some()) {
+            Some((_, size)) => format!(
+                "Content does not fit {} at error correction level {:?}; the smallest rMQR size that holds it is {}",
+                target,
+                level,
+                size.name()
+            ),
+            None => format!(
+                "Content does not fit {} at error correction level {:?}; shorten it or use a QR code",
+                target, level
+            ),
+        };
+        return Err(ApiError::ValidationError(message));
+    };
+
+    let mut grid = Grid::new(size);
+    grid.draw_function_patterns(size, format_information(index, high));
+    grid.place_data(&codewords);
+    let quiet_zone = customization.quiet_zone() as usize;
+    Ok(EncodedSymbol {
+        matrix: QrMatrix::new(
+            size.columns,
+            size.rows,
+            grid.modules,
+            grid.functional,
+            quiet_zone,
+            vec![(0, 0)],
+        ),
+        segments,
+    })
+}
+
+/// Splits the content into the numeric, alphanumeric and byte segments
+/// that take the fewest bits in `size`.
+///
+/// For each character and each mode the last segment may be in, this
+/// keeps the cheapest encoding of the content so far, counted in sixths of
+/// a bit so that digits (10 bits per 3) and alphanumeric characters (11
+/// bits per 2) add up exactly. Switching modes costs a rounded-up segment
+/// and the next segment's header. As in QR codes, the Kanji mode is only
+/// used when asked for.
+fn optimal_segments(content: &str, size: &RmqrSize) -> Vec<Segment> {
+    const MODES: [EncodingMode; 3] = [
+        EncodingMode::Numeric,
+        EncodingMode::Alphanumeric,
+        EncodingMode::Byte,
+    ];
+    let header = |mode: usize| (3 + size.count_bits[mode]) * 6;
+    let chars: Vec<char> = content.chars().collect();
+
+    let mut costs = [header(0), header(1), header(2)];
+    // For each character, the mode it is encoded in when the content so
+    // far ends in each mode
+    let mut choices: Vec<[Option<usize>; 3]> = Vec::with_capacity(chars.len());
+    for &c in &chars {
+        let mut extended = [usize::MAX; 3];
+        let mut choice = [None; 3];
+        for (mode, encoding) in MODES.iter().enumerate() {
+            if encoding.encodes(c) {
+                extended[mode] = costs[mode] + [20, 33, 48 * c.len_utf8()][mode];
+                choice[mode] = Some(mode);
+            }
+        }
+
+        let extensible = choice;
+        costs = extended;
+        for to in 0..3 {
+            for from in (0..3).filter(|&from| extensible[from].is_some()) {
+                let switched = extended[from].div_ceil(6) * 6 + header(to);
+                if switched < costs[to] {
+                    costs[to] = switched;
+                    choice[to] = Some(from);
+                }
+            }
+        }
+        choices.push(choice);
+    }
+
+    // Trace the cheapest final mode back to the first character
+    let mut mode = (0..3).min_by_key(|&mode| costs[mode]).unwrap_or(2);
+    let mut modes = vec![2; chars.len()];
+    for (index, choice) in choices.iter().enumerate().rev() {
+        mode = choice[mode].unwrap_or(2);
+        modes[index] = mode;
+    }
+
+    let mut segments: Vec<Segment> = Vec::new();
+    for (&c, &mode) in chars.iter().zip(&modes) {
+        match segments.last_mut() {
+            Some(segment) if segment.mode == MODES[mode] => segment.text.push(c),
+            _ => segments.push(Segment {
+                mode: MODES[mode],
+                text: c.to_string(),
+            }),
+        }
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Symbology;
+
+    fn rmqr() -> QrCustomization {
+        QrCustomization {
+            symbology: Symbology::Rmqr,
+            ..Default::default()
+        }
+    }
+
+    fn size_of(content: &str, customization: &QrCustomization) -> (usize, usize) {
+        let matrix = encode_rmqr(content, None, customization).unwrap().matrix;
+        (matrix.height(), matrix.width())
+    }
+
+    #[test]
+    fn test_sizes_match_their_data_modules() {
+        for (index, size) in SIZES.iter().enumerate() {
+            let mut grid = Grid::new(size);
+            grid.draw_function_patterns(size, format_information(index, false));
+            let data_modules = grid
+                .functional
+                .iter()
+                .filter(|&&functional| !functional)
+                .count();
+            for blocks in size.blocks {
+                let codewords: usize = blocks
+                    .iter()
+                    .map(|&(count, codewords, _)| count * codewords)
+                    .sum();
+                assert_eq!(codewords, data_modules / 8, "{}", size.name());
+                // Every block of a level has the same number of error
+                // correction codewords
+                assert!(blocks
+                    .iter()
+                    .all(|&(_, total, data)| total - data == blocks[0].1 - blocks[0].2));
+            }
+        }
+    }
+
+    #[test]
+    fn test_format_information_distance() {
+        // BCH(18, 6) keeps any two format words at least 8 bits apart
+        let words: Vec<u32> = (0..64)
+            .map(|data| format_information(data & 31, data >= 32))
+            .collect();
+        for (i, a) in words.iter().enumerate() {
+            assert_eq!(a >> 12, i as u32);
+            assert!(words[i + 1..].iter().all(|b| (a ^ b).count_ones() >= 8));
+        }
+    }
+
+    #[test]
+    fn test_function_patterns() {
+        let symbol = encode_rmqr("CBL-42", None, &rmqr()).unwrap();
+        let matrix = symbol.matrix;
+        let (width, height) = (matrix.width(), matrix.height());
+        assert_eq!((height, width), (11, 27));
+        assert_eq!(matrix.finder_origins(), &[(0, 0)]);
+
+        // Finder, sub-pattern center and ring, and both corner patterns
+        assert!(matrix.is_dark(0, 0) && matrix.is_dark(3, 3) && !matrix.is_dark(1, 1));
+        assert!(matrix.is_dark(width - 3, height - 3) && !matrix.is_dark(width - 2, height - 2));
+        assert!(
+            matrix.is_dark(width - 2, 0)
+                && matrix.is_dark(width - 1, 1)
+                && !matrix.is_dark(width - 2, 1)
+        );
+        assert!(
+            matrix.is_dark(1, height - 1)
+                && matrix.is_dark(0, height - 2)
+                && !matrix.is_dark(1, height - 2)
+        );
+        // Timing pattern along the top edge
+        assert!(matrix.is_dark(12, 0) && !matrix.is_dark(13, 0));
+        assert!(matrix.is_functional(8, 1) && !matrix.is_functional(12, 5));
+    }
+
+    /// Modules of the symbol as rows of `#` for dark and `.` for light.
+    fn rows_of(matrix: &QrMatrix) -> Vec<String> {
+        (0..matrix.height())
+            .map(|y| {
+                (0..matrix.width())
+                    .map(|x| if matrix.is_dark(x, y) { '#' } else { '.' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_known_answer() {
+        // Both symbols were checked against a separate implementation of
+        // ISO/IEC 23941 with its own Reed-Solomon encoder. R7x43 at level M
+        // holds "0123456789" as one numeric segment
+        let customization = QrCustomization {
+            rows: Some(7),
+            columns: Some(43),
+            error_correction: ErrorCorrectionLevel::M,
+            ..rmqr()
+        };
+        let segments = optimal_segments("0123456789", &SIZES[0]);
+        let codewords = SIZES[0]
+            .codewords(&segments, false, Preamble::of(&customization))
+            .unwrap();
+        assert_eq!(
+            codewords,
+            [0x34, 0x06, 0x2B, 0x35, 0x34, 0x80, 0x2C, 0x27, 0x03, 0x99, 0xC7, 0x57, 0x99]
+        );
+        let matrix = encode_rmqr("0123456789", None, &customization)
+            .unwrap()
+            .matrix;
+        assert_eq!(
+            rows_of(&matrix),
+            [
+                "#######.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.###",
+                "#.....#..#.##.#.##..#.#.#.###.##.#.##...#.#",
+                "#.###.#.#.###.##.#..#####...#.#..##########",
+                "#.###.#..##..##.#...#..##.###.#..#....#...#",
+                "#.###.#...#.....#.#####.###..#.##.##..#.#.#",
+                "#.....#.####.#...#..#.#.###.#.#.##.##.#...#",
+                "#######.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#####",
+            ]
+        );
+
+        // R11x59 at level H holds "rMQR test" as one byte segment in two
+        // interleaved blocks, with two alignment patterns and both corner
+        // patterns
+        let customization = QrCustomization {
+            rows: Some(11),
+            columns: Some(59),
+            error_correction: ErrorCorrectionLevel::H,
+            ..rmqr()
+        };
+        let segments = [Segment {
+            mode: EncodingMode::Byte,
+            text: "rMQR test".to_string(),
+        }];
+        let matrix = encode_rmqr("rMQR test", Some(&segments), &customization)
+            .unwrap()
+            .matrix;
+        assert_eq!(
+            rows_of(&matrix),
+            [
+                "#######.#.#.#.#.#.###.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.###",
+                "#.....#.#.#...#...#.#..#..#.#.#..#..###.###.#..###...#.#..#",
+                "#.###.#....##....####.#...##.#..##.##.###....###.##.##.#.##",
+                "#.###.#.#.###.......#......###...##..#.....#.###.#.#..#.##.",
+                "#.###.#...###.#..#.##.###.##.##..####..#....#...##.##..#.##",
+                "#.....#....#####.#....#.##.#...##.####...##.....#.#..##....",
+                "#######.#...#.##.###..####..#.#####.#..###.#.##..##.#.#####",
+                "...........#...#.#..#......###.###......###...#.##.##.#...#",
+                "###..#..#..#..########..#....##..###.####.....#.#.##..#.#.#",
+                "#.####.....##...#.#.#.#.##..#..#..#.###.##.#.##.#....##...#",
+                "###.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#.#.###.#.#.#.#.#.#.#####",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_smallest_rectangle() {
+        // R11x27 has the smallest area, R7x43 the smallest height
+        assert_eq!(size_of("12345", &rmqr()), (11, 27));
+        assert_eq!(
+            size_of(
+                "12345",
+                &QrCustomization {
+                    max_rows: Some(8),
+                    ..rmqr()
+                }
+            ),
+            (7, 43)
+        );
+        assert_eq!(
+            size_of(
+                "12345",
+                &QrCustomization {
+                    rows: Some(9),
+                    ..rmqr()
+                }
+            ),
+            (9, 43)
+        );
+        assert_eq!(
+            size_of(
+                "12345",
+                &QrCustomization {
+                    columns: Some(139),
+                    ..rmqr()
+                }
+            ),
+            (7, 139)
+        );
+
+        // 30 bytes need R7x139 at level M, and taller symbols at H
+        let url = "https://example.com/cable/0042";
+        assert_eq!(
+            size_of(
+                url,
+                &QrCustomization {
+                    max_rows: Some(7),
+                    ..rmqr()
+                }
+            ),
+            (7, 139)
+        );
+        let high = QrCustomization {
+            error_correction: ErrorCorrectionLevel::H,
+            ..rmqr()
+        };
+        assert!(size_of(url, &high).0 > 7);
+    }
+
+    #[test]
+    fn test_size_errors() {
+        let url = "https://example.com/cable/0042";
+        let error = encode_rmqr(
+            url,
+            None,
+            &QrCustomization {
+                rows: Some(7),
+                columns: Some(43),
+                ..rmqr()
+            },
+        )
+        .unwrap_err();
+        assert!(
+            error.to_string().contains("does not fit R7x43 at error correction level M; the smallest rMQR size that holds it is R15x43"),
+            "{}",
+            error
+        );
+
+        let error = encode_rmqr(
+            "1",
+            None,
+            &QrCustomization {
+                rows: Some(7),
+                columns: Some(27),
+                ..rmqr()
+            },
+        )
+        .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("No rMQR size has 7 rows and 27 columns"),
+            "{}",
+            error
+        );
+
+        let error = encode_rmqr(&"x".repeat(200), None, &rmqr()).unwrap_err();
+        assert!(
+            error.to_string().contains("does not fit any rMQR size"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_optimal_segments() {
+        let segments = optimal_segments("TUBE-2024-0000000000017", &SIZES[31]);
+        let modes: Vec<EncodingMode> = segments.iter().map(|segment| segment.mode).collect();
+        assert_eq!(modes, [EncodingMode::Alphanumeric, EncodingMode::Numeric]);
+        assert_eq!(segments[1].text, "0000000000017");
+
+        // Short runs of digits are not worth a segment of their own
+        let segments = optimal_segments("lot 7b", &SIZES[31]);
+        assert_eq!(
+            segments,
+            [Segment {
+                mode: EncodingMode::Byte,
+                text: "lot 7b".to_string()
+            }]
+        );
+    }
+}
@@ -0,0 +1,699 @@
+use super::{matrix::QrMatrix, paint::Paint};
+use crate::models::{EyeShape, EyeStyle, ModuleStyle, QrColors};
+
+/// Control point distance for approximating a quarter circle with a cubic
+/// Bézier curve.
+const KAPPA: f64 = 0.552_284_749_8;
+
+/// Straight segments each curve is split into for hit testing outlines.
+const CURVE_SEGMENTS: usize = 8;
+
+/// Radius of the corners of `ModuleStyle::Rounded`, in modules.
+const ROUNDED_RADIUS: f64 = 0.3;
+
+/// Gap left on each side of a bar so neighbouring bars stay distinct.
+const BAR_INSET: f64 = 0.1;
+
+/// Rectangle with an individual radius per corner, in module units.
+/// Radii are ordered top-left, top-right, bottom-right, bottom-left.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RoundedRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub radii: [f64; 4],
+}
+
+impl RoundedRect {
+    pub fn square(x: f64, y: f64, width: f64, height: f64) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+            radii: [0.0; 4],
+        }
+    }
+
+    pub fn uniform(x: f64, y: f64, width: f64, height: f64, radius: f64) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+            radii: [radius; 4],
+        }
+    }
+
+    pub fn contains(&self, px: f64, py: f64) -> bool {
+        if px < self.x || py < self.y || px > self.x + self.width || py > self.y + self.height {
+            return false;
+        }
+
+        let [tl, tr, br, bl] = self.radii;
+        let right = self.x + self.width;
+        let bottom = self.y + self.height;
+        let corners = [
+            (
+                tl,
+                self.x + tl,
+                self.y + tl,
+                px < self.x + tl && py < self.y + tl,
+            ),
+            (
+                tr,
+                right - tr,
+                self.y + tr,
+                px > right - tr && py < self.y + tr,
+            ),
+            (
+                br,
+                right - br,
+                bottom - br,
+                px > right - br && py > bottom - br,
+            ),
+            (
+                bl,
+                self.x + bl,
+                bottom - bl,
+                px < self.x + bl && py > bottom - bl,
+            ),
+        ];
+
+        corners.iter().all(|&(r, cx, cy, in_corner)| {
+            !in_corner || r <= 0.0 || (px - cx).powi(2) + (py - cy).powi(2) <= r * r
+        })
+    }
+
+    fn is_square(&self) -> bool {
+        self.radii.iter().all(|r| *r <= 0.0)
+    }
+
+    /// Outline as a closed path, clockwise unless `reverse` is set.
+    pub fn path(&self, reverse: bool) -> Vec<PathOp> {
+        let [tl, tr, br, bl] = self.radii;
+        let (x, y, right, bottom) = (self.x, self.y, self.x + self.width, self.y + self.height);
+
+        // Each corner as (start of the arc, corner point, end of the arc), clockwise
+        let mut corners = vec![
+            ((right - tr, y), (right, y), (right, y + tr)),
+            ((right, bottom - br), (right, bottom), (right - br, bottom)),
+            ((x + bl, bottom), (x, bottom), (x, bottom - bl)),
+            ((x, y + tl), (x, y), (x + tl, y)),
+        ];
+        if reverse {
+            corners = corners
+                .into_iter()
+                .rev()
+                .map(|(from, corner, to)| (to, corner, from))
+                .collect();
+        }
+
+        let start = (x + tl, y);
+        let mut current = start;
+        let mut ops = vec![PathOp::MoveTo(start.0, start.1)];
+        for (from, corner, to) in corners {
+            if from != current {
+                ops.push(PathOp::LineTo(from.0, from.1));
+            }
+            if from != to {
+                let c1 = (
+                    from.0 + (corner.0 - from.0) * KAPPA,
+                    from.1 + (corner.1 - from.1) * KAPPA,
+                );
+                let c2 = (
+                    to.0 + (corner.0 - to.0) * KAPPA,
+                    to.1 + (corner.1 - to.1) * KAPPA,
+                );
+                ops.push(PathOp::CubicTo(c1.0, c1.1, c2.0, c2.1, to.0, to.1));
+            }
+            current = to;
+        }
+        ops.push(PathOp::Close);
+        ops
+    }
+}
+
+/// A filled primitive in module coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    Rect(RoundedRect),
+    /// Square rotated by 45 degrees, given by its center and half-diagonal.
+    Diamond {
+        cx: f64,
+        cy: f64,
+        radius: f64,
+    },
+    /// Outer shape with the inner shape cut out.
+    Ring {
+        outer: RoundedRect,
+        inner: RoundedRect,
+    },
+    /// Free-form outline such as a glyph.
+    Outline(Outline),
+}
+
+impl Shape {
+    pub fn contains(&self, px: f64, py: f64) -> bool {
+        match self {
+            Shape::Rect(rect) => rect.contains(px, py),
+            Shape::Diamond { cx, cy, radius } => (px - cx).abs() + (py - cy).abs() <= *radius,
+            Shape::Ring { outer, inner } => outer.contains(px, py) && !inner.contains(px, py),
+            Shape::Outline(outline) => outline.contains(px, py),
+        }
+    }
+
+    /// Axis-aligned bounding box as `(x, y, width, height)`.
+    pub fn bounds(&self) -> (f64, f64, f64, f64) {
+        match self {
+            Shape::Rect(rect) | Shape::Ring { outer: rect, .. } => {
+                (rect.x, rect.y, rect.width, rect.height)
+            }
+            Shape::Diamond { cx, cy, radius } => {
+                (cx - radius, cy - radius, radius * 2.0, radius * 2.0)
+            }
+            Shape::Outline(outline) => outline.bounds,
+        }
+    }
+
+    /// Whether the shape is a plain axis-aligned rectangle, which renderers
+    /// can fill exactly without sampling.
+    pub fn is_axis_aligned_rect(&self) -> bool {
+        matches!(self, Shape::Rect(rect) if rect.is_square())
+    }
+
+    /// Whether the shape only has horizontal and vertical edges.
+    pub fn is_rectilinear(&self) -> bool {
+        match self {
+            Shape::Rect(rect) => rect.is_square(),
+            Shape::Diamond { .. } | Shape::Outline(_) => false,
+            Shape::Ring { outer, inner } => outer.is_square() && inner.is_square(),
+        }
+    }
+
+    /// Outline as path operations. Holes are wound counter-clockwise so the
+    /// default nonzero fill rule cuts them out.
+    pub fn path(&self) -> Vec<PathOp> {
+        match self {
+            Shape::Rect(rect) => rect.path(false),
+            Shape::Diamond { cx, cy, radius } => vec![
+                PathOp::MoveTo(*cx, cy - radius),
+                PathOp::LineTo(cx + radius, *cy),
+                PathOp::LineTo(*cx, cy + radius),
+                PathOp::LineTo(cx - radius, *cy),
+                PathOp::Close,
+            ],
+            Shape::Ring { outer, inner } => {
+                let mut ops = outer.path(false);
+                ops.extend(inner.path(true));
+                ops
+            }
+            Shape::Outline(outline) => outline.ops.clone(),
+        }
+    }
+}
+
+/// Closed contours of lines and curves, filled with the nonzero rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outline {
+    ops: Vec<PathOp>,
+    /// The contours flattened to polygons, for hit testing.
+    polygons: Vec<Vec<(f64, f64)>>,
+    bounds: (f64, f64, f64, f64),
+}
+
+impl Outline {
+    pub fn new(ops: Vec<PathOp>) -> Self {
+        let mut polygons: Vec<Vec<(f64, f64)>> = Vec::new();
+        let mut current = (0.0, 0.0);
+        for op in &ops {
+            match *op {
+                PathOp::MoveTo(x, y) => {
+                    polygons.push(vec![(x, y)]);
+                    current = (x, y);
+                }
+                PathOp::LineTo(x, y) => {
+                    if let Some(polygon) = polygons.last_mut() {
+                        polygon.push((x, y));
+                    }
+                    current = (x, y);
+                }
+                PathOp::CubicTo(x1, y1, x2, y2, x, y) => {
+                    if let Some(polygon) = polygons.last_mut() {
+                        let (x0, y0) = current;
+                        for step in 1..=CURVE_SEGMENTS {
+                            let t = step as f64 / CURVE_SEGMENTS as f64;
+                            let u = 1.0 - t;
+                            let (a, b, c, d) =
+                                (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+                            polygon.push((
+                                a * x0 + b * x1 + c * x2 + d * x,
+                                a * y0 + b * y1 + c * y2 + d * y,
+                            ));
+                        }
+                    }
+                    current = (x, y);
+                }
+                PathOp::Close => {}
+            }
+        }
+
+        let points = || polygons.iter().flatten();
+        let (mut x0, mut y0) = (f64::INFINITY, f64::INFINITY);
+        let (mut x1, mut y1) = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for &(x, y) in points() {
+            (x0, y0, x1, y1) = (x0.min(x), y0.min(y), x1.max(x), y1.max(y));
+        }
+        let bounds = if x0 <= x1 {
+            (x0, y0, x1 - x0, y1 - y0)
+        } else {
+            (0.0, 0.0, 0.0, 0.0)
+        };
+
+        Self {
+            ops,
+            polygons,
+            bounds,
+        }
+    }
+
+    /// Nonzero winding test: the point is inside when the contours wind
+    /// around it on balance.
+    fn contains(&self, px: f64, py: f64) -> bool {
+        let mut winding = 0;
+        for polygon in &self.polygons {
+            for (i, &(ax, ay)) in polygon.iter().enumerate() {
+                let (bx, by) = polygon[(i + 1) % polygon.len()];
+                if (ay <= py) != (by <= py) {
+                    let x = ax + (py - ay) / (by - ay) * (bx - ax);
+                    if x > px {
+                        winding += if by > ay { 1 } else { -1 };
+                    }
+                }
+            }
+        }
+        winding != 0
+    }
+}
+
+/// Vector path operation in module coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathOp {
+    MoveTo(f64, f64),
+    LineTo(f64, f64),
+    CubicTo(f64, f64, f64, f64, f64, f64),
+    Close,
+}
+
+/// Shapes filled with a single paint.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layer {
+    pub paint: Paint,
+    pub shapes: Vec<Shape>,
+}
+
+/// Builds every shape of the symbol, grouped into layers by paint and in
+/// painting order: data modules first, then the finder patterns.
+pub fn symbol_layers(
+    matrix: &QrMatrix,
+    style: &ModuleStyle,
+    eye_style: &EyeStyle,
+    colors: &QrColors,
+) -> Vec<Layer> {
+    let foreground = Paint::foreground(colors);
+    let mut layers = vec![Layer {
+        paint: foreground.clone(),
+        shapes: module_shapes(matrix, style),
+    }];
+
+    for (index, &origin) in matrix.finder_origins().iter().enumerate() {
+        let (outer, inner) = eye_shapes(origin, eye_style);
+        let (outer_color, inner_color) = colors.eye_overrides(index);
+        for (shape, color) in [(outer, outer_color), (inner, inner_color)] {
+            // Eyes without their own color are painted like the data modules
+            let paint = match color {
+                Some(color) => Paint::Solid(color.to_string()),
+                None => foreground.clone(),
+            };
+            match layers.iter_mut().find(|layer| layer.paint == paint) {
+                Some(layer) => layer.shapes.push(shape),
+                None => layers.push(Layer {
+                    paint,
+                    shapes: vec![shape],
+                }),
+            }
+        }
+    }
+
+    layers
+}
+
+/// Builds the shapes for every dark module outside the finder patterns.
+pub fn module_shapes(matrix: &QrMatrix, style: &ModuleStyle) -> Vec<Shape> {
+    let mut shapes = Vec::new();
+
+    match style {
+        ModuleStyle::Square => {
+            for (x, y, len) in data_runs(matrix, false) {
+                shapes.push(Shape::Rect(RoundedRect::square(
+                    x as f64, y as f64, len as f64, 1.0,
+                )));
+            }
+        }
+        ModuleStyle::Dots => {
+            for (x, y) in data_modules(matrix) {
+                shapes.push(Shape::Rect(RoundedRect::uniform(
+                    x as f64, y as f64, 1.0, 1.0, 0.5,
+                )));
+            }
+        }
+        ModuleStyle::Rounded => {
+            for (x, y) in data_modules(matrix) {
+                shapes.push(Shape::Rect(RoundedRect::uniform(
+                    x as f64,
+                    y as f64,
+                    1.0,
+                    1.0,
+                    ROUNDED_RADIUS,
+                )));
+            }
+        }
+        ModuleStyle::ConnectedRounded => {
+            for (x, y) in data_modules(matrix) {
+                let up = y > 0 && is_data_dark(matrix, x, y - 1);
+                let down = is_data_dark(matrix, x, y + 1);
+                let left = x > 0 && is_data_dark(matrix, x - 1, y);
+                let right = is_data_dark(matrix, x + 1, y);
+                // Only outer corners, where neither neighbour continues the shape, are rounded
+                let radius = |a: bool, b: bool| if a || b { 0.0 } else { 0.5 };
+                shapes.push(Shape::Rect(RoundedRect {
+                    x: x as f64,
+                    y: y as f64,
+                    width: 1.0,
+                    height: 1.0,
+                    radii: [
+                        radius(up, left),
+                        radius(up, right),
+                        radius(down, right),
+                        radius(down, left),
+                    ],
+                }));
+            }
+        }
+        ModuleStyle::Diamond => {
+            for (x, y) in data_modules(matrix) {
+                shapes.push(Shape::Diamond {
+                    cx: x as f64 + 0.5,
+                    cy: y as f64 + 0.5,
+                    radius: 0.5,
+                });
+            }
+        }
+        ModuleStyle::VerticalBars => {
+            let width = 1.0 - 2.0 * BAR_INSET;
+            for (x, y, len) in data_runs(matrix, true) {
+                shapes.push(Shape::Rect(RoundedRect::uniform(
+                    x as f64 + BAR_INSET,
+                    y as f64,
+                    width,
+                    len as f64,
+                    width / 2.0,
+                )));
+            }
+        }
+        ModuleStyle::HorizontalBars => {
+            let height = 1.0 - 2.0 * BAR_INSET;
+            for (x, y, len) in data_runs(matrix, false) {
+                shapes.push(Shape::Rect(RoundedRect::uniform(
+                    x as f64,
+                    y as f64 + BAR_INSET,
+                    len as f64,
+                    height,
+                    height / 2.0,
+                )));
+            }
+        }
+    }
+
+    shapes
+}
+
+fn is_data_dark(matrix: &QrMatrix, x: usize, y: usize) -> bool {
+    matrix.is_dark(x, y) && !matrix.is_finder(x, y)
+}
+
+/// Runs of dark modules outside the finder patterns as `(x, y, length)`,
+/// along rows or, if `vertical` is set, along columns.
+fn data_runs(matrix: &QrMatrix, vertical: bool) -> Vec<(usize, usize, usize)> {
+    let (lines, length) = if vertical {
+        (matrix.width(), matrix.height())
+    } else {
+        (matrix.height(), matrix.width())
+    };
+    let dark = |line: usize, pos: usize| {
+        if vertical {
+            is_data_dark(matrix, line, pos)
+        } else {
+            is_data_dark(matrix, pos, line)
+        }
+    };
+
+    let mut runs = Vec::new();
+    for line in 0..lines {
+        let mut pos = 0;
+        while pos < length {
+            if !dark(line, pos) {
+                pos += 1;
+                continue;
+            }
+            let start = pos;
+            while pos < length && dark(line, pos) {
+                pos += 1;
+            }
+            runs.push(if vertical {
+                (line, start, pos - start)
+            } else {
+                (start, line, pos - start)
+            });
+        }
+    }
+    runs
+}
+
+/// Dark modules outside the finder patterns.
+fn data_modules(matrix: &QrMatrix) -> impl Iterator<Item = (usize, usize)> + '_ {
+    (0..matrix.height())
+        .flat_map(move |y| (0..matrix.width()).map(move |x| (x, y)))
+        .filter(|&(x, y)| is_data_dark(matrix, x, y))
+}
+
+/// Outer ring and pupil of the finder pattern whose top-left corner is at
+/// `origin`.
+fn eye_shapes(origin: (usize, usize), eye_style: &EyeStyle) -> (Shape, Shape) {
+    let (x, y) = (origin.0 as f64, origin.1 as f64);
+    // Leaves keep the corners that face the rest of the code square. The
+    // top-left eye faces down-right, the other two face along the diagonal.
+    let leaf = |radius: f64| {
+        if origin == (0, 0) {
+            [0.0, radius, 0.0, radius]
+        } else {
+            [radius, 0.0, radius, 0.0]
+        }
+    };
+    let radii = |shape: &EyeShape, size: f64| match shape {
+        EyeShape::Square => [0.0; 4],
+        EyeShape::Rounded => [size / 4.0; 4],
+        EyeShape::Circle => [size / 2.0; 4],
+        EyeShape::Leaf => leaf(size / 2.0),
+    };
+
+    let outer = Shape::Ring {
+        outer: RoundedRect {
+            x,
+            y,
+            width: 7.0,
+            height: 7.0,
+            radii: radii(&eye_style.outer, 7.0),
+        },
+        inner: RoundedRect {
+            x: x + 1.0,
+            y: y + 1.0,
+            width: 5.0,
+            height: 5.0,
+            radii: radii(&eye_style.outer, 5.0),
+        },
+    };
+    let inner = Shape::Rect(RoundedRect {
+        x: x + 2.0,
+        y: y + 2.0,
+        width: 3.0,
+        height: 3.0,
+        radii: radii(&eye_style.inner, 3.0),
+    });
+
+    (outer, inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qrcode::QrCode;
+
+    fn matrix() -> QrMatrix {
+        QrMatrix::from_qr_code(&QrCode::new(b"https://example.com").unwrap(), 4)
+    }
+
+    fn rasterized_modules(shapes: &[Shape], width: usize) -> Vec<bool> {
+        (0..width * width)
+            .map(|i| {
+                let (x, y) = ((i % width) as f64 + 0.5, (i / width) as f64 + 0.5);
+                shapes.iter().any(|s| s.contains(x, y))
+            })
+            .collect()
+    }
+
+    fn all_shapes(matrix: &QrMatrix, style: &ModuleStyle, eye_style: &EyeStyle) -> Vec<Shape> {
+        symbol_layers(matrix, style, eye_style, &QrColors::default())
+            .into_iter()
+            .flat_map(|layer| layer.shapes)
+            .collect()
+    }
+
+    #[test]
+    fn test_every_style_covers_module_centers() {
+        let matrix = matrix();
+        let expected: Vec<bool> = (0..matrix.width() * matrix.width())
+            .map(|i| matrix.is_dark(i % matrix.width(), i / matrix.width()))
+            .collect();
+
+        for style in [
+            ModuleStyle::Square,
+            ModuleStyle::Dots,
+            ModuleStyle::Rounded,
+            ModuleStyle::ConnectedRounded,
+            ModuleStyle::Diamond,
+            ModuleStyle::VerticalBars,
+            ModuleStyle::HorizontalBars,
+        ] {
+            let shapes = all_shapes(&matrix, &style, &EyeStyle::default());
+            assert_eq!(
+                rasterized_modules(&shapes, matrix.width()),
+                expected,
+                "{:?}",
+                style
+            );
+        }
+    }
+
+    #[test]
+    fn test_eye_shapes_cover_finder_centers() {
+        let matrix = matrix();
+        let expected: Vec<bool> = (0..matrix.width() * matrix.width())
+            .map(|i| matrix.is_dark(i % matrix.width(), i / matrix.width()))
+            .collect();
+
+        for shape in [
+            EyeShape::Square,
+            EyeShape::Rounded,
+            EyeShape::Circle,
+            EyeShape::Leaf,
+        ] {
+            let eye_style = EyeStyle {
+                outer: shape.clone(),
+                inner: shape.clone(),
+            };
+            let shapes = all_shapes(&matrix, &ModuleStyle::Square, &eye_style);
+            let rasterized = rasterized_modules(&shapes, matrix.width());
+            // Ring and pupil centers stay dark, the separator stays light
+            for &(fx, fy) in matrix.finder_origins() {
+                for (dx, dy) in [(3, 0), (0, 3), (6, 3), (3, 6), (3, 3), (2, 3), (3, 1)] {
+                    let index = (fy + dy) * matrix.width() + fx + dx;
+                    assert_eq!(
+                        rasterized[index],
+                        expected[index],
+                        "{:?} at {:?}",
+                        shape,
+                        (dx, dy)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_layers_group_by_color() {
+        use crate::models::EyeColors;
+
+        let matrix = matrix();
+        let mut colors = QrColors::default();
+        assert_eq!(
+            symbol_layers(&matrix, &ModuleStyle::Square, &EyeStyle::default(), &colors).len(),
+            1
+        );
+
+        colors.eyes = vec![EyeColors {
+            outer: Some("#FF0000".to_string()),
+            inner: Some("#0000FF".to_string()),
+        }];
+        let layers = symbol_layers(&matrix, &ModuleStyle::Square, &EyeStyle::default(), &colors);
+        let summary: Vec<(&Paint, usize)> =
+            layers.iter().map(|l| (&l.paint, l.shapes.len())).collect();
+        assert_eq!(
+            summary[1..],
+            [
+                (&Paint::Solid("#FF0000".to_string()), 3),
+                (&Paint::Solid("#0000FF".to_string()), 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dots_leave_corners_empty() {
+        let dot = Shape::Rect(RoundedRect::uniform(0.0, 0.0, 1.0, 1.0, 0.5));
+        assert!(dot.contains(0.5, 0.5));
+        assert!(!dot.contains(0.05, 0.05));
+    }
+
+    #[test]
+    fn test_ring_has_hole() {
+        let ring = Shape::Ring {
+            outer: RoundedRect::square(0.0, 0.0, 7.0, 7.0),
+            inner: RoundedRect::square(1.0, 1.0, 5.0, 5.0),
+        };
+        assert!(ring.contains(0.5, 3.5));
+        assert!(!ring.contains(3.5, 3.5));
+    }
+
+    #[test]
+    fn test_outline_fills_nonzero() {
+        // A ring outline built from a rounded rectangle and a reversed hole,
+        // as glyphs like "o" are drawn
+        let mut ops = RoundedRect::uniform(0.0, 0.0, 6.0, 6.0, 2.0).path(false);
+        ops.extend(RoundedRect::square(2.0, 2.0, 2.0, 2.0).path(true));
+        let outline = Shape::Outline(Outline::new(ops));
+
+        assert!(outline.contains(1.0, 3.0));
+        assert!(!outline.contains(3.0, 3.0));
+        // Outside the rounded corner
+        assert!(!outline.contains(0.1, 0.1));
+        assert_eq!(outline.bounds(), (0.0, 0.0, 6.0, 6.0));
+        assert!(!outline.is_rectilinear());
+    }
+
+    #[test]
+    fn test_rounded_rect_path_is_closed() {
+        let ops = RoundedRect::uniform(0.0, 0.0, 1.0, 1.0, 0.3).path(false);
+        assert!(matches!(ops.first(), Some(PathOp::MoveTo(_, _))));
+        assert_eq!(ops.last(), Some(&PathOp::Close));
+        assert_eq!(
+            ops.iter()
+                .filter(|op| matches!(op, PathOp::CubicTo(..)))
+                .count(),
+            4
+        );
+
+        let square = RoundedRect::square(0.0, 0.0, 1.0, 1.0).path(false);
+        assert!(!square.iter().any(|op| matches!(op, PathOp::CubicTo(..))));
+    }
+}
@@ -0,0 +1,450 @@
+use std::fmt::Write;
+
+use super::{
+    caption::PlacedCaption,
+    layout::QrLayout,
+    logo::PlacedLogo,
+    paint::{GradientGeometry, ImagePaint, Paint},
+    shapes::{Layer, PathOp, Shape},
+};
+use crate::models::{hex_to_rgba, Gradient, PrintSize, TextAlign};
+
+/// Font requested for caption text, with similar fallbacks for viewers
+/// without DejaVu Sans.
+const CAPTION_FONT_FAMILY: &str = "'DejaVu Sans', Verdana, sans-serif";
+
+/// Renders the layers as vector SVG.
+///
+/// Paths are measured in modules and placed with the same geometry as the
+/// raster renderers, so the output stays crisp at any scale and matches the
+/// PNG pixel for pixel at its nominal size. Each layer is merged into a
+/// single `<path>` to keep the file small, and gradients become native
+/// `<linearGradient>`/`<radialGradient>` definitions and image paints
+/// patterns of an embedded PNG. A caption is real `<text>`, so it stays selectable and
+/// searchable.
+pub fn render_svg(
+    layers: &[Layer],
+    layout: &QrLayout,
+    print: Option<&PrintSize>,
+    background: &str,
+    logo: Option<&PlacedLogo>,
+    caption: Option<&PlacedCaption>,
+) -> String {
+    let mut defs = String::new();
+    let mut paths = String::new();
+    let mut gradients: Vec<&Paint> = Vec::new();
+    let mut images: Vec<&ImagePaint> = Vec::new();
+    for layer in layers {
+        path_element(&mut paths, layer, &mut gradients, &mut images);
+    }
+
+    // The logo keeps its original encoding, so vector logos stay vector
+    if let Some(logo) = logo {
+        let (x, y, width, height) = logo.bounds();
+        let _ = writeln!(
+            paths,
+            r#"<image x="{}" y="{}" width="{}" height="{}" preserveAspectRatio="xMidYMid meet" xlink:href="{}"/>"#,
+            num(x),
+            num(y),
+            num(width),
+            num(height),
+            logo.data_uri()
+        );
+        for layer in logo.patterns() {
+            path_element(&mut paths, layer, &mut gradients, &mut images);
+        }
+    }
+
+    for (index, paint) in gradients.iter().enumerate() {
+        if let Paint::Gradient(gradient) = paint {
+            defs.push_str(&gradient_def(
+                index,
+                gradient,
+                (layout.modules as f64, layout.rows as f64),
+            ));
+        }
+    }
+    for (index, fill) in images.iter().enumerate() {
+        let _ = writeln!(
+            defs,
+            r#"<pattern id="qr-image-{}" patternUnits="userSpaceOnUse" x="{x}" y="{y}" width="{size}" height="{size}"><image width="{size}" height="{size}" preserveAspectRatio="none" xlink:href="{}"/></pattern>"#,
+            index,
+            fill.data_uri,
+            x = num(fill.x),
+            y = num(fill.y),
+            size = num(fill.size),
+        );
+    }
+    if !defs.is_empty() {
+        defs = format!("<defs>\n{}</defs>\n", defs);
+    }
+
+    // Anti-aliasing only blurs the edges of purely rectilinear codes
+    let rendering = if layers
+        .iter()
+        .flat_map(|l| &l.shapes)
+        .all(Shape::is_rectilinear)
+    {
+        r#" shape-rendering="crispEdges""#
+    } else {
+        ""
+    };
+
+    format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+            "\n",
+            r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" version="1.1" width="{length_x}" height="{length_y}" viewBox="0 0 {width} {height}"{rendering}>"#,
+            "\n",
+            "{defs}",
+            "{background}",
+            r#"<g transform="translate({origin_x} {origin_y}) scale({module_size})">"#,
+            "\n",
+            "{paths}",
+            "</g>\n",
+            "{caption}",
+            "</svg>\n"
+        ),
+        width = layout.width,
+        height = layout.height,
+        length_x = length(layout.width, layout, print),
+        length_y = length(layout.height, layout, print),
+        rendering = rendering,
+        origin_x = layout.origin().0,
+        origin_y = layout.origin().1,
+        module_size = layout.module_size,
+        background = background_rect(layout, background),
+        defs = defs,
+        paths = paths,
+        caption = caption.map(caption_text).unwrap_or_default(),
+    )
+}
+
+/// Writes a layer as a single `<path>`, collecting the gradients and image
+/// paints it uses for the definitions.
+fn path_element<'a>(
+    paths: &mut String,
+    layer: &'a Layer,
+    gradients: &mut Vec<&'a Paint>,
+    images: &mut Vec<&'a ImagePaint>,
+) {
+    let fill = match &layer.paint {
+        Paint::Solid(color) => color_attrs("fill", "fill-opacity", color),
+        Paint::Gradient(_) => {
+            let index = match gradients.iter().position(|&p| p == &layer.paint) {
+                Some(index) => index,
+                None => {
+                    gradients.push(&layer.paint);
+                    gradients.len() - 1
+                }
+            };
+            format!(r#"fill="url(#qr-gradient-{})""#, index)
+        }
+        Paint::Image(fill) => {
+            let index = images.len();
+            images.push(fill);
+            format!(r#"fill="url(#qr-image-{})""#, index)
+        }
+    };
+    let _ = writeln!(
+        paths,
+        r#"<path {} d="{}"/>"#,
+        fill,
+        path_data(&layer.shapes)
+    );
+}
+
+/// Caption lines as `<text>` elements in canvas pixels.
+fn caption_text(caption: &PlacedCaption) -> String {
+    let anchor = match caption.align() {
+        TextAlign::Left => "start",
+        TextAlign::Center => "middle",
+        TextAlign::Right => "end",
+    };
+    let mut text = String::new();
+    for line in caption.lines() {
+        let _ = writeln!(
+            text,
+            r#"<text x="{}" y="{}" font-family="{}" font-size="{}" text-anchor="{}" {}>{}</text>"#,
+            num(line.anchor),
+            num(line.baseline),
+            CAPTION_FONT_FAMILY,
+            num(caption.font_size()),
+            anchor,
+            color_attrs("fill", "fill-opacity", caption.color()),
+            escape_xml(&line.text)
+        );
+    }
+    text
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Gradient definition in the module coordinates of the symbol group.
+fn gradient_def(index: usize, gradient: &Gradient, size: (f64, f64)) -> String {
+    let mut stops = String::new();
+    for (offset, stop) in gradient.offsets().into_iter().zip(&gradient.stops) {
+        let _ = writeln!(
+            stops,
+            r#"<stop offset="{}" {}/>"#,
+            num(offset),
+            color_attrs("stop-color", "stop-opacity", &stop.color)
+        );
+    }
+
+    let element = match GradientGeometry::new(gradient, size) {
+        GradientGeometry::Linear { start, end } => format!(
+            r#"linearGradient id="qr-gradient-{}" gradientUnits="userSpaceOnUse" x1="{}" y1="{}" x2="{}" y2="{}""#,
+            index,
+            num(start.0),
+            num(start.1),
+            num(end.0),
+            num(end.1)
+        ),
+        GradientGeometry::Radial { center, radius } => format!(
+            r#"radialGradient id="qr-gradient-{}" gradientUnits="userSpaceOnUse" cx="{}" cy="{}" r="{}""#,
+            index,
+            num(center.0),
+            num(center.1),
+            num(radius)
+        ),
+    };
+    let name = element.split(' ').next().unwrap_or_default();
+    format!("<{}>\n{}</{}>\n", element, stops, name)
+}
+
+/// Value of the `width` or `height` attribute for `pixels` of the canvas:
+/// the pixels themselves, or the share of the print size they cover.
+fn length(pixels: u32, layout: &QrLayout, print: Option<&PrintSize>) -> String {
+    match print {
+        Some(print) => format!(
+            "{}{}",
+            num(print.width * pixels as f64 / layout.width as f64),
+            print.unit
+        ),
+        None => pixels.to_string(),
+    }
+}
+
+/// Full-canvas background, omitted entirely when it is fully transparent.
+fn background_rect(layout: &QrLayout, background: &str) -> String {
+    if matches!(hex_to_rgba(background), Ok((_, _, _, 0))) {
+        return String::new();
+    }
+    format!(
+        "<rect width=\"{}\" height=\"{}\" {}/>\n",
+        layout.width,
+        layout.height,
+        color_attrs("fill", "fill-opacity", background)
+    )
+}
+
+/// Color attributes for a validated color. SVG 1.1 has no alpha in color
+/// values, so translucency becomes a separate opacity attribute.
+fn color_attrs(color_attr: &str, opacity_attr: &str, color: &str) -> String {
+    match hex_to_rgba(color) {
+        Ok((r, g, b, a)) if a < 255 => format!(
+            r##"{}="#{:02X}{:02X}{:02X}" {}="{}""##,
+            color_attr,
+            r,
+            g,
+            b,
+            opacity_attr,
+            num(a as f64 / 255.0)
+        ),
+        _ => format!(r#"{}="{}""#, color_attr, color),
+    }
+}
+
+/// Serializes shapes as compact SVG path data.
+pub fn path_data(shapes: &[Shape]) -> String {
+    let mut data = String::new();
+    for shape in shapes {
+        let ops = shape.path();
+        let mut current = (0.0, 0.0);
+        let mut subpath_start = (0.0, 0.0);
+
+        // Writing to a String cannot fail
+        for (i, op) in ops.iter().enumerate() {
+            match *op {
+                PathOp::MoveTo(x, y) => {
+                    let _ = write!(data, "M{} {}", num(x), num(y));
+                    current = (x, y);
+                    subpath_start = current;
+                }
+                PathOp::LineTo(x, y) => {
+                    // Closing the path draws this segment anyway
+                    if (x, y) == subpath_start && matches!(ops.get(i + 1), Some(PathOp::Close)) {
+                        continue;
+                    }
+                    if y == current.1 {
+                        let _ = write!(data, "H{}", num(x));
+                    } else if x == current.0 {
+                        let _ = write!(data, "V{}", num(y));
+                    } else {
+                        let _ = write!(data, "L{} {}", num(x), num(y));
+                    }
+                    current = (x, y);
+                }
+                PathOp::CubicTo(x1, y1, x2, y2, x, y) => {
+                    let _ = write!(
+                        data,
+                        "C{} {} {} {} {} {}",
+                        num(x1),
+                        num(y1),
+                        num(x2),
+                        num(y2),
+                        num(x),
+                        num(y)
+                    );
+                    current = (x, y);
+                }
+                PathOp::Close => {
+                    data.push('Z');
+                    current = subpath_start;
+                }
+            }
+        }
+    }
+    data
+}
+
+/// Formats a coordinate with at most three decimals and no trailing zeros.
+pub fn num(value: f64) -> String {
+    fixed(value, 3)
+}
+
+/// Formats a number with at most `decimals` decimals and no trailing zeros.
+pub fn fixed(value: f64, decimals: usize) -> String {
+    let formatted = format!("{:.*}", decimals, value);
+    let trimmed = formatted.trim_end_matches('0').trim_end_matches('.');
+    if trimmed == "-0" {
+        "0".to_string()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{EyeStyle, ModuleStyle, QrColors};
+    use crate::services::{matrix::QrMatrix, shapes::symbol_layers};
+    use qrcode::QrCode;
+
+    fn svg_for(text: &[u8], style: ModuleStyle, size: u32) -> String {
+        svg_with_colors(text, style, size, &QrColors::default())
+    }
+
+    fn svg_with_colors(text: &[u8], style: ModuleStyle, size: u32, colors: &QrColors) -> String {
+        let qr_code = QrCode::new(text).unwrap();
+        let matrix = QrMatrix::from_qr_code(&qr_code, 4);
+        let layout = QrLayout::compute(&matrix, size).unwrap();
+        let layers = symbol_layers(&matrix, &style, &EyeStyle::default(), colors);
+        render_svg(&layers, &layout, None, &colors.background, None, None)
+    }
+
+    #[test]
+    fn test_render_svg_is_vector() {
+        let svg = svg_for(b"https://example.com", ModuleStyle::Square, 300);
+
+        assert!(svg.contains(r#"viewBox="0 0 300 300""#));
+        assert!(svg.contains(r#"width="300" height="300""#));
+        assert!(svg.contains(r##"fill="#000000""##));
+        assert!(svg.contains(r##"fill="#FFFFFF""##));
+        assert!(!svg.contains("<image"));
+        assert!(svg.contains(r#"shape-rendering="crispEdges""#));
+        // Every module of one color in a single merged path
+        assert_eq!(svg.matches("<path").count(), 1);
+        assert!(svg.trim_end().ends_with("</svg>"));
+    }
+
+    #[test]
+    fn test_render_svg_offsets_by_quiet_zone() {
+        // 21 + 2 * 4 = 29 modules at 5px each, with 5px of padding
+        let svg = svg_for(b"hello", ModuleStyle::Square, 150);
+
+        assert!(svg.contains(r#"transform="translate(22 22) scale(5)""#));
+        // The top-left finder ring, with its hole wound the other way
+        assert!(svg.contains("M0 0H7V7H0ZM1 1V6H6V1Z"));
+    }
+
+    #[test]
+    fn test_styled_paths_use_curves() {
+        let dots = svg_for(b"hello", ModuleStyle::Dots, 290);
+        assert!(dots.contains('C'));
+        assert!(!dots.contains("crispEdges"));
+
+        let diamonds = svg_for(b"hello", ModuleStyle::Diamond, 290);
+        assert!(diamonds.contains("L"));
+        assert!(!diamonds.contains('C'));
+    }
+
+    #[test]
+    fn test_gradient_uses_native_definition() {
+        use crate::models::{Gradient, GradientKind, GradientStop};
+
+        let colors = QrColors {
+            gradient: Some(Gradient {
+                kind: GradientKind::Radial,
+                angle: 0.0,
+                stops: vec![
+                    GradientStop {
+                        offset: None,
+                        color: "#000000".to_string(),
+                    },
+                    GradientStop {
+                        offset: Some(0.6),
+                        color: "#3A0CA3".to_string(),
+                    },
+                    GradientStop {
+                        offset: None,
+                        color: "#0000AA".to_string(),
+                    },
+                ],
+            }),
+            ..Default::default()
+        };
+        let svg = svg_with_colors(b"hello", ModuleStyle::Square, 290, &colors);
+
+        assert!(svg.contains(r#"<radialGradient id="qr-gradient-0" gradientUnits="userSpaceOnUse" cx="10.5" cy="10.5""#));
+        assert!(svg.contains(r##"<stop offset="0.6" stop-color="#3A0CA3"/>"##));
+        assert!(svg.contains(r##"<stop offset="1" stop-color="#0000AA"/>"##));
+        // Data modules and eyes share one gradient
+        assert_eq!(svg.matches("<path").count(), 1);
+        assert!(svg.contains(r#"fill="url(#qr-gradient-0)""#));
+    }
+
+    #[test]
+    fn test_transparency_uses_opacity_attributes() {
+        let colors = QrColors {
+            foreground: "#1A237ECC".to_string(),
+            background: "transparent".to_string(),
+            ..Default::default()
+        };
+        let svg = svg_with_colors(b"hello", ModuleStyle::Square, 290, &colors);
+
+        assert!(!svg.contains("<rect"));
+        assert!(svg.contains(r##"<path fill="#1A237E" fill-opacity="0.8" d="##));
+
+        let colors = QrColors {
+            background: "#FFFFFF80".to_string(),
+            ..Default::default()
+        };
+        let svg = svg_with_colors(b"hello", ModuleStyle::Square, 290, &colors);
+        assert!(svg
+            .contains(r##"<rect width="290" height="290" fill="#FFFFFF" fill-opacity="0.502"/>"##));
+    }
+
+    #[test]
+    fn test_num_formatting() {
+        assert_eq!(num(4.0), "4");
+        assert_eq!(num(0.5), "0.5");
+        assert_eq!(num(0.1234), "0.123");
+        assert_eq!(num(-0.0001), "0");
+    }
+}
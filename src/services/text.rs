@@ -0,0 +1,221 @@
+use std::fmt::Write as _;
+
+use super::{matrix::QrMatrix, paint::GradientSampler};
+use crate::{
+    errors::ApiError,
+    models::{composite, hex_to_rgba, ImageDimensions, OutputFormat, QrColors},
+};
+
+/// Renders the symbol and its quiet zone as text, one line per row of
+/// character cells.
+///
+/// `text` and `ansi` pack two module rows into each line with the half
+/// block characters, which keeps modules square in a typical terminal font.
+/// `ascii` uses two characters per module instead. Plain text draws dark
+/// modules as ink, so it reads correctly on a light background; `ansi`
+/// paints both colors explicitly and works on any terminal theme.
+pub fn render_text(
+    matrix: &QrMatrix,
+    format: &OutputFormat,
+    colors: &QrColors,
+) -> Result<String, ApiError> {
+    match format {
+        OutputFormat::Ascii => Ok(render_ascii(matrix)),
+        OutputFormat::Ansi => render_ansi(matrix, colors),
+        _ => Ok(render_half_blocks(matrix)),
+    }
+}
+
+/// Size of the text from `render_text`. Width and height count character
+/// cells, and `module_size` is the number of columns per module.
+pub fn text_dimensions(matrix: &QrMatrix, format: &OutputFormat) -> ImageDimensions {
+    let (columns, rows) = (matrix.total_width() as u32, matrix.total_height() as u32);
+    let (width, height, module_size) = match format {
+        OutputFormat::Ascii => (columns * 2, rows, 2),
+        _ => (columns, rows.div_ceil(2), 1),
+    };
+
+    ImageDimensions {
+        width,
+        height,
+        module_size,
+        quiet_zone: matrix.quiet_zone() as u32,
+        modules: matrix.width() as u32,
+        rows: matrix.height() as u32,
+    }
+}
+
+/// Whether the module at (x, y) is dark, in coordinates that include the
+/// quiet zone. Anything outside the symbol is light.
+fn is_dark(matrix: &QrMatrix, x: usize, y: usize) -> bool {
+    let quiet_zone = matrix.quiet_zone();
+    x >= quiet_zone && y >= quiet_zone && matrix.is_dark(x - quiet_zone, y - quiet_zone)
+}
+
+fn render_half_blocks(matrix: &QrMatrix) -> String {
+    let (columns, rows) = (matrix.total_width(), matrix.total_height());
+    let mut text = String::with_capacity(columns * rows.div_ceil(2) * 3);
+    for y in (0..rows).step_by(2) {
+        for x in 0..columns {
+            text.push(match (is_dark(matrix, x, y), is_dark(matrix, x, y + 1)) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            });
+        }
+        text.push('\n');
+    }
+    text
+}
+
+fn render_ascii(matrix: &QrMatrix) -> String {
+    let (columns, rows) = (matrix.total_width(), matrix.total_height());
+    let mut text = String::with_capacity((columns * 2 + 1) * rows);
+    for y in 0..rows {
+        for x in 0..columns {
+            text.push_str(if is_dark(matrix, x, y) { "##" } else { "  " });
+        }
+        text.push('\n');
+    }
+    text
+}
+
+fn render_ansi(matrix: &QrMatrix, colors: &QrColors) -> Result<String, ApiError> {
+    let palette = AnsiPalette::new(matrix, colors).map_err(ApiError::ValidationError)?;
+    let (columns, rows) = (matrix.total_width(), matrix.total_height());
+
+    let mut text = String::new();
+    for y in (0..rows).step_by(2) {
+        let mut current = None;
+        for x in 0..columns {
+            // The upper half block takes the foreground color, the rest of
+            // the cell the background color
+            let cell = (
+                palette.color_at(matrix, x, y),
+                palette.color_at(matrix, x, y + 1),
+            );
+            if current != Some(cell) {
+                let ((tr, tg, tb), (br, bg, bb)) = cell;
+                let _ = write!(
+                    text,
+                    "\x1b[38;2;{};{};{};48;2;{};{};{}m",
+                    tr, tg, tb, br, bg, bb
+                );
+                current = Some(cell);
+            }
+            text.push('▀');
+        }
+        text.push_str("\x1b[0m\n");
+    }
+    Ok(text)
+}
+
+/// Resolved module colors for ANSI output, flattened onto the effective
+/// background since terminals have no transparency.
+struct AnsiPalette<'a> {
+    colors: &'a QrColors,
+    base: (u8, u8, u8),
+    foreground: (u8, u8, u8),
+    background: (u8, u8, u8),
+    gradient: Option<GradientSampler>,
+}
+
+impl<'a> AnsiPalette<'a> {
+    fn new(matrix: &QrMatrix, colors: &'a QrColors) -> Result<Self, String> {
+        let base = colors.effective_background()?;
+        let gradient = match &colors.gradient {
+            Some(gradient) => Some(GradientSampler::new(
+                gradient,
+                (matrix.width() as f64, matrix.height() as f64),
+            )?),
+            None => None,
+        };
+
+        Ok(Self {
+            colors,
+            base,
+            foreground: composite(colors.foreground_rgba()?, base),
+            background: composite(colors.background_rgba()?, base),
+            gradient,
+        })
+    }
+
+    fn color_at(&self, matrix: &QrMatrix, x: usize, y: usize) -> (u8, u8, u8) {
+        if !is_dark(matrix, x, y) {
+            return self.background;
+        }
+
+        // Symbol coordinates; dark modules never lie in the quiet zone
+        let (sx, sy) = (x - matrix.quiet_zone(), y - matrix.quiet_zone());
+        for (index, &(fx, fy)) in matrix.finder_origins().iter().enumerate() {
+            if (fx..fx + 7).contains(&sx) && (fy..fy + 7).contains(&sy) {
+                let (outer, inner) = self.colors.eye_overrides(index);
+                let is_inner = (fx + 2..fx + 5).contains(&sx) && (fy + 2..fy + 5).contains(&sy);
+                let color = if is_inner { inner } else { outer };
+                if let Some(rgba) = color.and_then(|color| hex_to_rgba(color).ok()) {
+                    return composite(rgba, self.base);
+                }
+            }
+        }
+
+        match &self.gradient {
+            Some(sampler) => composite(
+                sampler.color_at(sx as f64 + 0.5, sy as f64 + 0.5),
+                self.base,
+            ),
+            None => self.foreground,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use qrcode::{EcLevel, QrCode, Version};
+
+    fn matrix(quiet_zone: usize) -> QrMatrix {
+        let qr_code = QrCode::with_version(b"hello", Version::Normal(1), EcLevel::M).unwrap();
+        QrMatrix::from_qr_code(&qr_code, quiet_zone)
+    }
+
+    #[test]
+    fn test_half_blocks_pack_two_rows_per_line() {
+        let text = render_text(&matrix(2), &OutputFormat::Text, &QrColors::default()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        // 21 modules + 2 * 2 quiet zone = 25 rows, packed into 13 lines
+        assert_eq!(lines.len(), 13);
+        assert!(lines.iter().all(|line| line.chars().count() == 25));
+        assert_eq!(lines[0].trim(), "");
+        // The finder starts on the second line, after the quiet zone
+        assert!(lines[1].starts_with("  █▀▀▀▀▀█"));
+        assert_eq!(text_dimensions(&matrix(2), &OutputFormat::Text).height, 13);
+    }
+
+    #[test]
+    fn test_ascii_uses_two_columns_per_module() {
+        let text = render_text(&matrix(0), &OutputFormat::Ascii, &QrColors::default()).unwrap();
+        let first = text.lines().next().unwrap();
+
+        assert_eq!(text.lines().count(), 21);
+        assert!(first.starts_with("##############  "));
+        assert!(text.is_ascii());
+        assert_eq!(text_dimensions(&matrix(0), &OutputFormat::Ascii).width, 42);
+    }
+
+    #[test]
+    fn test_ansi_uses_requested_colors() {
+        let colors = QrColors {
+            foreground: "#1A237E".to_string(),
+            background: "#FFF8E1".to_string(),
+            ..Default::default()
+        };
+        let text = render_text(&matrix(1), &OutputFormat::Ansi, &colors).unwrap();
+
+        // Quiet zone above the finder, then finder over quiet zone
+        assert!(text.starts_with("\x1b[38;2;255;248;225;48;2;255;248;225m▀"));
+        assert!(text.contains("\x1b[38;2;255;248;225;48;2;26;35;126m▀"));
+        assert!(text.lines().all(|line| line.ends_with("\x1b[0m")));
+    }
+}